@@ -89,6 +89,9 @@ void printType(WITSession* s, const WITTypeDef* td, int indent)
     const char* name;
     CHECK(wit_typedef_name_get(s, td, &name));
 
+    const char* typeName;
+    CHECK(wit_typedef_typename_get(s, td, &typeName));
+
     WITType ty;
     CHECK(wit_typedef_type_get(s, td, &ty));
 
@@ -100,9 +103,12 @@ void printType(WITSession* s, const WITTypeDef* td, int indent)
 
     printf("[name=%s, type=%s, size=%d, align=%d", 
         name, witType2Str(ty), size, align);
+    if (*typeName)
+        printf(", typename=%s", typeName);
     switch (ty)
     {
         case WITType::Variant:
+        case WITType::Union:
             {
                 uint8_t tag;
                 CHECK(wit_variant_tag_get(s, td, &tag));
@@ -110,6 +116,36 @@ void printType(WITSession* s, const WITTypeDef* td, int indent)
             }
             break;
 
+        case WITType::Flags:
+            {
+                size_t count;
+                CHECK(wit_flags_count_get(s, td, &count));
+                printf(", flags=[");
+                for (size_t i = 0; i < count; ++i)
+                {
+                    const char* flag;
+                    CHECK(wit_flags_name_get_by_index(s, td, i, &flag));
+                    printf("%s%s", i > 0 ? ", " : "", flag);
+                }
+                printf("]");
+            }
+            break;
+
+        case WITType::Enum:
+            {
+                size_t count;
+                CHECK(wit_enum_case_count_get(s, td, &count));
+                printf(", cases=[");
+                for (size_t i = 0; i < count; ++i)
+                {
+                    const char* name;
+                    CHECK(wit_enum_case_name_get_by_index(s, td, i, &name));
+                    printf("%s%s", i > 0 ? ", " : "", name);
+                }
+                printf("]");
+            }
+            break;
+
         default:
             break;
     }
@@ -151,6 +187,23 @@ void printType(WITSession* s, const WITTypeDef* td, int indent)
             }
             break;
 
+        case WITType::Union:
+            {
+                WITUnionCaseIter* ui;
+                CHECK(wit_union_case_walk(s, td, &ui));
+
+                while (!wit_union_case_iter_off(s, ui))
+                {
+                    const WITTypeDef* uty;
+                    CHECK(wit_union_case_iter_at(s, ui, &uty));
+
+                    printType(s, uty, indent + 1);
+                    CHECK(wit_union_case_iter_next(s, ui));
+                }
+                wit_union_case_iter_delete(s, ui);
+            }
+            break;
+
         case WITType::Expected:
             {
                 const WITTypeDef* okTy;
@@ -166,7 +219,7 @@ void printType(WITSession* s, const WITTypeDef* td, int indent)
         case WITType::Option:
             {
                 const WITTypeDef* ty;
-                CHECK(wit_option_typedef_get(s, td, &ty));
+                CHECK(wit_option_some_typedef_get(s, td, &ty));
                 printType(s, ty, indent + 1);
             }
             break;
@@ -179,6 +232,19 @@ void printType(WITSession* s, const WITTypeDef* td, int indent)
             }
             break;
 
+        case WITType::Tuple:
+            {
+                size_t count;
+                CHECK(wit_tuple_elem_count_get(s, td, &count));
+                for (size_t i = 0; i < count; ++i)
+                {
+                    const WITTypeDef* ety;
+                    CHECK(wit_tuple_elem_typedef_get_by_index(s, td, i, &ety));
+                    printType(s, ety, indent + 1);
+                }
+            }
+            break;
+
         case WITType::Type:
             {
                 const WITTypeDef* ty;
@@ -246,10 +312,18 @@ void printFunc(WITSession* s, const WITFunction* func)
     }
     wit_typedef_iter_delete(s, tdIter);
 
-    printf("Result:\n");
-    const WITTypeDef* resTd;
-    CHECK(wit_func_result_get(s, func, &resTd));
-    printType(s, resTd, 1);
+    printf("Results:\n");
+    CHECK(wit_func_result_walk(s, func, &tdIter));
+    while (!wit_typedef_iter_off(s, tdIter))
+    {
+        const WITTypeDef* td;
+        CHECK(wit_typedef_iter_at(s, tdIter, &td));
+
+        printType(s, td, 1);
+
+        CHECK(wit_typedef_iter_next(s, tdIter));
+    }
+    wit_typedef_iter_delete(s, tdIter);
 }
 
 char *readWIT(const char *path, long *len)
@@ -306,19 +380,37 @@ int main(int argc, char *argv[])
 
     if (argc == 2)
     {
-        size_t count;
-        CHECK(wit_func_count_get(s, wit, &count));
-
         printf("Functions:\n");
-        for (int i = 0; i < count; ++i)
+        WITFuncIter* fi;
+        CHECK(wit_func_walk(s, wit, &fi));
+        while (!wit_func_iter_off(s, fi))
         {
             const WITFunction* func;
-            CHECK(wit_func_get_by_index(s, wit, i, &func));
+            CHECK(wit_func_iter_at(s, fi, &func));
 
             const char* name;
             CHECK(wit_func_name_get(s, func, &name));
 
             printf("  %s\n", name);
+            CHECK(wit_func_iter_next(s, fi));
+        }
+        wit_func_iter_delete(s, fi);
+
+        size_t count;
+        CHECK(wit_type_count_get(s, wit, &count));
+
+        printf("Types:\n");
+        for (int i = 0; i < count; ++i)
+        {
+            const WITTypeDef* td;
+            CHECK(wit_type_get_by_index(s, wit, i, &td));
+
+            const char* name;
+            CHECK(wit_typedef_typename_get(s, td, &name));
+
+            // Skip anonymous types like `list<u8>`.
+            if (*name)
+                printf("  %s\n", name);
         }
     }
     else if (argc == 3)
@@ -0,0 +1,80 @@
+// Prints the functions and types of a WIT file using the C++ wrapper.
+//
+// Built by `make wrapper`, so a change to the C API that the wrapper hasn't
+// caught up with fails the build.
+
+#include <iostream>
+#include "to-wit.hpp"
+
+static void printType(const towit::TypeDef &td, const char *indent)
+{
+    std::cout << indent << td.name() << ": " << td.type_name()
+              << " (size " << td.size() << ", align " << td.align() << ")\n";
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc != 2)
+    {
+        std::cerr << "Usage: " << argv[0] << " <wit-file>\n";
+        return 1;
+    }
+
+    try
+    {
+        towit::Session s;
+        towit::Wit wit = s.parse_file(argv[1]);
+        std::cout << "Interface " << wit.name() << "\n";
+
+        std::cout << "Functions:\n";
+        for (auto func : wit.functions())
+        {
+            std::cout << "  " << func.name() << " " << func.core_signature() << "\n";
+            for (auto param : func.params())
+                printType(param, "    ");
+            if (!func.result().is_unit())
+                printType(func.result(), "    -> ");
+        }
+
+        std::cout << "Types:\n";
+        for (auto td : wit.types())
+        {
+            if (td.type_name().empty())
+                continue;
+            printType(td, "  ");
+            switch (td.type())
+            {
+                case WITType::Record:
+                    for (auto field : td.fields())
+                        printType(field, "    ");
+                    break;
+                case WITType::Variant:
+                    for (auto c : td.cases())
+                        printType(c, "    ");
+                    break;
+                case WITType::Enum:
+                    for (auto name : td.enum_cases())
+                        std::cout << "    " << name << "\n";
+                    break;
+                case WITType::Flags:
+                    for (auto name : td.flags())
+                        std::cout << "    " << name << "\n";
+                    break;
+                default:
+                    break;
+            }
+        }
+
+        for (const auto &warning : s.warnings())
+            std::cerr << "warning: " << warning << "\n";
+    }
+    catch (const towit::Error &e)
+    {
+        std::cerr << e.what() << "\n";
+        if (e.location())
+            std::cerr << "at " << e.location()->file << ":" << e.location()->line
+                      << ":" << e.location()->column << "\n";
+        return 1;
+    }
+    return 0;
+}
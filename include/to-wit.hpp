@@ -0,0 +1,762 @@
+#pragma once
+
+// Header-only C++17 wrapper over the to-wit C API in to-wit.h.
+//
+// Objects the library allocates (sessions, parsed interfaces, collections,
+// diffs, buffers) are owned by move-only classes that free them on
+// destruction.  Functions, types, resources and changes are lightweight
+// views that stay valid as long as the interface or diff they came from.
+// Every view remembers the session it was obtained through, which must
+// outlive it.  Errors are thrown as towit::Error carrying the session's
+// error message, and for parse errors where in the source they occurred.
+//
+// Only the commonly used parts of the API are wrapped; raw() and session()
+// give the underlying handles for calling anything else directly.
+
+#include <cstddef>
+#include <cstdint>
+#include <memory>
+#include <optional>
+#include <stdexcept>
+#include <string>
+#include <string_view>
+#include <utility>
+#include <vector>
+
+#include "to-wit.h"
+
+namespace towit {
+
+// See WITErrorLocation.
+struct Location {
+    std::string file;
+    size_t line;
+    size_t column;
+    size_t start;
+    size_t end;
+};
+
+class Error : public std::runtime_error {
+public:
+    explicit Error(const std::string &msg, std::optional<Location> location = std::nullopt)
+        : std::runtime_error(msg), location_(std::move(location)) {}
+
+    const std::optional<Location> &location() const { return location_; }
+
+private:
+    std::optional<Location> location_;
+};
+
+namespace detail {
+
+// Throws the session's error, clearing it, if `ok` is false.
+inline void check(WITSession *s, bool ok)
+{
+    if (ok)
+        return;
+    const char *msg = wit_error_get(s);
+    std::string text = msg ? msg : "Unknown error";
+    std::optional<Location> location;
+    WITErrorLocation loc;
+    if (wit_error_location_get(s, &loc))
+        location = Location{loc.file, loc.line, loc.column, loc.start, loc.end};
+    wit_error_clear(s);
+    throw Error(text, std::move(location));
+}
+
+inline std::string str(const char *p)
+{
+    return p ? std::string(p) : std::string();
+}
+
+// Runs an entry point following the (out, cap, len) buffer protocol twice:
+// once to learn the length, then with a buffer large enough for the text
+// and its terminator.
+template <typename F>
+std::string text(WITSession *s, F &&f)
+{
+    size_t len = 0;
+    check(s, f(nullptr, 0, &len));
+    std::vector<char> buf(len + 1);
+    check(s, f(buf.data(), buf.size(), &len));
+    return std::string(buf.data(), len);
+}
+
+// The delete functions ignore the session, so owners don't need to keep one.
+struct WitDeleter {
+    void operator()(WIT *p) const { wit_delete(nullptr, p); }
+};
+struct CollectionDeleter {
+    void operator()(WITCollection *p) const { wit_collection_delete(nullptr, p); }
+};
+struct DiffDeleter {
+    void operator()(WITDiff *p) const { wit_diff_delete(nullptr, p); }
+};
+struct BufferDeleter {
+    void operator()(WITBuffer *p) const { wit_buffer_delete(nullptr, p); }
+};
+
+} // namespace detail
+
+// A random-access range over the `count` items an indexed getter returns,
+// so that `for (auto f : wit.functions())` works.
+template <typename T, typename Get>
+class Range {
+public:
+    Range(size_t count, Get get) : count_(count), get_(std::move(get)) {}
+
+    class iterator {
+    public:
+        iterator(const Range *r, size_t i) : r_(r), i_(i) {}
+        T operator*() const { return r_->get_(i_); }
+        iterator &operator++() { ++i_; return *this; }
+        bool operator==(const iterator &o) const { return i_ == o.i_; }
+        bool operator!=(const iterator &o) const { return i_ != o.i_; }
+
+    private:
+        const Range *r_;
+        size_t i_;
+    };
+
+    iterator begin() const { return iterator(this, 0); }
+    iterator end() const { return iterator(this, count_); }
+    size_t size() const { return count_; }
+    bool empty() const { return count_ == 0; }
+    T operator[](size_t i) const { return get_(i); }
+
+private:
+    size_t count_;
+    Get get_;
+};
+
+template <typename T, typename Get>
+Range<T, Get> make_range(size_t count, Get get)
+{
+    return Range<T, Get>(count, std::move(get));
+}
+
+class TypeDef {
+private:
+    using StrGet = bool (*)(WITSession *, const WITTypeDef *, const char **);
+    using SizeGet = bool (*)(WITSession *, const WITTypeDef *, size_t *);
+    using ChildGet = bool (*)(WITSession *, const WITTypeDef *, const WITTypeDef **);
+    using IndexGet = bool (*)(WITSession *, const WITTypeDef *, size_t, const WITTypeDef **);
+    using NameGet = bool (*)(WITSession *, const WITTypeDef *, size_t, const char **);
+
+    std::string get_str(StrGet get) const
+    {
+        const char *res;
+        detail::check(s_, get(s_, td_, &res));
+        return detail::str(res);
+    }
+
+    size_t get_size(SizeGet get) const
+    {
+        size_t res;
+        detail::check(s_, get(s_, td_, &res));
+        return res;
+    }
+
+    TypeDef child(ChildGet get) const
+    {
+        const WITTypeDef *res;
+        detail::check(s_, get(s_, td_, &res));
+        return TypeDef(s_, res);
+    }
+
+    auto children(SizeGet count, IndexGet get) const
+    {
+        WITSession *s = s_;
+        const WITTypeDef *td = td_;
+        return make_range<TypeDef>(get_size(count), [s, td, get](size_t i) {
+            const WITTypeDef *res;
+            detail::check(s, get(s, td, i, &res));
+            return TypeDef(s, res);
+        });
+    }
+
+    auto names(SizeGet count, NameGet get) const
+    {
+        WITSession *s = s_;
+        const WITTypeDef *td = td_;
+        return make_range<std::string>(get_size(count), [s, td, get](size_t i) {
+            const char *res;
+            detail::check(s, get(s, td, i, &res));
+            return detail::str(res);
+        });
+    }
+
+public:
+    TypeDef(WITSession *s, const WITTypeDef *td) : s_(s), td_(td) {}
+
+    const WITTypeDef *raw() const { return td_; }
+    WITSession *session() const { return s_; }
+
+    std::string name() const { return get_str(wit_typedef_name_get); }
+    std::string type_name() const { return get_str(wit_typedef_typename_get); }
+    std::string docs() const { return get_str(wit_typedef_docs_get); }
+
+    WITType type() const
+    {
+        WITType res;
+        detail::check(s_, wit_typedef_type_get(s_, td_, &res));
+        return res;
+    }
+
+    size_t size() const { return get_size(wit_typedef_size_get); }
+    size_t align() const { return get_size(wit_typedef_align_get); }
+
+    bool is_unit() const
+    {
+        bool res;
+        detail::check(s_, wit_typedef_is_unit(s_, td_, &res));
+        return res;
+    }
+
+    auto fields() const
+    {
+        return children(wit_record_field_count_get, wit_record_field_get_by_index);
+    }
+
+    auto cases() const
+    {
+        return children(wit_variant_case_count_get, wit_variant_case_get_by_index);
+    }
+
+    auto elements() const
+    {
+        return children(wit_tuple_elem_count_get, wit_tuple_elem_typedef_get_by_index);
+    }
+
+    auto enum_cases() const
+    {
+        return names(wit_enum_case_count_get, wit_enum_case_name_get_by_index);
+    }
+
+    auto flags() const
+    {
+        return names(wit_flags_count_get, wit_flags_name_get_by_index);
+    }
+
+    TypeDef list_elem() const { return child(wit_list_elem_typedef_get); }
+    TypeDef option_some() const { return child(wit_option_some_typedef_get); }
+    TypeDef expected_ok() const { return child(wit_expected_ok_typedef_get); }
+    TypeDef expected_err() const { return child(wit_expected_err_typedef_get); }
+    TypeDef aliased() const { return child(wit_type_aliased_typedef_get); }
+
+    std::string to_json_schema() const
+    {
+        return detail::text(s_, [&](char *out, size_t cap, size_t *len) {
+            return wit_typedef_to_json_schema(s_, td_, out, cap, len);
+        });
+    }
+
+private:
+    WITSession *s_;
+    const WITTypeDef *td_;
+};
+
+class Function {
+public:
+    Function(WITSession *s, const WITFunction *f) : s_(s), f_(f) {}
+
+    const WITFunction *raw() const { return f_; }
+    WITSession *session() const { return s_; }
+
+    std::string name() const { return get_str(wit_func_name_get); }
+    std::string docs() const { return get_str(wit_func_docs_get); }
+    std::string core_export_name() const { return get_str(wit_func_core_export_name_get); }
+    std::string core_signature() const { return get_str(wit_func_core_sig_string_get); }
+
+    WITFuncKind kind() const
+    {
+        WITFuncKind res;
+        detail::check(s_, wit_func_kind_get(s_, f_, &res));
+        return res;
+    }
+
+    bool needs_post_return() const
+    {
+        bool res;
+        detail::check(s_, wit_func_needs_post_return(s_, f_, &res));
+        return res;
+    }
+
+    std::string post_return_name() const { return get_str(wit_func_post_return_name_get); }
+
+    auto params() const
+    {
+        size_t count;
+        detail::check(s_, wit_func_param_count_get(s_, f_, &count));
+        WITSession *s = s_;
+        const WITFunction *f = f_;
+        return make_range<TypeDef>(count, [s, f](size_t i) {
+            const WITTypeDef *res;
+            detail::check(s, wit_func_param_get_by_index(s, f, i, &res));
+            return TypeDef(s, res);
+        });
+    }
+
+    TypeDef result() const
+    {
+        const WITTypeDef *res;
+        detail::check(s_, wit_func_result_get(s_, f_, &res));
+        return TypeDef(s_, res);
+    }
+
+private:
+    using StrGet = bool (*)(WITSession *, const WITFunction *, const char **);
+
+    std::string get_str(StrGet get) const
+    {
+        const char *res;
+        detail::check(s_, get(s_, f_, &res));
+        return detail::str(res);
+    }
+
+    WITSession *s_;
+    const WITFunction *f_;
+};
+
+class Resource {
+public:
+    Resource(WITSession *s, const WITResource *r) : s_(s), r_(r) {}
+
+    const WITResource *raw() const { return r_; }
+
+    std::string name() const
+    {
+        const char *res;
+        detail::check(s_, wit_resource_name_get(s_, r_, &res));
+        return detail::str(res);
+    }
+
+    auto functions() const
+    {
+        size_t count;
+        detail::check(s_, wit_resource_func_count_get(s_, r_, &count));
+        WITSession *s = s_;
+        const WITResource *r = r_;
+        return make_range<Function>(count, [s, r](size_t i) {
+            const WITFunction *res;
+            detail::check(s, wit_resource_func_get_by_index(s, r, i, &res));
+            return Function(s, res);
+        });
+    }
+
+private:
+    WITSession *s_;
+    const WITResource *r_;
+};
+
+struct Change {
+    WITChangeKind kind;
+    WITItemKind item;
+    std::string name;
+    std::string detail;
+};
+
+class Diff {
+public:
+    Diff(WITSession *s, WITDiff *d) : s_(s), d_(d) {}
+
+    const WITDiff *raw() const { return d_.get(); }
+
+    auto changes() const
+    {
+        size_t count;
+        detail::check(s_, wit_diff_count_get(s_, d_.get(), &count));
+        WITSession *s = s_;
+        const WITDiff *d = d_.get();
+        return make_range<Change>(count, [s, d](size_t i) {
+            const WITChange *c;
+            detail::check(s, wit_diff_get_by_index(s, d, i, &c));
+            Change res;
+            const char *name, *text;
+            detail::check(s, wit_change_kind_get(s, c, &res.kind));
+            detail::check(s, wit_change_item_get(s, c, &res.item));
+            detail::check(s, wit_change_name_get(s, c, &name));
+            detail::check(s, wit_change_detail_get(s, c, &text));
+            res.name = detail::str(name);
+            res.detail = detail::str(text);
+            return res;
+        });
+    }
+
+private:
+    WITSession *s_;
+    std::unique_ptr<WITDiff, detail::DiffDeleter> d_;
+};
+
+class Wit;
+
+// A parsed interface that is not owned, e.g. one in a Collection.
+class WitView {
+public:
+    WitView(WITSession *s, const WIT *w) : s_(s), w_(w) {}
+
+    const WIT *raw() const { return w_; }
+    WITSession *session() const { return s_; }
+
+    std::string name() const
+    {
+        const char *res;
+        detail::check(s_, wit_name_get(s_, w_, &res));
+        return detail::str(res);
+    }
+
+    auto functions() const
+    {
+        WITSession *s = s_;
+        const WIT *w = w_;
+        return make_range<Function>(count(wit_func_count_get), [s, w](size_t i) {
+            const WITFunction *res;
+            detail::check(s, wit_func_get_by_index(s, w, i, &res));
+            return Function(s, res);
+        });
+    }
+
+    Function function(const std::string &name) const
+    {
+        const WITFunction *res;
+        detail::check(s_, wit_func_get_by_name(s_, w_, name.c_str(), &res));
+        return Function(s_, res);
+    }
+
+    auto types() const
+    {
+        WITSession *s = s_;
+        const WIT *w = w_;
+        return make_range<TypeDef>(count(wit_type_count_get), [s, w](size_t i) {
+            const WITTypeDef *res;
+            detail::check(s, wit_type_get_by_index(s, w, i, &res));
+            return TypeDef(s, res);
+        });
+    }
+
+    auto resources() const
+    {
+        WITSession *s = s_;
+        const WIT *w = w_;
+        return make_range<Resource>(count(wit_resource_count_get), [s, w](size_t i) {
+            const WITResource *res;
+            detail::check(s, wit_resource_get_by_index(s, w, i, &res));
+            return Resource(s, res);
+        });
+    }
+
+    std::string to_json() const { return emit(wit_to_json); }
+    std::string abi_to_json() const { return emit(wit_abi_to_json); }
+    std::string markdown() const { return emit(wit_emit_markdown); }
+    std::string dot() const { return emit(wit_emit_dot); }
+    std::string typescript() const { return emit(wit_emit_typescript); }
+    std::string layout_report() const { return emit(wit_layout_report); }
+
+    std::string c_header(const char *prefix = nullptr, const char *guard = nullptr) const
+    {
+        WITHeaderOptions opts{prefix, guard};
+        return detail::text(s_, [&](char *out, size_t cap, size_t *len) {
+            return wit_emit_c_header(s_, w_, &opts, out, cap, len);
+        });
+    }
+
+    std::string c_guest(WITGuestFile file) const
+    {
+        return detail::text(s_, [&](char *out, size_t cap, size_t *len) {
+            return wit_emit_c_guest(s_, w_, file, out, cap, len);
+        });
+    }
+
+    std::string modern_wit(const char *package = nullptr) const { return emit(wit_to_modern_wit, package); }
+    std::string create_function(const std::string &module) const { return emit(wit_emit_create_function, module.c_str()); }
+    std::string proto(const char *package = nullptr) const { return emit(wit_emit_proto, package); }
+    std::string go(const char *package = nullptr) const { return emit(wit_emit_go, package); }
+
+    std::vector<uint8_t> snapshot() const
+    {
+        size_t len = 0;
+        detail::check(s_, wit_snapshot_save(s_, w_, nullptr, 0, &len));
+        std::vector<uint8_t> res(len);
+        detail::check(s_, wit_snapshot_save(s_, w_, res.data(), res.size(), &len));
+        return res;
+    }
+
+    Diff diff(const WitView &newer) const
+    {
+        WITDiff *res;
+        detail::check(s_, wit_diff(s_, w_, newer.w_, &res));
+        return Diff(s_, res);
+    }
+
+    WITCompat compat(const WitView &newer) const
+    {
+        WITCompat res;
+        detail::check(s_, wit_compat_check(s_, w_, newer.w_, &res));
+        return res;
+    }
+
+    void walk(const WITWalker &walker) const
+    {
+        detail::check(s_, wit_walk(s_, w_, &walker));
+    }
+
+    inline Wit project(const std::vector<std::string> &names) const;
+    inline Wit merge(const WitView &other) const;
+
+protected:
+    using CountGet = bool (*)(WITSession *, const WIT *, size_t *);
+    using Emit = bool (*)(WITSession *, const WIT *, char *, size_t, size_t *);
+    using EmitNamed = bool (*)(WITSession *, const WIT *, const char *, char *, size_t, size_t *);
+
+    size_t count(CountGet get) const
+    {
+        size_t res;
+        detail::check(s_, get(s_, w_, &res));
+        return res;
+    }
+
+    std::string emit(Emit f) const
+    {
+        return detail::text(s_, [&](char *out, size_t cap, size_t *len) {
+            return f(s_, w_, out, cap, len);
+        });
+    }
+
+    std::string emit(EmitNamed f, const char *name) const
+    {
+        return detail::text(s_, [&](char *out, size_t cap, size_t *len) {
+            return f(s_, w_, name, out, cap, len);
+        });
+    }
+
+    WITSession *s_;
+    const WIT *w_;
+};
+
+// An owned parsed interface.
+class Wit : public WitView {
+public:
+    Wit(WITSession *s, WIT *w) : WitView(s, w), owner_(w) {}
+
+private:
+    std::unique_ptr<WIT, detail::WitDeleter> owner_;
+};
+
+inline Wit WitView::project(const std::vector<std::string> &names) const
+{
+    std::vector<const char *> ptrs;
+    for (const auto &n : names)
+        ptrs.push_back(n.c_str());
+    WIT *res;
+    detail::check(s_, wit_project(s_, w_, ptrs.data(), ptrs.size(), &res));
+    return Wit(s_, res);
+}
+
+inline Wit WitView::merge(const WitView &other) const
+{
+    WIT *res;
+    detail::check(s_, wit_merge(s_, w_, other.w_, &res));
+    return Wit(s_, res);
+}
+
+// The interfaces parsed from a multi-interface file or a component.
+class Collection {
+public:
+    Collection(WITSession *s, WITCollection *c) : s_(s), c_(c) {}
+
+    const WITCollection *raw() const { return c_.get(); }
+
+    size_t size() const
+    {
+        size_t res;
+        detail::check(s_, wit_collection_count_get(s_, c_.get(), &res));
+        return res;
+    }
+
+    WitView operator[](size_t i) const
+    {
+        const WIT *res;
+        detail::check(s_, wit_collection_get_by_index(s_, c_.get(), i, &res));
+        return WitView(s_, res);
+    }
+
+    auto interfaces() const
+    {
+        WITSession *s = s_;
+        const WITCollection *c = c_.get();
+        return make_range<WitView>(size(), [s, c](size_t i) {
+            const WIT *res;
+            detail::check(s, wit_collection_get_by_index(s, c, i, &res));
+            return WitView(s, res);
+        });
+    }
+
+private:
+    WITSession *s_;
+    std::unique_ptr<WITCollection, detail::CollectionDeleter> c_;
+};
+
+// Builds a buffer of guest values with wit_buffer_append_typed.
+class Buffer {
+public:
+    explicit Buffer(WITSession *s) : s_(s)
+    {
+        WITBuffer *res;
+        detail::check(s_, wit_buffer_new(s_, &res));
+        b_.reset(res);
+    }
+
+    WITBuffer *raw() const { return b_.get(); }
+
+    void append(const TypeDef &td, const void *data, size_t len)
+    {
+        detail::check(s_, wit_buffer_append_typed(s_, b_.get(), td.raw(), static_cast<const uint8_t *>(data), len));
+    }
+
+    size_t offset_of_last() const
+    {
+        size_t res;
+        detail::check(s_, wit_buffer_offset_of_last(s_, b_.get(), &res));
+        return res;
+    }
+
+    // The returned bytes belong to the buffer.
+    std::pair<const uint8_t *, size_t> finish(size_t *align = nullptr)
+    {
+        const uint8_t *data;
+        size_t len, al;
+        detail::check(s_, wit_buffer_finish(s_, b_.get(), &data, &len, &al));
+        if (align)
+            *align = al;
+        return {data, len};
+    }
+
+private:
+    WITSession *s_;
+    std::unique_ptr<WITBuffer, detail::BufferDeleter> b_;
+};
+
+// Owns a WITSession: parse options, renames, resolver and the last error.
+class Session {
+public:
+    Session() : s_(wit_session_new()) {}
+    ~Session() { wit_session_delete(s_); }
+
+    Session(const Session &) = delete;
+    Session &operator=(const Session &) = delete;
+    Session(Session &&o) noexcept : s_(std::exchange(o.s_, nullptr)) {}
+    Session &operator=(Session &&o) noexcept
+    {
+        std::swap(s_, o.s_);
+        return *this;
+    }
+
+    WITSession *raw() const { return s_; }
+
+    void set_string_encoding(WITStringEncoding encoding) { wit_session_set_string_encoding(s_, encoding); }
+    void set_memory64(bool memory64) { wit_session_set_memory64(s_, memory64); }
+    void set_max_flat(size_t params, size_t results) { wit_session_set_max_flat(s_, params, results); }
+    void set_resolver(WITResolver cb, void *user_data) { wit_session_set_resolver(s_, cb, user_data); }
+    void set_logger(WITLogger cb, void *user_data, WITLogLevel level) { wit_session_set_logger(s_, cb, user_data, level); }
+
+    void set_rename(const std::string &from, const std::string &to)
+    {
+        detail::check(s_, wit_session_set_rename(s_, from.c_str(), to.c_str()));
+    }
+
+    void remove_rename(const std::string &from)
+    {
+        detail::check(s_, wit_session_set_rename(s_, from.c_str(), nullptr));
+    }
+
+    void clear_renames() { wit_session_clear_renames(s_); }
+
+    // The warnings from the last parse.
+    std::vector<std::string> warnings() const
+    {
+        size_t count;
+        detail::check(s_, wit_warning_count_get(s_, &count));
+        std::vector<std::string> res;
+        for (size_t i = 0; i < count; i++) {
+            const char *w;
+            detail::check(s_, wit_warning_get(s_, i, &w));
+            res.push_back(detail::str(w));
+        }
+        return res;
+    }
+
+    Wit parse(std::string_view content) const
+    {
+        WIT *res;
+        detail::check(s_, wit_parse(s_, data(content), content.size(), &res));
+        return Wit(s_, res);
+    }
+
+    Wit parse_named(const std::string &name, std::string_view content) const
+    {
+        WIT *res;
+        detail::check(s_, wit_parse_named(s_, name.c_str(), data(content), content.size(), &res));
+        return Wit(s_, res);
+    }
+
+    Wit parse_file(const std::string &path) const
+    {
+        WIT *res;
+        detail::check(s_, wit_parse_file(s_, path.c_str(), &res));
+        return Wit(s_, res);
+    }
+
+    // Parses several named documents, which may `use` each other by name.
+    Collection parse_all(const std::vector<std::pair<std::string, std::string>> &docs) const
+    {
+        std::vector<const char *> names;
+        std::vector<const uint8_t *> contents;
+        std::vector<size_t> lens;
+        for (const auto &[name, content] : docs) {
+            names.push_back(name.c_str());
+            contents.push_back(data(content));
+            lens.push_back(content.size());
+        }
+        WITCollection *res;
+        detail::check(s_, wit_parse_all(s_, names.data(), contents.data(), lens.data(), docs.size(), &res));
+        return Collection(s_, res);
+    }
+
+    // Parses the WIT in the custom sections named in `sections` (see
+    // wit_parse_module).
+    Collection parse_module(const std::vector<uint8_t> &module, const std::vector<std::string> &sections) const
+    {
+        std::vector<const char *> names;
+        for (const auto &name : sections)
+            names.push_back(name.c_str());
+        WITCollection *res;
+        detail::check(s_, wit_parse_module(s_, module.data(), module.size(), names.data(), names.size(), &res));
+        return Collection(s_, res);
+    }
+
+    Wit snapshot_load(const std::vector<uint8_t> &snapshot) const
+    {
+        WIT *res;
+        detail::check(s_, wit_snapshot_load(s_, snapshot.data(), snapshot.size(), &res));
+        return Wit(s_, res);
+    }
+
+    Wit snapshot_load_file(const std::string &path) const
+    {
+        WIT *res;
+        detail::check(s_, wit_snapshot_load_file(s_, path.c_str(), &res));
+        return Wit(s_, res);
+    }
+
+    Buffer buffer() const { return Buffer(s_); }
+
+private:
+    static const uint8_t *data(std::string_view s)
+    {
+        return reinterpret_cast<const uint8_t *>(s.data());
+    }
+
+    WITSession *s_;
+};
+
+} // namespace towit
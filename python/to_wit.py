@@ -0,0 +1,158 @@
+"""Thin cffi binding for libto_wit.
+
+The declarations are generated from the library by `make python`, which
+puts them in `target/python/to_wit_cdef.h` next to a copy of this file, so
+the binding never needs updating by hand.  `lib` exposes every `wit_*`
+entry point as is; `Session` adds what most callers need on top: errors
+raised as `Error`, out-parameters returned, text outputs decoded and parsed
+interfaces freed when collected.
+
+    import to_wit
+    s = to_wit.Session()
+    wit = s.parse_file("example/example.wit")
+    for func in s.functions(wit):
+        print(s.get("wit_func_name_get", func, "const char *"))
+
+The library is loaded from `$TO_WIT_LIB` if set, otherwise from
+`target/release` (or `target/debug`) relative to this file.
+"""
+
+import os
+import sys
+
+from cffi import FFI
+
+_HERE = os.path.dirname(os.path.abspath(__file__))
+
+ffi = FFI()
+with open(os.environ.get("TO_WIT_CDEF", os.path.join(_HERE, "to_wit_cdef.h"))) as f:
+    ffi.cdef(f.read())
+
+
+def _find_lib():
+    if "TO_WIT_LIB" in os.environ:
+        return os.environ["TO_WIT_LIB"]
+    name = {"darwin": "libto_wit.dylib", "win32": "to_wit.dll"}.get(sys.platform, "libto_wit.so")
+    for build in ("release", "debug"):
+        path = os.path.join(_HERE, "..", build, name)
+        if os.path.exists(path):
+            return path
+    return name
+
+
+lib = ffi.dlopen(_find_lib())
+
+
+class Error(Exception):
+    """A failed call.  `location` is None, or for parse errors a dict with
+    the `file`, `line`, `column`, `start` and `end` of WITErrorLocation."""
+
+    def __init__(self, msg, location=None):
+        super().__init__(msg)
+        self.location = location
+
+
+class Session:
+    """Owns a WITSession and turns failed calls into `Error`."""
+
+    def __init__(self):
+        self.s = ffi.gc(lib.wit_session_new(), lib.wit_session_delete)
+
+    def set_logger(self, fn, level):
+        """Passes the library's diagnostics of `level` (a `lib.WITLogLevel_*`
+        value) and more severe to `fn(level, msg)`; None removes the logger."""
+        if fn is None:
+            self._logger = None
+            lib.wit_session_set_logger(self.s, ffi.NULL, ffi.NULL, level)
+            return
+
+        @ffi.callback("void(void *, WITLogLevel, const char *)")
+        def logger(_, lvl, msg):
+            fn(lvl, ffi.string(msg).decode())
+
+        self._logger = logger  # keeps the callback alive
+        lib.wit_session_set_logger(self.s, logger, ffi.NULL, level)
+
+    def check(self, ok):
+        if not ok:
+            msg = lib.wit_error_get(self.s)
+            text = ffi.string(msg).decode() if msg != ffi.NULL else "Unknown error"
+            location = None
+            loc = ffi.new("WITErrorLocation *")
+            if lib.wit_error_location_get(self.s, loc):
+                location = {
+                    "file": ffi.string(loc.file).decode(),
+                    "line": loc.line,
+                    "column": loc.column,
+                    "start": loc.start,
+                    "end": loc.end,
+                }
+            lib.wit_error_clear(self.s)
+            raise Error(text, location)
+
+    def call(self, name, *args):
+        """Calls `wit_<...>(session, *args)`, raising if it fails."""
+        self.check(getattr(lib, name)(self.s, *args))
+
+    def get(self, name, obj, ctype, *args):
+        """Calls a getter with a single out-parameter of type `ctype` and
+        returns its value; strings are returned as `str`."""
+        res = ffi.new(ctype + " *")
+        self.call(name, obj, *args, res)
+        if ctype == "const char *":
+            return ffi.string(res[0]).decode() if res[0] != ffi.NULL else None
+        return res[0]
+
+    def text(self, name, *args):
+        """Calls an entry point following the (out, cap, len) buffer
+        protocol and returns the text."""
+        size = ffi.new("size_t *")
+        self.call(name, *args, ffi.NULL, 0, size)
+        out = ffi.new("char[]", size[0] + 1)
+        self.call(name, *args, out, size[0] + 1, size)
+        return ffi.string(out, size[0]).decode()
+
+    def warnings(self):
+        """Returns the warnings from the last parse."""
+        count = ffi.new("size_t *")
+        self.call("wit_warning_count_get", count)
+        res = ffi.new("const char **")
+        warnings = []
+        for i in range(count[0]):
+            self.call("wit_warning_get", i, res)
+            warnings.append(ffi.string(res[0]).decode())
+        return warnings
+
+    def _owned(self, ptr):
+        return ffi.gc(ptr, lambda p: lib.wit_delete(ffi.NULL, p))
+
+    def parse(self, content):
+        if isinstance(content, str):
+            content = content.encode()
+        res = ffi.new("WIT **")
+        self.call("wit_parse", content, len(content), res)
+        return self._owned(res[0])
+
+    def parse_named(self, name, content):
+        if isinstance(content, str):
+            content = content.encode()
+        res = ffi.new("WIT **")
+        self.call("wit_parse_named", name.encode(), content, len(content), res)
+        return self._owned(res[0])
+
+    def parse_file(self, path):
+        res = ffi.new("WIT **")
+        self.call("wit_parse_file", os.fsencode(path), res)
+        return self._owned(res[0])
+
+    def functions(self, wit):
+        for i in range(self.get("wit_func_count_get", wit, "size_t")):
+            yield self.get("wit_func_get_by_index", wit, "const WITFunction *", i)
+
+    def types(self, wit):
+        for i in range(self.get("wit_type_count_get", wit, "size_t")):
+            yield self.get("wit_type_get_by_index", wit, "const WITTypeDef *", i)
+
+    def params(self, func):
+        for i in range(self.get("wit_func_param_count_get", func, "size_t")):
+            yield self.get("wit_func_param_get_by_index", func, "const WITTypeDef *", i)
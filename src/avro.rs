@@ -0,0 +1,133 @@
+use parser::{Interface, Type, TypeDefKind, TypeId};
+use std::collections::HashSet;
+use crate::json::Json;
+
+// Builds the Avro schema for values of type `ty`.  Records, enums and lists
+// map directly, with `-` in names replaced by `_`; options are unions with
+// `null`, and flags are arrays of the enum of flag names.  Variants, unions
+// and expecteds are unions of one record per case, named `<type>_<case>`,
+// with the payload in a `value` field; wrapping every case keeps Avro from
+// rejecting unions of same-typed members.  Unsigned 32-bit integers need a
+// `long`, and u64 values above i64::MAX wrap.
+struct Avro<'a> {
+    iface:   &'a Interface,
+    defined: HashSet<String>,
+}
+
+pub(crate) fn document(iface: &Interface, namespace: &str, ty: &Type) -> Json {
+    let mut a = Avro {
+        iface,
+        defined: HashSet::new(),
+    };
+    match a.schema(ty) {
+        Json::Map(mut items) => {
+            items.insert(1, ("namespace".to_string(), Json::Str(name(namespace))));
+            Json::Map(items)
+        },
+        other => other,
+    }
+}
+
+impl<'a> Avro<'a> {
+    fn schema(&mut self, ty: &Type) -> Json {
+        let id = match ty {
+            Type::Unit => return Json::str("null"),
+            Type::Bool => return Json::str("boolean"),
+            Type::U8 | Type::U16 | Type::S8 | Type::S16 | Type::S32 => return Json::str("int"),
+            Type::U32 | Type::U64 | Type::S64 | Type::Handle(_) => return Json::str("long"),
+            Type::Float32 => return Json::str("float"),
+            Type::Float64 => return Json::str("double"),
+            Type::Char | Type::String => return Json::str("string"),
+            Type::Id(id) => *id,
+        };
+        let iface = self.iface;
+        match &iface.types[id].kind {
+            TypeDefKind::Type(t) => self.schema(t),
+            TypeDefKind::List(t) if matches!(crate::resolve_alias(iface, t), Type::U8) => Json::str("bytes"),
+            TypeDefKind::List(t) => object(vec![("type", Json::str("array")), ("items", self.schema(t))]),
+            TypeDefKind::Option(t) => Json::Arr(vec![Json::str("null"), self.schema(t)]),
+            TypeDefKind::Future(_) |
+            TypeDefKind::Stream(_) => Json::str("long"),
+            TypeDefKind::Record(r) => {
+                let fields = r.fields.iter().map(|f| (f.name.clone(), &f.ty)).collect();
+                self.record(self.type_name(id), fields)
+            },
+            TypeDefKind::Tuple(t) => {
+                let fields = t.types.iter().enumerate().map(|(i, t)| (format!("f{}", i), t)).collect();
+                self.record(self.type_name(id), fields)
+            },
+            TypeDefKind::Enum(e) => {
+                let names = e.cases.iter().map(|c| c.name.as_str());
+                self.enumeration(self.type_name(id), names)
+            },
+            TypeDefKind::Flags(f) => {
+                let names = f.flags.iter().map(|f| f.name.as_str());
+                let items = self.enumeration(self.type_name(id), names);
+                object(vec![("type", Json::str("array")), ("items", items)])
+            },
+            TypeDefKind::Variant(v) => {
+                let cases = v.cases.iter().map(|c| (c.name.clone(), &c.ty)).collect();
+                self.cases(id, cases)
+            },
+            TypeDefKind::Union(u) => {
+                let cases = u.cases.iter().enumerate().map(|(i, c)| (format!("c{}", i), &c.ty)).collect();
+                self.cases(id, cases)
+            },
+            TypeDefKind::Expected(e) => {
+                self.cases(id, vec![("ok".to_string(), &e.ok), ("err".to_string(), &e.err)])
+            },
+        }
+    }
+
+    // Anonymous types are named after their index.
+    fn type_name(&self, id: TypeId) -> String {
+        match &self.iface.types[id].name {
+            Some(n) => name(n),
+            None => format!("type{}", id.index()),
+        }
+    }
+
+    // Named types are defined where first used and referred to by name
+    // afterwards.
+    fn record(&mut self, type_name: String, fields: Vec<(String, &Type)>) -> Json {
+        if !self.defined.insert(type_name.clone()) {
+            return Json::Str(type_name);
+        }
+        let fields = fields.into_iter()
+            .map(|(n, ty)| object(vec![("name", Json::Str(name(&n))), ("type", self.schema(ty))]))
+            .collect();
+        object(vec![("type", Json::str("record")), ("name", Json::Str(type_name)), ("fields", Json::Arr(fields))])
+    }
+
+    fn enumeration<'b>(&mut self, type_name: String, symbols: impl Iterator<Item = &'b str>) -> Json {
+        if !self.defined.insert(type_name.clone()) {
+            return Json::Str(type_name);
+        }
+        let symbols = symbols.map(|s| Json::Str(name(s))).collect();
+        object(vec![("type", Json::str("enum")), ("name", Json::Str(type_name)), ("symbols", Json::Arr(symbols))])
+    }
+
+    fn cases(&mut self, id: TypeId, cases: Vec<(String, &Type)>) -> Json {
+        let base = self.type_name(id);
+        Json::Arr(cases.into_iter().map(|(case, ty)| {
+            let fields = if crate::is_unit(self.iface, ty) {
+                Vec::new()
+            } else {
+                vec![("value".to_string(), ty)]
+            };
+            self.record(format!("{}_{}", base, name(&case)), fields)
+        }).collect())
+    }
+}
+
+fn object(items: Vec<(&str, Json)>) -> Json {
+    Json::Map(items.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+// Avro names allow only letters, digits and `_`.
+fn name(s: &str) -> String {
+    s.trim_start_matches('%')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Result};
+use parser::{Function, FunctionKind, Interface, Type, TypeDefKind};
+use crate::ident::{self, Style};
+
+// Emits a SingleStore `CREATE FUNCTION ... AS WASM` statement per function,
+// loading the module and its WIT from `<module>.wasm` and `<module>.wit`.
+// Functions whose types have no SQL equivalent, and resource functions, are
+// listed in a comment instead.
+pub(crate) fn emit(iface: &Interface, module: &str) -> String {
+    let mut out = String::new();
+    for f in &iface.functions {
+        match statement(iface, f, module) {
+            Ok(stmt) => out.push_str(&stmt),
+            Err(e) => out.push_str(&format!("-- Skipped `{}`: {}\n", f.name, e)),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn statement(iface: &Interface, f: &Function, module: &str) -> Result<String> {
+    if !matches!(f.kind, FunctionKind::Freestanding) {
+        return Err(anyhow!("resource functions can't be called from SQL"));
+    }
+    let params = f.params.iter()
+        .map(|(n, ty)| {
+            let col = column(iface, ty).map_err(|e| anyhow!("param `{}`: {}", n, e))?;
+            Ok(format!("{} {}", ident::mangle(n, Style::Sql), col))
+        })
+        .collect::<Result<Vec<String>>>()?;
+    let returns = match table(iface, &f.result) {
+        Ok(Some(cols)) => Ok(format!("TABLE({})", cols)),
+        Ok(None) => column(iface, &f.result),
+        Err(e) => Err(e),
+    }.map_err(|e| anyhow!("result: {}", e))?;
+    Ok(format!(
+        "CREATE OR REPLACE FUNCTION {}({}) RETURNS {}\nAS WASM ABI CANONICAL FROM LOCAL INFILE '{}.wasm'\nWITH WIT FROM LOCAL INFILE '{}.wit'\nEXPORT '{}';\n",
+        ident::mangle(&f.name, Style::Sql), params.join(", "), returns, module, module, f.name,
+    ))
+}
+
+// A function returning a list of records is a table-valued function.
+fn table(iface: &Interface, ty: &Type) -> Result<Option<String>> {
+    let elem = match crate::resolve_alias(iface, ty) {
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::List(elem) => crate::resolve_alias(iface, elem),
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    match elem {
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Record(r) => Ok(Some(fields(iface, r.fields.iter().map(|f| (f.name.as_str(), &f.ty)))?)),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
+
+fn fields<'a>(iface: &Interface, fields: impl Iterator<Item = (&'a str, &'a Type)>) -> Result<String> {
+    let cols = fields
+        .map(|(n, ty)| Ok(format!("{} {}", ident::mangle(n, Style::Sql), column(iface, ty)?)))
+        .collect::<Result<Vec<String>>>()?;
+    Ok(cols.join(", "))
+}
+
+// SQL type of a column holding `ty`; options are nullable, everything else
+// is NOT NULL.
+fn column(iface: &Interface, ty: &Type) -> Result<String> {
+    if let Type::Id(id) = crate::resolve_alias(iface, ty) {
+        if let TypeDefKind::Option(t) = &iface.types[*id].kind {
+            return Ok(format!("{} NULL", sql_type(iface, t)?));
+        }
+    }
+    Ok(format!("{} NOT NULL", sql_type(iface, ty)?))
+}
+
+fn sql_type(iface: &Interface, ty: &Type) -> Result<String> {
+    let ty = crate::resolve_alias(iface, ty);
+    Ok(match ty {
+        Type::Bool => "BOOL",
+        Type::U8 => "TINYINT UNSIGNED",
+        Type::S8 => "TINYINT",
+        Type::U16 => "SMALLINT UNSIGNED",
+        Type::S16 => "SMALLINT",
+        Type::U32 => "INT UNSIGNED",
+        Type::S32 => "INT",
+        Type::U64 => "BIGINT UNSIGNED",
+        Type::S64 => "BIGINT",
+        Type::Float32 => "FLOAT",
+        Type::Float64 => "DOUBLE",
+        Type::Char => "CHAR(1)",
+        Type::String => "LONGTEXT",
+        Type::Id(id) => return match &iface.types[*id].kind {
+            TypeDefKind::List(elem) if matches!(crate::resolve_alias(iface, elem), Type::U8) => Ok("LONGBLOB".to_string()),
+            TypeDefKind::List(elem) => Ok(format!("ARRAY({})", column(iface, elem)?)),
+            TypeDefKind::Record(r) => Ok(format!("RECORD({})", fields(iface, r.fields.iter().map(|f| (f.name.as_str(), &f.ty)))?)),
+            _ => Err(anyhow!("no SQL equivalent")),
+        },
+        _ => return Err(anyhow!("no SQL equivalent")),
+    }.to_string())
+}
@@ -0,0 +1,309 @@
+use parser::abi::WasmType;
+use parser::{Function, Interface, Type, TypeDefKind};
+use crate::layout::Layout;
+use crate::markdown::{inline, type_expr};
+use crate::{WITChangeKind, WITCompat, WITItemKind};
+
+pub(crate) struct Change {
+    pub(crate) kind:   WITChangeKind,
+    pub(crate) item:   WITItemKind,
+    pub(crate) name:   String,
+    pub(crate) detail: String,
+}
+
+// Compares two interfaces by function and named type, in that order, each
+// in the order they appear in `new` followed by those removed from `old`.
+// Named types are compared by name wherever they are referred to, so a
+// change to a type is reported once, for the type itself.
+pub(crate) fn diff(old: &Interface, new: &Interface) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for f in &new.functions {
+        match old.functions.iter().find(|o| o.name == f.name) {
+            None => changes.push(change(WITChangeKind::Added, WITItemKind::Function, &f.name, signature(new, f))),
+            Some(o) => {
+                let (old_sig, new_sig) = (signature(old, o), signature(new, f));
+                let same = o.params.len() == f.params.len()
+                    && o.params.iter().zip(&f.params).all(|((on, ot), (nn, nt))| on == nn && same_type(old, ot, new, nt))
+                    && same_type(old, &o.result, new, &f.result);
+                if !same {
+                    changes.push(change(WITChangeKind::Changed, WITItemKind::Function, &f.name, format!("{} => {}", old_sig, new_sig)));
+                }
+            },
+        }
+    }
+    for o in &old.functions {
+        if !new.functions.iter().any(|f| f.name == o.name) {
+            changes.push(change(WITChangeKind::Removed, WITItemKind::Function, &o.name, signature(old, o)));
+        }
+    }
+
+    for (id, def) in new.types.iter() {
+        let name = match &def.name {
+            Some(n) => n,
+            None => continue,
+        };
+        match old.type_lookup.get(name) {
+            None => changes.push(change(WITChangeKind::Added, WITItemKind::Type, name, definition(new, &def.kind))),
+            Some(oid) => {
+                let details = kind_changes(old, &old.types[*oid].kind, new, &new.types[id].kind);
+                if !details.is_empty() {
+                    changes.push(change(WITChangeKind::Changed, WITItemKind::Type, name, details.join("; ")));
+                }
+            },
+        }
+    }
+    for (_, def) in old.types.iter() {
+        if let Some(name) = &def.name {
+            if !new.type_lookup.contains_key(name) {
+                changes.push(change(WITChangeKind::Removed, WITItemKind::Type, name, definition(old, &def.kind)));
+            }
+        }
+    }
+    changes
+}
+
+fn change(kind: WITChangeKind, item: WITItemKind, name: &str, detail: String) -> Change {
+    Change {
+        kind,
+        item,
+        name: name.to_string(),
+        detail,
+    }
+}
+
+fn signature(iface: &Interface, f: &Function) -> String {
+    let params: Vec<String> = f.params.iter().map(|(n, ty)| format!("{}: {}", n, type_expr(iface, ty))).collect();
+    if crate::is_unit(iface, &f.result) {
+        format!("func({})", params.join(", "))
+    } else {
+        format!("func({}) -> {}", params.join(", "), type_expr(iface, &f.result))
+    }
+}
+
+fn definition(iface: &Interface, kind: &TypeDefKind) -> String {
+    match kind {
+        TypeDefKind::Record(r) => format!("record {{ {} }}", r.fields.iter()
+            .map(|f| format!("{}: {}", f.name, type_expr(iface, &f.ty)))
+            .collect::<Vec<_>>().join(", ")),
+        TypeDefKind::Variant(v) => format!("variant {{ {} }}", v.cases.iter()
+            .map(|c| if crate::is_unit(iface, &c.ty) { c.name.clone() } else { format!("{}({})", c.name, type_expr(iface, &c.ty)) })
+            .collect::<Vec<_>>().join(", ")),
+        TypeDefKind::Flags(f) => format!("flags {{ {} }}", f.flags.iter().map(|f| f.name.as_str()).collect::<Vec<_>>().join(", ")),
+        TypeDefKind::Enum(e) => format!("enum {{ {} }}", e.cases.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ")),
+        kind => inline(iface, kind),
+    }
+}
+
+// Describes how a named type's definition changed; empty if it didn't.
+fn kind_changes(old: &Interface, ok: &TypeDefKind, new: &Interface, nk: &TypeDefKind) -> Vec<String> {
+    match (ok, nk) {
+        (TypeDefKind::Record(o), TypeDefKind::Record(n)) => {
+            let o: Vec<(&str, &Type)> = o.fields.iter().map(|f| (f.name.as_str(), &f.ty)).collect();
+            let n: Vec<(&str, &Type)> = n.fields.iter().map(|f| (f.name.as_str(), &f.ty)).collect();
+            members("field", old, &o, new, &n)
+        },
+        (TypeDefKind::Variant(o), TypeDefKind::Variant(n)) => {
+            let o: Vec<(&str, &Type)> = o.cases.iter().map(|c| (c.name.as_str(), &c.ty)).collect();
+            let n: Vec<(&str, &Type)> = n.cases.iter().map(|c| (c.name.as_str(), &c.ty)).collect();
+            members("case", old, &o, new, &n)
+        },
+        (TypeDefKind::Flags(o), TypeDefKind::Flags(n)) => {
+            let o: Vec<(&str, &Type)> = o.flags.iter().map(|f| (f.name.as_str(), &Type::Unit)).collect();
+            let n: Vec<(&str, &Type)> = n.flags.iter().map(|f| (f.name.as_str(), &Type::Unit)).collect();
+            members("flag", old, &o, new, &n)
+        },
+        (TypeDefKind::Enum(o), TypeDefKind::Enum(n)) => {
+            let o: Vec<(&str, &Type)> = o.cases.iter().map(|c| (c.name.as_str(), &Type::Unit)).collect();
+            let n: Vec<(&str, &Type)> = n.cases.iter().map(|c| (c.name.as_str(), &Type::Unit)).collect();
+            members("case", old, &o, new, &n)
+        },
+        (o, n) if same_kind(old, o, new, n) => Vec::new(),
+        (o, n) => vec![format!("{} => {}", definition(old, o), definition(new, n))],
+    }
+}
+
+// Members are matched by name; a member moving also changes the layout, so
+// reordering is reported too.
+fn members(what: &str, old: &Interface, o: &[(&str, &Type)], new: &Interface, n: &[(&str, &Type)]) -> Vec<String> {
+    let mut res = Vec::new();
+    for (i, (name, ty)) in n.iter().enumerate() {
+        match o.iter().position(|(on, _)| on == name) {
+            None => res.push(format!("{} `{}` added", what, name)),
+            Some(j) => {
+                if !same_type(old, o[j].1, new, ty) {
+                    res.push(format!("{} `{}`: {} => {}", what, name, type_expr(old, o[j].1), type_expr(new, ty)));
+                }
+                if i != j {
+                    res.push(format!("{} `{}` moved from position {} to {}", what, name, j, i));
+                }
+            },
+        }
+    }
+    for (name, _) in o {
+        if !n.iter().any(|(nn, _)| nn == name) {
+            res.push(format!("{} `{}` removed", what, name));
+        }
+    }
+    res
+}
+
+// True if `a` in `ai` and `b` in `bi` are the same type.  Named types are
+// the same if their names are.
+pub(crate) fn same_type(ai: &Interface, a: &Type, bi: &Interface, b: &Type) -> bool {
+    match (a, b) {
+        (Type::Id(x), Type::Id(y)) => {
+            let (x, y) = (&ai.types[*x], &bi.types[*y]);
+            match (&x.name, &y.name) {
+                (Some(xn), Some(yn)) => xn == yn,
+                (None, None) => same_kind(ai, &x.kind, bi, &y.kind),
+                _ => false,
+            }
+        },
+        (Type::Handle(x), Type::Handle(y)) => ai.resources[*x].name == bi.resources[*y].name,
+        (Type::Id(_), _) | (_, Type::Id(_)) | (Type::Handle(_), _) | (_, Type::Handle(_)) => false,
+        (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+    }
+}
+
+pub(crate) fn same_kind(ai: &Interface, a: &TypeDefKind, bi: &Interface, b: &TypeDefKind) -> bool {
+    let all = |x: Vec<&Type>, y: Vec<&Type>| {
+        x.len() == y.len() && x.iter().zip(&y).all(|(x, y)| same_type(ai, x, bi, y))
+    };
+    match (a, b) {
+        (TypeDefKind::Type(x), TypeDefKind::Type(y)) |
+        (TypeDefKind::List(x), TypeDefKind::List(y)) |
+        (TypeDefKind::Option(x), TypeDefKind::Option(y)) |
+        (TypeDefKind::Future(x), TypeDefKind::Future(y)) => same_type(ai, x, bi, y),
+        (TypeDefKind::Tuple(x), TypeDefKind::Tuple(y)) => all(x.types.iter().collect(), y.types.iter().collect()),
+        (TypeDefKind::Union(x), TypeDefKind::Union(y)) => {
+            all(x.cases.iter().map(|c| &c.ty).collect(), y.cases.iter().map(|c| &c.ty).collect())
+        },
+        (TypeDefKind::Expected(x), TypeDefKind::Expected(y)) => all(vec![&x.ok, &x.err], vec![&y.ok, &y.err]),
+        (TypeDefKind::Stream(x), TypeDefKind::Stream(y)) => all(vec![&x.element, &x.end], vec![&y.element, &y.end]),
+        (TypeDefKind::Record(x), TypeDefKind::Record(y)) => {
+            x.fields.len() == y.fields.len()
+                && x.fields.iter().zip(&y.fields).all(|(x, y)| x.name == y.name && same_type(ai, &x.ty, bi, &y.ty))
+        },
+        (TypeDefKind::Variant(x), TypeDefKind::Variant(y)) => {
+            x.cases.len() == y.cases.len()
+                && x.cases.iter().zip(&y.cases).all(|(x, y)| x.name == y.name && same_type(ai, &x.ty, bi, &y.ty))
+        },
+        (TypeDefKind::Flags(x), TypeDefKind::Flags(y)) => {
+            x.flags.len() == y.flags.len() && x.flags.iter().zip(&y.flags).all(|(x, y)| x.name == y.name)
+        },
+        (TypeDefKind::Enum(x), TypeDefKind::Enum(y)) => {
+            x.cases.len() == y.cases.len() && x.cases.iter().zip(&y.cases).all(|(x, y)| x.name == y.name)
+        },
+        _ => false,
+    }
+}
+
+// Classifies the changes from `old` to `new` for a caller of the functions
+// in `old`.  Renaming parameters is compatible; adding functions or types,
+// or appending cases to an enum, flags or variant that only flows into
+// functions and keeps its layout, is additive.  Anything else that changes
+// a function's signature or removes it is breaking.  Removed types are only
+// breaking through the functions that used them.
+pub(crate) fn compat(old: &Interface, old_layout: &Layout, new: &Interface, new_layout: &Layout) -> WITCompat {
+    let mut res = WITCompat::Compatible;
+    for f in &new.functions {
+        let same = match old.functions.iter().find(|o| o.name == f.name) {
+            None => {
+                res = res.max(WITCompat::Additive);
+                continue;
+            },
+            Some(o) => {
+                o.params.len() == f.params.len()
+                    && o.params.iter().zip(&f.params).all(|((_, ot), (_, nt))| same_type(old, ot, new, nt))
+                    && same_type(old, &o.result, new, &f.result)
+            },
+        };
+        if !same {
+            return WITCompat::Breaking;
+        }
+    }
+    if old.functions.iter().any(|o| !new.functions.iter().any(|f| f.name == o.name)) {
+        return WITCompat::Breaking;
+    }
+
+    for (id, def) in new.types.iter() {
+        let name = match &def.name {
+            Some(n) => n,
+            None => continue,
+        };
+        let oid = match old.type_lookup.get(name) {
+            None => {
+                res = res.max(WITCompat::Additive);
+                continue;
+            },
+            Some(oid) => *oid,
+        };
+        let (ok, nk) = (&old.types[oid].kind, &new.types[id].kind);
+        if kind_changes(old, ok, new, nk).is_empty() {
+            continue;
+        }
+        let (ot, nt) = (Type::Id(oid), Type::Id(id));
+        let extended = appended(old, ok, new, nk)
+            && old_layout.size(&ot) == new_layout.size(&nt)
+            && old_layout.align(&ot) == new_layout.align(&nt)
+            && flat(old, old_layout, &ot) == flat(new, new_layout, &nt);
+        // A result may now hold a case the caller doesn't know.
+        let returned = new.functions.iter().any(|f| reaches(new, &f.result, name));
+        if extended && !returned {
+            res = res.max(WITCompat::Additive);
+        } else if new.functions.iter().any(|f| f.params.iter().any(|(_, ty)| reaches(new, ty, name)) || reaches(new, &f.result, name)) {
+            return WITCompat::Breaking;
+        }
+    }
+    res
+}
+
+// True if `nk` is `ok` with members appended.
+fn appended(old: &Interface, ok: &TypeDefKind, new: &Interface, nk: &TypeDefKind) -> bool {
+    match (ok, nk) {
+        (TypeDefKind::Variant(o), TypeDefKind::Variant(n)) => {
+            o.cases.len() <= n.cases.len()
+                && o.cases.iter().zip(&n.cases).all(|(o, n)| o.name == n.name && same_type(old, &o.ty, new, &n.ty))
+        },
+        (TypeDefKind::Flags(o), TypeDefKind::Flags(n)) => {
+            o.flags.len() <= n.flags.len() && o.flags.iter().zip(&n.flags).all(|(o, n)| o.name == n.name)
+        },
+        (TypeDefKind::Enum(o), TypeDefKind::Enum(n)) => {
+            o.cases.len() <= n.cases.len() && o.cases.iter().zip(&n.cases).all(|(o, n)| o.name == n.name)
+        },
+        _ => false,
+    }
+}
+
+fn flat(iface: &Interface, layout: &Layout, ty: &Type) -> Vec<WasmType> {
+    let mut res = Vec::new();
+    crate::flatten(iface, ty, layout.ptr_type(), &mut res);
+    res
+}
+
+// True if values of `ty` can contain a value of the named type `name`.
+fn reaches(iface: &Interface, ty: &Type, name: &str) -> bool {
+    let id = match ty {
+        Type::Id(id) => *id,
+        _ => return false,
+    };
+    let def = &iface.types[id];
+    if def.name.as_deref() == Some(name) {
+        return true;
+    }
+    let any = |tys: Vec<&Type>| tys.into_iter().any(|t| reaches(iface, t, name));
+    match &def.kind {
+        TypeDefKind::Type(t) |
+        TypeDefKind::List(t) |
+        TypeDefKind::Option(t) |
+        TypeDefKind::Future(t) => reaches(iface, t, name),
+        TypeDefKind::Tuple(t) => any(t.types.iter().collect()),
+        TypeDefKind::Union(u) => any(u.cases.iter().map(|c| &c.ty).collect()),
+        TypeDefKind::Expected(e) => any(vec![&e.ok, &e.err]),
+        TypeDefKind::Stream(s) => any(vec![&s.element, &s.end]),
+        TypeDefKind::Record(r) => any(r.fields.iter().map(|f| &f.ty).collect()),
+        TypeDefKind::Variant(v) => any(v.cases.iter().map(|c| &c.ty).collect()),
+        TypeDefKind::Flags(_) |
+        TypeDefKind::Enum(_) => false,
+    }
+}
@@ -0,0 +1,104 @@
+use parser::{Interface, Type, TypeDefKind};
+
+// Emits a GraphViz DOT graph with a node per function, named type and
+// resource, and an edge from each to the named types and resources it refers
+// to, labeled with the param, field or case doing the referring.  Anonymous
+// types such as `list<point>` are looked through, so the edge goes straight
+// to `point`; primitive types are left out.
+pub(crate) fn emit(iface: &Interface, name: &str) -> String {
+    let mut out = format!("digraph {} {{\n", quote(name));
+    out.push_str("  node [shape=box];\n");
+    for (rid, r) in iface.resources.iter() {
+        out.push_str(&format!("  r{} [label={}, shape=hexagon];\n", rid.index(), quote(&r.name)));
+    }
+    for (id, def) in iface.types.iter() {
+        if let Some(n) = &def.name {
+            out.push_str(&format!("  t{} [label={}];\n", id.index(), quote(n)));
+        }
+    }
+    for (i, f) in iface.functions.iter().enumerate() {
+        out.push_str(&format!("  f{} [label={}, shape=ellipse];\n", i, quote(&f.name)));
+    }
+
+    for (id, def) in iface.types.iter() {
+        if def.name.is_none() {
+            continue;
+        }
+        let from = format!("t{}", id.index());
+        let refs: Vec<(String, &Type)> = match &def.kind {
+            TypeDefKind::Record(r) => r.fields.iter().map(|f| (f.name.clone(), &f.ty)).collect(),
+            TypeDefKind::Variant(v) => v.cases.iter().map(|c| (c.name.clone(), &c.ty)).collect(),
+            TypeDefKind::Union(u) => u.cases.iter().enumerate().map(|(i, c)| (i.to_string(), &c.ty)).collect(),
+            TypeDefKind::Tuple(t) => t.types.iter().enumerate().map(|(i, t)| (i.to_string(), t)).collect(),
+            TypeDefKind::Type(t) |
+            TypeDefKind::List(t) |
+            TypeDefKind::Option(t) |
+            TypeDefKind::Future(t) => vec![(String::new(), t)],
+            TypeDefKind::Expected(e) => vec![("ok".to_string(), &e.ok), ("err".to_string(), &e.err)],
+            TypeDefKind::Stream(s) => vec![(String::new(), &s.element), (String::new(), &s.end)],
+            TypeDefKind::Flags(_) |
+            TypeDefKind::Enum(_) => Vec::new(),
+        };
+        for (label, ty) in refs {
+            edges(iface, &from, &label, ty, &mut out);
+        }
+    }
+    for (i, f) in iface.functions.iter().enumerate() {
+        let from = format!("f{}", i);
+        for (n, ty) in &f.params {
+            edges(iface, &from, n, ty, &mut out);
+        }
+        edges(iface, &from, "result", &f.result, &mut out);
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn edges(iface: &Interface, from: &str, label: &str, ty: &Type, out: &mut String) {
+    let mut targets = Vec::new();
+    targets_of(iface, ty, &mut targets);
+    for to in targets {
+        if label.is_empty() {
+            out.push_str(&format!("  {} -> {};\n", from, to));
+        } else {
+            out.push_str(&format!("  {} -> {} [label={}];\n", from, to, quote(label)));
+        }
+    }
+}
+
+// Collects the nodes of the named types and resources `ty` refers to.
+fn targets_of(iface: &Interface, ty: &Type, res: &mut Vec<String>) {
+    let id = match ty {
+        Type::Handle(r) => return res.push(format!("r{}", r.index())),
+        Type::Id(id) => *id,
+        _ => return,
+    };
+    let def = &iface.types[id];
+    if def.name.is_some() {
+        return res.push(format!("t{}", id.index()));
+    }
+    match &def.kind {
+        TypeDefKind::Type(t) |
+        TypeDefKind::List(t) |
+        TypeDefKind::Option(t) |
+        TypeDefKind::Future(t) => targets_of(iface, t, res),
+        TypeDefKind::Tuple(t) => t.types.iter().for_each(|t| targets_of(iface, t, res)),
+        TypeDefKind::Union(u) => u.cases.iter().for_each(|c| targets_of(iface, &c.ty, res)),
+        TypeDefKind::Expected(e) => {
+            targets_of(iface, &e.ok, res);
+            targets_of(iface, &e.err, res);
+        },
+        TypeDefKind::Stream(s) => {
+            targets_of(iface, &s.element, res);
+            targets_of(iface, &s.end, res);
+        },
+        TypeDefKind::Record(r) => r.fields.iter().for_each(|f| targets_of(iface, &f.ty, res)),
+        TypeDefKind::Variant(v) => v.cases.iter().for_each(|c| targets_of(iface, &c.ty, res)),
+        TypeDefKind::Flags(_) |
+        TypeDefKind::Enum(_) => {},
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
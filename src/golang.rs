@@ -0,0 +1,118 @@
+use parser::Interface;
+
+// Emits a Go package wrapping this library's FFI for one interface: Parse
+// loads the interface and looks up each of its functions once, exposing
+// their core export name, post-return export and core signature as plain
+// Go values.  It builds against the C flavor of the cbindgen header
+// (`cbindgen --lang c -o to-wit.h`) and the static library.
+pub(crate) fn emit(iface: &Interface, name: &str, package: &str) -> String {
+    let funcs: Vec<(String, &str)> = iface.functions.iter()
+        .map(|f| (pascal(&f.name), f.name.as_str()))
+        .collect();
+    let mut out = format!("// Code generated by to-wit from the `{}` interface. DO NOT EDIT.\n\n", name);
+    out.push_str(&format!("package {}\n\n", package));
+    out.push_str(PRELUDE);
+    out.push_str(&format!("\n// Interface is a parsed `{}` interface and its functions.\ntype Interface struct {{\n", name));
+    out.push_str("\tsession *C.WITSession\n\twit     *C.WIT\n\n");
+    for (field, func) in &funcs {
+        out.push_str(&format!("\t{} Func // `{}`\n", field, func));
+    }
+    out.push_str("}\n");
+    out.push_str(&format!("\n// Parse parses `src`, which must define the functions of the `{}`\n// interface.\n", name));
+    out.push_str("func Parse(src []byte) (*Interface, error) {\n");
+    out.push_str("\ti := &Interface{session: C.wit_session_new()}\n");
+    out.push_str("\tvar p unsafe.Pointer\n\tif len(src) > 0 {\n\t\tp = unsafe.Pointer(&src[0])\n\t}\n");
+    out.push_str("\tif !C.wit_parse(i.session, (*C.uint8_t)(p), C.size_t(len(src)), &i.wit) {\n");
+    out.push_str("\t\terr := i.err()\n\t\tC.wit_session_delete(i.session)\n\t\treturn nil, err\n\t}\n");
+    out.push_str("\tfor _, f := range []struct {\n\t\tname string\n\t\tfn   *Func\n\t}{\n");
+    for (field, func) in &funcs {
+        out.push_str(&format!("\t\t{{{:?}, &i.{}}},\n", func, field));
+    }
+    out.push_str("\t} {\n\t\tif err := i.load(f.name, f.fn); err != nil {\n\t\t\ti.Close()\n\t\t\treturn nil, err\n\t\t}\n\t}\n");
+    out.push_str("\treturn i, nil\n}\n");
+    out.push_str(SUFFIX);
+    out
+}
+
+const PRELUDE: &str = "\
+/*
+#cgo LDFLAGS: -lto_wit -lpthread -ldl -lm
+#include <stdlib.h>
+#include \"to-wit.h\"
+*/
+import \"C\"
+
+import (
+\t\"errors\"
+\t\"unsafe\"
+)
+
+// Func is a function of the interface.
+type Func struct {
+\tptr        *C.WITFunction
+\tName       string
+\tExportName string // core export implementing the function
+\tPostReturn string // core export freeing its result, or empty
+\tSignature  string // core signature of ExportName, e.g. `(i32, i32) -> i32`
+}
+
+// Ptr returns the underlying function handle for use with the C API.
+func (f *Func) Ptr() unsafe.Pointer {
+\treturn unsafe.Pointer(f.ptr)
+}
+";
+
+const SUFFIX: &str = "
+func (i *Interface) load(name string, f *Func) error {
+\tcname := C.CString(name)
+\tdefer C.free(unsafe.Pointer(cname))
+\tif !C.wit_func_get_by_name(i.session, i.wit, cname, &f.ptr) {
+\t\treturn i.err()
+\t}
+\tf.Name = name
+\tvar s *C.char
+\tif !C.wit_func_core_export_name_get(i.session, f.ptr, &s) {
+\t\treturn i.err()
+\t}
+\tf.ExportName = C.GoString(s)
+\tvar post C.bool
+\tif !C.wit_func_needs_post_return(i.session, f.ptr, &post) {
+\t\treturn i.err()
+\t}
+\tif post {
+\t\tif !C.wit_func_post_return_name_get(i.session, f.ptr, &s) {
+\t\t\treturn i.err()
+\t\t}
+\t\tf.PostReturn = C.GoString(s)
+\t}
+\tif !C.wit_func_core_sig_string_get(i.session, f.ptr, &s) {
+\t\treturn i.err()
+\t}
+\tf.Signature = C.GoString(s)
+\treturn nil
+}
+
+// Close frees the interface.  Its functions must not be used afterwards.
+func (i *Interface) Close() {
+\tC.wit_delete(i.session, i.wit)
+\tC.wit_session_delete(i.session)
+}
+
+func (i *Interface) err() error {
+\treturn errors.New(C.GoString(C.wit_error_get(i.session)))
+}
+";
+
+// `get-value` and `counter::get-value` become `GetValue` and
+// `CounterGetValue`.
+fn pascal(name: &str) -> String {
+    name.split(|c| c == '-' || c == ':' || c == '_' || c == '%')
+        .map(|w| {
+            let mut c = w.chars();
+            match c.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + c.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
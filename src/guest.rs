@@ -0,0 +1,461 @@
+use parser::abi::WasmType;
+use parser::{Interface, Type, TypeDefKind, TypeId};
+use crate::header::Header;
+use crate::ident::{self, Style};
+use crate::{WITFunction, WIT};
+
+// Generates guest-side C bindings in the style of wit-bindgen's C backend:
+// a header declaring the interface's types, their `_free` functions and the
+// functions the guest must implement, and a source file with `cabi_realloc`
+// and an export shim per function that lifts the core arguments, calls the
+// implementation and lowers its result.
+//
+// Guest C structs have the canonical ABI memory layout, so values in memory
+// (param blocks and return areas) are read and written as whole structs.
+// Memory owned by params passes to the implementation, which must free it;
+// memory owned by results is freed by the `cabi_post_*` export.
+
+pub(crate) fn header(wit: &WIT, ns: &str) -> String {
+    let prefix = format!("{}_", ns);
+    let mut h = Header::new(&wit.iface, &wit.align, &prefix, true);
+    let guard = format!("__BINDINGS_{}_H", ns.to_ascii_uppercase());
+    let mut out = format!("#ifndef {0}\n#define {0}\n#ifdef __cplusplus\nextern \"C\" {{\n#endif\n\n", guard);
+    out.push_str("#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n\n");
+    out.push_str(&h.types());
+    out.push_str(&format!("void {0}string_set({0}string_t *ret, const char *s);\n", prefix));
+    out.push_str(&format!("void {0}string_free({0}string_t *ptr);\n", prefix));
+    for (id, _) in owning_types(&wit.iface) {
+        let name = h.type_name(&Type::Id(id));
+        out.push_str(&format!("void {}({} *ptr);\n", free_name(&name), name));
+    }
+    out.push('\n');
+    for f in &wit.iface.functions {
+        out.push_str(&prototype(&wit.iface, &h, &wit.funcs[&f.name]));
+        out.push_str(";\n");
+    }
+    out.push_str(&format!("\n#ifdef __cplusplus\n}}\n#endif\n#endif  // {}\n", guard));
+    out
+}
+
+pub(crate) fn source(wit: &WIT, ns: &str) -> String {
+    let iface = &*wit.iface;
+    let prefix = format!("{}_", ns);
+    let h = Header::new(iface, &wit.align, &prefix, true);
+    let g = Guest {
+        iface,
+        h: &h,
+        ptr: wit.align.ptr_type(),
+    };
+    let mut out = format!("#include <stdlib.h>\n#include <string.h>\n#include \"{}.h\"\n\n", wit.name.to_string_lossy());
+    out.push_str(PRELUDE);
+    out.push_str(&format!(
+        "\nvoid {0}string_set({0}string_t *ret, const char *s) {{\n    ret->ptr = (char *) s;\n    ret->len = strlen(s);\n}}\n",
+        prefix));
+    out.push_str(&format!(
+        "\nvoid {0}string_free({0}string_t *ptr) {{\n    if (ptr->len > 0) {{\n        free(ptr->ptr);\n    }}\n    ptr->ptr = NULL;\n    ptr->len = 0;\n}}\n",
+        prefix));
+    for (id, kind) in owning_types(iface) {
+        out.push_str(&g.free_fn(id, kind));
+    }
+    for f in &iface.functions {
+        out.push('\n');
+        out.push_str(&g.export(&wit.funcs[&f.name]));
+    }
+    out
+}
+
+const PRELUDE: &str = "\
+__attribute__((weak, export_name(\"cabi_realloc\")))
+void *cabi_realloc(void *ptr, size_t orig_size, size_t align, size_t new_size) {
+    if (new_size == 0) {
+        return (void *) align;
+    }
+    void *ret = realloc(ptr, new_size);
+    if (!ret) {
+        abort();
+    }
+    return ret;
+}
+
+static inline float __wit_i32_f32(int32_t i) { float f; memcpy(&f, &i, 4); return f; }
+static inline int32_t __wit_f32_i32(float f) { int32_t i; memcpy(&i, &f, 4); return i; }
+static inline double __wit_i64_f64(int64_t i) { double f; memcpy(&f, &i, 8); return f; }
+static inline int64_t __wit_f64_i64(double f) { int64_t i; memcpy(&i, &f, 8); return i; }
+";
+
+struct Guest<'a> {
+    iface: &'a Interface,
+    h:     &'a Header<'a>,
+    ptr:   WasmType,
+}
+
+impl<'a> Guest<'a> {
+    fn export(&self, func: &WITFunction) -> String {
+        let iface = self.iface;
+        let f = &iface.functions[func.index];
+        let sig = &func.sig.sig;
+        if sig.results.len() > 1 {
+            return format!("// Skipped `{}`: multi-value results can't be returned from C.\n", f.name);
+        }
+        let params: Vec<(String, &Type)> = f.params.iter()
+            .filter(|(_, ty)| !crate::is_unit(iface, ty))
+            .map(|(n, ty)| (ident::mangle(n, Style::C), ty))
+            .collect();
+        let core_params: Vec<String> = sig.params.iter().enumerate()
+            .map(|(i, t)| format!("{} arg{}", core_type(*t), i))
+            .collect();
+        let core_result = sig.results.first().map(|t| core_type(*t)).unwrap_or("void");
+        let name = fn_name(self.h, &f.name);
+        let mut out = format!("__attribute__((export_name(\"{}\")))\n{} __wasm_export_{}({}) {{\n",
+            func.export_name.to_string_lossy(), core_result, name, core_params.join(", "));
+
+        // Lift the params, either from the param block or from the flat args.
+        let mut args = Vec::new();
+        if sig.indirect_params {
+            out.push_str("    struct {\n");
+            for (n, ty) in &params {
+                out.push_str(&format!("        {} {};\n", self.h.type_name(ty), n));
+            }
+            out.push_str("    } params;\n");
+            out.push_str("    memcpy(&params, (void *)(uintptr_t) arg0, sizeof(params));\n");
+            out.push_str("    free((void *)(uintptr_t) arg0);\n");
+            for (n, ty) in &params {
+                args.push(arg(iface, &format!("params.{}", n), ty));
+            }
+        } else {
+            let mut next = 0;
+            for (n, ty) in &params {
+                out.push_str(&format!("    {} {};\n", self.h.type_name(ty), n));
+                let flat = self.flat(ty);
+                let slots: Vec<(String, WasmType)> = flat.iter().enumerate()
+                    .map(|(i, t)| (format!("arg{}", next + i), *t))
+                    .collect();
+                next += flat.len();
+                self.lift(n, ty, &slots, 1, &mut out);
+                args.push(arg(iface, n, ty));
+            }
+        }
+
+        // Call the implementation.
+        let result = &f.result;
+        if crate::is_unit(iface, result) {
+            out.push_str(&format!("    {}({});\n", name, args.join(", ")));
+        } else if by_value(iface, result) {
+            out.push_str(&format!("    {} ret = {}({});\n", self.h.type_name(result), name, args.join(", ")));
+        } else {
+            args.push("&ret".to_string());
+            out.push_str(&format!("    {} ret;\n    {}({});\n", self.h.type_name(result), name, args.join(", ")));
+        }
+
+        // Lower the result, into the return area if it doesn't fit the
+        // core results.
+        if sig.retptr {
+            out.push_str(&format!("    static {} ret_area;\n    ret_area = ret;\n", self.h.type_name(result)));
+            out.push_str(&format!("    return ({})(uintptr_t) &ret_area;\n", core_result));
+        } else if let Some(t) = sig.results.first() {
+            out.push_str(&format!("    {} ret0 = 0;\n", core_type(*t)));
+            self.lower("ret", result, &[("ret0".to_string(), *t)], 1, &mut out);
+            out.push_str("    return ret0;\n");
+        }
+        out.push_str("}\n");
+
+        if func.post_return.is_some() {
+            let mut post = format!("\n__attribute__((export_name(\"cabi_post_{}\")))\nvoid __wasm_export_{}_post_return({}) {{\n",
+                func.export_name.to_string_lossy(), name,
+                sig.results.iter().enumerate()
+                    .map(|(i, t)| format!("{} arg{}", core_type(*t), i))
+                    .collect::<Vec<_>>()
+                    .join(", "));
+            let ty_name = self.h.type_name(result);
+            if sig.retptr {
+                post.push_str(&format!("    {}(({} *)(uintptr_t) arg0);\n", self.free_call(result), ty_name));
+            } else {
+                post.push_str(&format!("    {} ret;\n", ty_name));
+                let slots: Vec<(String, WasmType)> = sig.results.iter().enumerate()
+                    .map(|(i, t)| (format!("arg{}", i), *t))
+                    .collect();
+                self.lift("ret", result, &slots, 1, &mut post);
+                post.push_str(&format!("    {}(&ret);\n", self.free_call(result)));
+            }
+            post.push_str("}\n");
+            out.push_str(&post);
+        }
+        out
+    }
+
+    fn flat(&self, ty: &Type) -> Vec<WasmType> {
+        let mut flat = Vec::new();
+        crate::flatten(self.iface, ty, self.ptr, &mut flat);
+        flat
+    }
+
+    // Emits statements storing the value of type `ty` held in the core
+    // values `slots` into the lvalue `dst`.  Slot types may be wider than
+    // `ty`'s own flattening inside variants, so leaves coerce.
+    fn lift(&self, dst: &str, ty: &Type, slots: &[(String, WasmType)], depth: usize, out: &mut String) {
+        let ty = crate::resolve_alias(self.iface, ty);
+        let pad = "    ".repeat(depth);
+        let nat = self.flat(ty);
+        let leaf = |i: usize| coerce(&slots[i].0, slots[i].1, nat[i]);
+        match ty {
+            Type::Unit => {},
+            Type::Bool => out.push_str(&format!("{}{} = {} != 0;\n", pad, dst, leaf(0))),
+            Type::String => {
+                out.push_str(&format!("{}{}.ptr = (char *)(uintptr_t) {};\n", pad, dst, leaf(0)));
+                out.push_str(&format!("{}{}.len = (size_t) {};\n", pad, dst, leaf(1)));
+            },
+            Type::Id(id) => match &self.iface.types[*id].kind {
+                TypeDefKind::List(elem) => {
+                    out.push_str(&format!("{}{}.ptr = ({} *)(uintptr_t) {};\n", pad, dst, self.h.type_name(elem), leaf(0)));
+                    out.push_str(&format!("{}{}.len = (size_t) {};\n", pad, dst, leaf(1)));
+                },
+                TypeDefKind::Flags(_) if nat.len() > 1 => {
+                    for i in 0..nat.len() {
+                        out.push_str(&format!("{}{}.words[{}] = (uint32_t) {};\n", pad, dst, i, leaf(i)));
+                    }
+                },
+                kind => match (fields(kind), cases(kind)) {
+                    (Some(fields), _) => {
+                        let mut next = 0;
+                        for (member, fty) in fields {
+                            let n = self.flat(fty).len();
+                            self.lift(&format!("{}.{}", dst, member), fty, &slots[next..next + n], depth, out);
+                            next += n;
+                        }
+                    },
+                    (_, Some(cases)) => {
+                        out.push_str(&format!("{}{}.tag = {};\n", pad, dst, leaf(0)));
+                        out.push_str(&format!("{}switch ({}.tag) {{\n", pad, dst));
+                        for (i, (member, cty)) in cases.into_iter().enumerate() {
+                            if crate::is_unit(self.iface, cty) {
+                                continue;
+                            }
+                            let n = self.flat(cty).len();
+                            out.push_str(&format!("{}    case {}:\n", pad, i));
+                            self.lift(&format!("{}.val.{}", dst, member), cty, &slots[1..1 + n], depth + 2, out);
+                            out.push_str(&format!("{}        break;\n", pad));
+                        }
+                        out.push_str(&format!("{}}}\n", pad));
+                    },
+                    _ => out.push_str(&format!("{}{} = {};\n", pad, dst, leaf(0))),
+                },
+            },
+            _ => out.push_str(&format!("{}{} = ({}) {};\n", pad, dst, self.h.type_name(ty), leaf(0))),
+        }
+    }
+
+    // Emits statements storing the value of type `ty` in the lvalue `src`
+    // into the core value lvalues `slots`; the reverse of `lift`.
+    fn lower(&self, src: &str, ty: &Type, slots: &[(String, WasmType)], depth: usize, out: &mut String) {
+        let ty = crate::resolve_alias(self.iface, ty);
+        let pad = "    ".repeat(depth);
+        let nat = self.flat(ty);
+        let store = |i: usize, value: String| {
+            let value = format!("({}) {}", core_type(nat[i]), value);
+            format!("{}{} = {};\n", pad, slots[i].0, coerce(&value, nat[i], slots[i].1))
+        };
+        match ty {
+            Type::Unit => {},
+            Type::String => {
+                out.push_str(&store(0, format!("(uintptr_t) {}.ptr", src)));
+                out.push_str(&store(1, format!("{}.len", src)));
+            },
+            Type::Id(id) => match &self.iface.types[*id].kind {
+                TypeDefKind::List(_) => {
+                    out.push_str(&store(0, format!("(uintptr_t) {}.ptr", src)));
+                    out.push_str(&store(1, format!("{}.len", src)));
+                },
+                TypeDefKind::Flags(_) if nat.len() > 1 => {
+                    for i in 0..nat.len() {
+                        out.push_str(&store(i, format!("{}.words[{}]", src, i)));
+                    }
+                },
+                kind => match (fields(kind), cases(kind)) {
+                    (Some(fields), _) => {
+                        let mut next = 0;
+                        for (member, fty) in fields {
+                            let n = self.flat(fty).len();
+                            self.lower(&format!("{}.{}", src, member), fty, &slots[next..next + n], depth, out);
+                            next += n;
+                        }
+                    },
+                    (_, Some(cases)) => {
+                        out.push_str(&store(0, format!("{}.tag", src)));
+                        out.push_str(&format!("{}switch ({}.tag) {{\n", pad, src));
+                        for (i, (member, cty)) in cases.into_iter().enumerate() {
+                            if crate::is_unit(self.iface, cty) {
+                                continue;
+                            }
+                            let n = self.flat(cty).len();
+                            out.push_str(&format!("{}    case {}:\n", pad, i));
+                            self.lower(&format!("{}.val.{}", src, member), cty, &slots[1..1 + n], depth + 2, out);
+                            out.push_str(&format!("{}        break;\n", pad));
+                        }
+                        out.push_str(&format!("{}}}\n", pad));
+                    },
+                    _ => out.push_str(&store(0, src.to_string())),
+                },
+            },
+            _ => out.push_str(&store(0, src.to_string())),
+        }
+    }
+
+    fn free_fn(&self, id: TypeId, kind: &TypeDefKind) -> String {
+        let name = self.h.type_name(&Type::Id(id));
+        let mut body = String::new();
+        let free = |lv: String, ty: &Type, pad: &str| {
+            if crate::owns_memory(self.iface, ty) {
+                format!("{}{}(&{});\n", pad, self.free_call(ty), lv)
+            } else {
+                String::new()
+            }
+        };
+        match kind {
+            TypeDefKind::Type(t) => body.push_str(&free("*ptr".to_string(), t, "    ")),
+            TypeDefKind::List(elem) => {
+                if crate::owns_memory(self.iface, elem) {
+                    body.push_str("    for (size_t i = 0; i < ptr->len; i++) {\n");
+                    body.push_str(&free("ptr->ptr[i]".to_string(), elem, "        "));
+                    body.push_str("    }\n");
+                }
+                body.push_str("    if (ptr->len > 0) {\n        free(ptr->ptr);\n    }\n");
+            },
+            kind => match (fields(kind), cases(kind)) {
+                (Some(fields), _) => {
+                    for (member, fty) in fields {
+                        body.push_str(&free(format!("ptr->{}", member), fty, "    "));
+                    }
+                },
+                (_, Some(cases)) => {
+                    body.push_str("    switch (ptr->tag) {\n");
+                    for (i, (member, cty)) in cases.into_iter().enumerate() {
+                        if crate::owns_memory(self.iface, cty) {
+                            body.push_str(&format!("        case {}:\n", i));
+                            body.push_str(&free(format!("ptr->val.{}", member), cty, "            "));
+                            body.push_str("            break;\n");
+                        }
+                    }
+                    body.push_str("    }\n");
+                },
+                _ => {},
+            },
+        }
+        format!("\nvoid {}({} *ptr) {{\n{}}}\n", free_name(&name), name, body)
+    }
+
+    fn free_call(&self, ty: &Type) -> String {
+        free_name(&self.h.type_name(ty))
+    }
+}
+
+// Types needing a `_free` function, i.e. those holding strings or lists.
+fn owning_types(iface: &Interface) -> Vec<(TypeId, &TypeDefKind)> {
+    iface.types.iter()
+        .filter(|(id, _)| crate::owns_memory(iface, &Type::Id(*id)))
+        .map(|(id, def)| (id, &def.kind))
+        .collect()
+}
+
+// `foo_string_t` is freed by `foo_string_free`, and so on.
+fn free_name(type_name: &str) -> String {
+    format!("{}_free", type_name.strip_suffix("_t").unwrap_or(type_name))
+}
+
+fn fn_name(h: &Header, name: &str) -> String {
+    let parts: Vec<String> = name.split("::").map(|n| ident::mangle(n, Style::C)).collect();
+    let prefix = h.type_name(&Type::String);
+    format!("{}{}", prefix.strip_suffix("string_t").unwrap_or(&prefix), parts.join("_"))
+}
+
+// Prototype of the function the guest implements.  Scalars are passed and
+// returned by value, everything else by pointer.
+fn prototype(iface: &Interface, h: &Header, func: &WITFunction) -> String {
+    let f = &iface.functions[func.index];
+    let mut params: Vec<String> = f.params.iter()
+        .filter(|(_, ty)| !crate::is_unit(iface, ty))
+        .map(|(n, ty)| {
+            let star = if by_value(iface, ty) { "" } else { "*" };
+            format!("{} {}{}", h.type_name(ty), star, ident::mangle(n, Style::C))
+        })
+        .collect();
+    let mut result = "void".to_string();
+    if !crate::is_unit(iface, &f.result) {
+        if by_value(iface, &f.result) {
+            result = h.type_name(&f.result);
+        } else {
+            params.push(format!("{} *ret", h.type_name(&f.result)));
+        }
+    }
+    if params.is_empty() {
+        params.push("void".to_string());
+    }
+    format!("{} {}({})", result, fn_name(h, &f.name), params.join(", "))
+}
+
+fn arg(iface: &Interface, lv: &str, ty: &Type) -> String {
+    if by_value(iface, ty) {
+        lv.to_string()
+    } else {
+        format!("&{}", lv)
+    }
+}
+
+fn by_value(iface: &Interface, ty: &Type) -> bool {
+    match crate::resolve_alias(iface, ty) {
+        Type::String => false,
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Flags(f) => f.repr().count() <= 1,
+            TypeDefKind::Enum(_) |
+            TypeDefKind::Future(_) |
+            TypeDefKind::Stream(_) => true,
+            _ => false,
+        },
+        _ => true,
+    }
+}
+
+// Members of records and tuples, named as in the header.
+fn fields(kind: &TypeDefKind) -> Option<Vec<(String, &Type)>> {
+    match kind {
+        TypeDefKind::Record(r) => Some(r.fields.iter().map(|f| (ident::mangle(&f.name, Style::C), &f.ty)).collect()),
+        TypeDefKind::Tuple(t) => Some(t.types.iter().enumerate().map(|(i, t)| (format!("f{}", i), t)).collect()),
+        _ => None,
+    }
+}
+
+// Cases of variant-like types, named as in the header's payload unions.
+fn cases(kind: &TypeDefKind) -> Option<Vec<(String, &Type)>> {
+    match kind {
+        TypeDefKind::Variant(v) => Some(v.cases.iter().map(|c| (ident::mangle(&c.name, Style::C), &c.ty)).collect()),
+        TypeDefKind::Union(u) => Some(u.cases.iter().enumerate().map(|(i, c)| (format!("f{}", i), &c.ty)).collect()),
+        TypeDefKind::Option(t) => Some(vec![("none".to_string(), &Type::Unit), ("some".to_string(), t)]),
+        TypeDefKind::Expected(e) => Some(vec![("ok".to_string(), &e.ok), ("err".to_string(), &e.err)]),
+        _ => None,
+    }
+}
+
+fn core_type(t: WasmType) -> &'static str {
+    match t {
+        WasmType::I32 => "int32_t",
+        WasmType::I64 => "int64_t",
+        WasmType::F32 => "float",
+        WasmType::F64 => "double",
+    }
+}
+
+// Converts `expr` from core type `from` to `to`, per the canonical ABI's
+// joining of variant payloads: floats are bitcast to integers, and i32 is
+// zero-extended to i64.
+fn coerce(expr: &str, from: WasmType, to: WasmType) -> String {
+    match (from, to) {
+        (WasmType::I32, WasmType::F32) => format!("__wit_i32_f32({})", expr),
+        (WasmType::F32, WasmType::I32) => format!("__wit_f32_i32({})", expr),
+        (WasmType::I64, WasmType::F64) => format!("__wit_i64_f64({})", expr),
+        (WasmType::F64, WasmType::I64) => format!("__wit_f64_i64({})", expr),
+        (WasmType::I64, WasmType::I32) => format!("(int32_t) ({})", expr),
+        (WasmType::I32, WasmType::I64) => format!("(int64_t) (uint32_t) ({})", expr),
+        (WasmType::I64, WasmType::F32) => format!("__wit_i32_f32((int32_t) ({}))", expr),
+        (WasmType::F32, WasmType::I64) => format!("(int64_t) (uint32_t) __wit_f32_i32({})", expr),
+        _ => expr.to_string(),
+    }
+}
@@ -0,0 +1,250 @@
+use parser::{Function, Int, Interface, Type, TypeDefKind, TypeId};
+use std::collections::HashSet;
+use crate::ident::{self, Style};
+use crate::layout::Layout;
+
+// Emits C declarations for an interface: one typedef per type, mirroring its
+// layout in guest memory, and one prototype per function.  Strings and lists
+// hold guest addresses rather than host pointers, so their pointer and length
+// are 32- or 64-bit integers depending on the guest's memory.
+//
+// Guest code sees its own memory directly, so the guest flavor (see
+// `guest.rs`) declares strings and lists with real pointers and `size_t`
+// lengths instead.
+pub(crate) struct Header<'a> {
+    iface:  &'a Interface,
+    layout: &'a Layout,
+    prefix: &'a str,
+    guest:  bool,
+    out:    String,
+    done:   HashSet<TypeId>,
+}
+
+pub(crate) fn emit(iface: &Interface, layout: &Layout, prefix: &str, guard: &str) -> String {
+    let mut h = Header::new(iface, layout, prefix, false);
+    let mut out = format!("#ifndef {0}\n#define {0}\n\n", guard);
+    out.push_str("#include <stdbool.h>\n#include <stdint.h>\n\n");
+    out.push_str(&h.types());
+    for func in &iface.functions {
+        out.push_str(&h.prototype(func));
+    }
+    out.push_str(&format!("\n#endif  // {}\n", guard));
+    out
+}
+
+impl<'a> Header<'a> {
+    pub(crate) fn new(iface: &'a Interface, layout: &'a Layout, prefix: &'a str, guest: bool) -> Header<'a> {
+        Header {
+            iface,
+            layout,
+            prefix,
+            guest,
+            out: String::new(),
+            done: HashSet::new(),
+        }
+    }
+
+    // Returns the typedefs for the string type and every type in the
+    // interface, dependencies first.
+    pub(crate) fn types(&mut self) -> String {
+        self.out.clear();
+        self.done.clear();
+        let string = if self.guest {
+            "    char *ptr;\n    size_t len;\n".to_string()
+        } else {
+            format!("    {0} ptr;\n    {0} len;\n", self.addr_type())
+        };
+        self.out.push_str(&format!("typedef struct {{\n{}}} {}string_t;\n\n", string, self.prefix));
+        for (id, _) in self.iface.types.iter() {
+            self.define(id);
+        }
+        std::mem::take(&mut self.out)
+    }
+
+    fn addr_type(&self) -> &'static str {
+        if self.layout.memory64() {
+            "uint64_t"
+        } else {
+            "uint32_t"
+        }
+    }
+
+    pub(crate) fn type_name(&self, ty: &Type) -> String {
+        match ty {
+            Type::Unit => "void".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::U8 => "uint8_t".to_string(),
+            Type::U16 => "uint16_t".to_string(),
+            Type::U32 => "uint32_t".to_string(),
+            Type::U64 => "uint64_t".to_string(),
+            Type::S8 => "int8_t".to_string(),
+            Type::S16 => "int16_t".to_string(),
+            Type::S32 => "int32_t".to_string(),
+            Type::S64 => "int64_t".to_string(),
+            Type::Float32 => "float".to_string(),
+            Type::Float64 => "double".to_string(),
+            Type::Char |
+            Type::Handle(_) => "uint32_t".to_string(),
+            Type::String => format!("{}string_t", self.prefix),
+            Type::Id(id) => format!("{}{}_t", self.prefix, self.base_name(*id)),
+        }
+    }
+
+    // Anonymous types, e.g. `list<u8>` or `tuple<u32, u32>`, are named after
+    // their index.
+    fn base_name(&self, id: TypeId) -> String {
+        match &self.iface.types[id].name {
+            Some(name) => ident::mangle(name, Style::C),
+            None => format!("type{}", id.index()),
+        }
+    }
+
+    fn macro_name(&self, id: TypeId, case: &str) -> String {
+        format!("{}{}_{}", self.prefix, self.base_name(id), ident::mangle(case, Style::C)).to_ascii_uppercase()
+    }
+
+    // Emits the typedef for `id` after those of the types it refers to.
+    fn define(&mut self, id: TypeId) {
+        if !self.done.insert(id) {
+            return;
+        }
+        let iface = self.iface;
+        let kind = &iface.types[id].kind;
+        for ty in children(kind) {
+            if let Type::Id(child) = ty {
+                self.define(*child);
+            }
+        }
+        let name = self.type_name(&Type::Id(id));
+        let decl = match kind {
+            TypeDefKind::Type(t) => format!("typedef {} {};\n", self.type_name(t), name),
+            TypeDefKind::Record(r) => {
+                self.structure(r.fields.iter().map(|f| (ident::mangle(&f.name, Style::C), &f.ty)), &name)
+            },
+            TypeDefKind::Tuple(t) => {
+                self.structure(t.types.iter().enumerate().map(|(i, t)| (format!("f{}", i), t)), &name)
+            },
+            TypeDefKind::List(elem) if self.guest => {
+                format!("typedef struct {{\n    {} *ptr;\n    size_t len;\n}} {};\n", self.type_name(elem), name)
+            },
+            TypeDefKind::List(_) => {
+                format!("typedef struct {{\n    {0} ptr;\n    {0} len;\n}} {1};\n", self.addr_type(), name)
+            },
+            TypeDefKind::Flags(f) => {
+                let mut decl = match self.layout.size(&Type::Id(id)) {
+                    1 => format!("typedef uint8_t {};\n", name),
+                    2 => format!("typedef uint16_t {};\n", name),
+                    4 => format!("typedef uint32_t {};\n", name),
+                    size => format!("typedef struct {{\n    uint32_t words[{}];\n}} {};\n", size / 4, name),
+                };
+                // Masks are within word `i / 32` for flags wider than 32 bits.
+                for (i, flag) in f.flags.iter().enumerate() {
+                    decl.push_str(&format!("#define {} (1u << {})\n", self.macro_name(id, &flag.name), i % 32));
+                }
+                decl
+            },
+            TypeDefKind::Enum(e) => {
+                let mut decl = format!("typedef {} {};\n", tag_type(e.tag()), name);
+                for (i, case) in e.cases.iter().enumerate() {
+                    decl.push_str(&format!("#define {} {}\n", self.macro_name(id, &case.name), i));
+                }
+                decl
+            },
+            TypeDefKind::Variant(v) => {
+                let cases = v.cases.iter().map(|c| (ident::mangle(&c.name, Style::C), &c.ty));
+                let mut decl = self.variant(v.tag(), cases, &name);
+                for (i, case) in v.cases.iter().enumerate() {
+                    decl.push_str(&format!("#define {} {}\n", self.macro_name(id, &case.name), i));
+                }
+                decl
+            },
+            TypeDefKind::Union(u) => {
+                self.variant(u.tag(), u.cases.iter().enumerate().map(|(i, c)| (format!("f{}", i), &c.ty)), &name)
+            },
+            TypeDefKind::Option(t) => {
+                self.variant(Int::U8, vec![("none".to_string(), &Type::Unit), ("some".to_string(), t)], &name)
+            },
+            TypeDefKind::Expected(e) => {
+                self.variant(Int::U8, vec![("ok".to_string(), &e.ok), ("err".to_string(), &e.err)], &name)
+            },
+            TypeDefKind::Future(_) |
+            TypeDefKind::Stream(_) => format!("typedef uint32_t {};\n", name),
+        };
+        self.out.push_str(&decl);
+        self.out.push('\n');
+    }
+
+    // Unit fields take no space and are left out.
+    fn structure<'b>(&self, fields: impl Iterator<Item = (String, &'b Type)>, name: &str) -> String {
+        let mut decl = "typedef struct {\n".to_string();
+        for (field, ty) in fields {
+            if !crate::is_unit(self.iface, ty) {
+                decl.push_str(&format!("    {} {};\n", self.type_name(ty), field));
+            }
+        }
+        decl.push_str(&format!("}} {};\n", name));
+        decl
+    }
+
+    // A C union places the payload where the canonical ABI does: after the
+    // tag, aligned to the most aligned case.
+    fn variant<'b>(&self, tag: Int, cases: impl IntoIterator<Item = (String, &'b Type)>, name: &str) -> String {
+        let mut payload = String::new();
+        for (case, ty) in cases {
+            if !crate::is_unit(self.iface, ty) {
+                payload.push_str(&format!("        {} {};\n", self.type_name(ty), case));
+            }
+        }
+        let mut decl = format!("typedef struct {{\n    {} tag;\n", tag_type(tag));
+        if !payload.is_empty() {
+            decl.push_str(&format!("    union {{\n{}    }} val;\n", payload));
+        }
+        decl.push_str(&format!("}} {};\n", name));
+        decl
+    }
+
+    fn prototype(&self, func: &Function) -> String {
+        let name: Vec<String> = func.name.split("::").map(|n| ident::mangle(n, Style::C)).collect();
+        let params: Vec<String> = func.params.iter()
+            .filter(|(_, ty)| !crate::is_unit(self.iface, ty))
+            .map(|(n, ty)| format!("{} {}", self.type_name(ty), ident::mangle(n, Style::C)))
+            .collect();
+        let params = if params.is_empty() {
+            "void".to_string()
+        } else {
+            params.join(", ")
+        };
+        let result = if crate::is_unit(self.iface, &func.result) {
+            "void".to_string()
+        } else {
+            self.type_name(&func.result)
+        };
+        format!("{} {}{}({});\n", result, self.prefix, name.join("_"), params)
+    }
+}
+
+fn children(kind: &TypeDefKind) -> Vec<&Type> {
+    match kind {
+        TypeDefKind::Type(t) |
+        TypeDefKind::List(t) |
+        TypeDefKind::Option(t) => vec![t],
+        TypeDefKind::Record(r) => r.fields.iter().map(|f| &f.ty).collect(),
+        TypeDefKind::Tuple(t) => t.types.iter().collect(),
+        TypeDefKind::Variant(v) => v.cases.iter().map(|c| &c.ty).collect(),
+        TypeDefKind::Union(u) => u.cases.iter().map(|c| &c.ty).collect(),
+        TypeDefKind::Expected(e) => vec![&e.ok, &e.err],
+        TypeDefKind::Flags(_) |
+        TypeDefKind::Enum(_) |
+        TypeDefKind::Future(_) |
+        TypeDefKind::Stream(_) => Vec::new(),
+    }
+}
+
+fn tag_type(tag: Int) -> &'static str {
+    match tag {
+        Int::U8 => "uint8_t",
+        Int::U16 => "uint16_t",
+        Int::U32 => "uint32_t",
+        Int::U64 => "uint64_t",
+    }
+}
@@ -0,0 +1,143 @@
+// Words reserved by the WIT lexer.  Identifiers spelled like one of these
+// must be written with a leading `%` in WIT source.
+const KEYWORDS: &[&str] = &[
+    "_", "as", "async", "bool", "char", "enum", "expected", "flags", "float32",
+    "float64", "from", "func", "future", "handle", "interface", "list", "option",
+    "record", "resource", "s16", "s32", "s64", "s8", "static", "stream", "string",
+    "tuple", "type", "u16", "u32", "u64", "u8", "union", "unit", "use", "variant",
+];
+
+pub(crate) fn is_keyword(name: &str) -> bool {
+    KEYWORDS.contains(&name)
+}
+
+// Returns `name` as it must be spelled in WIT source, i.e. `%`-escaped if it
+// collides with a keyword.
+pub(crate) fn escape(name: &str) -> String {
+    if is_keyword(name) {
+        format!("%{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+// Folds `name` for loose comparison: lowercase, with `_` treated as `-`.
+pub(crate) fn normalize(name: &str) -> String {
+    name.trim_start_matches('%')
+        .chars()
+        .map(|c| if c == '_' { '-' } else { c.to_ascii_lowercase() })
+        .collect()
+}
+
+// Target languages for identifier mangling.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Style {
+    C,
+    Cpp,
+    Sql,
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "bool", "break", "case", "char", "const", "continue", "default",
+    "do", "double", "else", "enum", "extern", "false", "float", "for", "goto",
+    "if", "inline", "int", "long", "register", "restrict", "return", "short",
+    "signed", "sizeof", "static", "struct", "switch", "true", "typedef",
+    "union", "unsigned", "void", "volatile", "while",
+];
+
+const CPP_KEYWORDS: &[&str] = &[
+    "alignas", "alignof", "and", "and_eq", "asm", "bitand", "bitor", "catch",
+    "char16_t", "char32_t", "char8_t", "class", "co_await", "co_return",
+    "co_yield", "compl", "concept", "const_cast", "consteval", "constexpr",
+    "constinit", "decltype", "delete", "dynamic_cast", "explicit", "export",
+    "friend", "mutable", "namespace", "new", "noexcept", "not", "not_eq",
+    "nullptr", "operator", "or", "or_eq", "private", "protected", "public",
+    "reinterpret_cast", "requires", "static_assert", "static_cast", "template",
+    "this", "thread_local", "throw", "try", "typeid", "typename", "using",
+    "virtual", "wchar_t", "xor", "xor_eq",
+];
+
+const SQL_KEYWORDS: &[&str] = &[
+    "all", "alter", "and", "as", "asc", "between", "by", "case", "check",
+    "column", "constraint", "create", "database", "default", "delete", "desc",
+    "distinct", "drop", "else", "end", "exists", "false", "for", "foreign",
+    "from", "function", "group", "having", "in", "index", "inner", "insert",
+    "into", "is", "join", "key", "left", "like", "limit", "not", "null", "on",
+    "or", "order", "outer", "primary", "procedure", "references", "return",
+    "right", "select", "set", "table", "then", "to", "true", "union", "unique",
+    "update", "using", "values", "when", "where", "with",
+];
+
+fn is_reserved(name: &str, style: Style) -> bool {
+    match style {
+        Style::C => C_KEYWORDS.contains(&name),
+        Style::Cpp => C_KEYWORDS.contains(&name) || CPP_KEYWORDS.contains(&name),
+        Style::Sql => SQL_KEYWORDS.contains(&name.to_ascii_lowercase().as_str()),
+    }
+}
+
+// Converts a WIT identifier to one that is valid in `style`.  A kebab-case
+// name (lowercase words of letters and digits joined by single dashes) has
+// its dashes turned into underscores, unless that gives a reserved word.
+// Any other name is escaped: every character but a lowercase letter other
+// than `x`, a digit after the first character or, outside SQL, an uppercase
+// letter becomes `x`, its code point in hex and `x`, and a trailing `_` marks the escape, which
+// a plain name can't end with.  So distinct WIT names never map to the same
+// identifier, SQL identifiers are lowercase and survive case folding, and
+// no identifier contains `__` or starts with `_`, which C and C++ reserve.
+pub(crate) fn mangle(name: &str, style: Style) -> String {
+    let name = name.trim_start_matches('%');
+    let plain = name.replace('-', "_");
+    if is_kebab(name) && !is_reserved(&plain, style) {
+        return plain;
+    }
+    let mut res = String::with_capacity(name.len() + 1);
+    for (i, c) in name.chars().enumerate() {
+        let keep = match c {
+            'x' => false,
+            'a'..='z' => true,
+            '0'..='9' => i > 0,
+            'A'..='Z' => style != Style::Sql,
+            _ => false,
+        };
+        if keep {
+            res.push(c);
+        } else {
+            res.push_str(&format!("x{:x}x", c as u32));
+        }
+    }
+    res.push('_');
+    res
+}
+
+// Reverses `mangle`.
+pub(crate) fn demangle(name: &str, _style: Style) -> String {
+    let escaped = match name.strip_suffix('_') {
+        Some(escaped) => escaped,
+        None => return name.replace('_', "-"),
+    };
+    let mut res = String::with_capacity(escaped.len());
+    let mut rest = escaped;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        if c != 'x' {
+            res.push(c);
+            continue;
+        }
+        let code = rest.find('x')
+            .and_then(|end| u32::from_str_radix(&rest[..end], 16).ok().and_then(char::from_u32).map(|c| (c, end)));
+        match code {
+            Some((c, end)) => {
+                res.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => res.push(c),
+        }
+    }
+    res
+}
+
+fn is_kebab(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.split('-').all(|word| !word.is_empty() && word.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()))
+}
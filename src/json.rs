@@ -0,0 +1,273 @@
+use parser::{abi, FunctionKind, Interface, Type, TypeDefKind};
+use crate::{WITSignature, WITStringEncoding, WIT};
+
+// Minimal JSON document model, enough to dump parse results without pulling
+// in a serializer.  Object keys keep their insertion order so dumps are
+// stable across runs.
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Num(u64),
+    Int(i64),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(&'static str, Json)>),
+    Map(Vec<(String, Json)>),  // an object with computed keys
+}
+
+impl Json {
+    pub(crate) fn str(s: &str) -> Json {
+        Json::Str(s.to_string())
+    }
+
+    // Renders the document with two-space indentation and a trailing newline.
+    pub(crate) fn render(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Json::Num(n) => out.push_str(&n.to_string()),
+            Json::Int(n) => out.push_str(&n.to_string()),
+            Json::Str(s) => quote(s, out),
+            Json::Arr(items) if items.is_empty() => out.push_str("[]"),
+            Json::Obj(items) if items.is_empty() => out.push_str("{}"),
+            Json::Arr(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    pad(out, indent + 1);
+                    item.write(out, indent + 1);
+                }
+                out.push('\n');
+                pad(out, indent);
+                out.push(']');
+            },
+            Json::Obj(items) => {
+                write_object(items.iter().map(|(k, v)| (*k, v)), out, indent);
+            },
+            Json::Map(items) if items.is_empty() => out.push_str("{}"),
+            Json::Map(items) => {
+                write_object(items.iter().map(|(k, v)| (k.as_str(), v)), out, indent);
+            },
+        }
+    }
+}
+
+fn write_object<'a>(items: impl Iterator<Item = (&'a str, &'a Json)>, out: &mut String, indent: usize) {
+    out.push('{');
+    for (i, (key, item)) in items.enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        pad(out, indent + 1);
+        quote(key, out);
+        out.push_str(": ");
+        item.write(out, indent + 1);
+    }
+    out.push('\n');
+    pad(out, indent);
+    out.push('}');
+}
+
+fn pad(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn quote(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// Dumps everything known about the interface.  Types are referred to by
+// name if primitive, e.g. "u32", or else by their index into "types".
+pub(crate) fn full(wit: &WIT) -> Json {
+    let iface = &wit.iface;
+    let types = iface.types.iter().map(|(id, def)| {
+        let ty = Type::Id(id);
+        let mut obj = vec![
+            ("index", Json::Num(id.index() as u64)),
+            ("name", def.name.as_deref().map(Json::str).unwrap_or(Json::Null)),
+            ("docs", def.docs.contents.as_deref().map(Json::str).unwrap_or(Json::Null)),
+            ("size", Json::Num(wit.align.size(&ty) as u64)),
+            ("align", Json::Num(wit.align.align(&ty) as u64)),
+        ];
+        obj.extend(kind(iface, &def.kind));
+        Json::Obj(obj)
+    }).collect();
+    let functions = iface.functions.iter().map(|f| {
+        let func = &wit.funcs[&f.name];
+        let (kind, resource) = match &f.kind {
+            FunctionKind::Freestanding => ("freestanding", Json::Null),
+            FunctionKind::Static { resource, .. } => ("static", Json::str(&iface.resources[*resource].name)),
+            FunctionKind::Method { resource, .. } => ("method", Json::str(&iface.resources[*resource].name)),
+        };
+        let params = f.params.iter().map(|(name, ty)| {
+            Json::Obj(vec![("name", Json::str(name)), ("type", type_ref(iface, ty))])
+        }).collect();
+        Json::Obj(vec![
+            ("name", Json::str(&f.name)),
+            ("kind", Json::str(kind)),
+            ("resource", resource),
+            ("docs", f.docs.contents.as_deref().map(Json::str).unwrap_or(Json::Null)),
+            ("params", Json::Arr(params)),
+            ("result", type_ref(iface, &f.result)),
+            ("export_name", Json::Str(func.export_name.to_string_lossy().into_owned())),
+            ("post_return", func.post_return.as_ref()
+                .map(|p| Json::Str(p.to_string_lossy().into_owned()))
+                .unwrap_or(Json::Null)),
+            ("export_signature", signature(&func.sig)),
+            ("import_signature", signature(&func.import_sig)),
+        ])
+    }).collect();
+    let resources = iface.resources.iter().map(|(_, r)| Json::str(&r.name)).collect();
+    Json::Obj(vec![
+        ("name", Json::Str(wit.name.to_string_lossy().into_owned())),
+        ("memory64", Json::Bool(wit.align.memory64())),
+        ("string_encoding", Json::str(match wit.string_encoding {
+            WITStringEncoding::Utf8 => "utf8",
+            WITStringEncoding::Utf16 => "utf16",
+            WITStringEncoding::Latin1Utf16 => "latin1+utf16",
+        })),
+        ("types", Json::Arr(types)),
+        ("resources", Json::Arr(resources)),
+        ("functions", Json::Arr(functions)),
+    ])
+}
+
+// Dumps only the computed ABI facts, for detecting ABI breaks by diffing the
+// output between versions of a guest.  Functions and named types are sorted
+// by name; anonymous types are left out since their indices aren't stable
+// and their layout already shows in whatever refers to them.
+pub(crate) fn abi(wit: &WIT) -> Json {
+    let mut names: Vec<&String> = wit.funcs.keys().collect();
+    names.sort();
+    let functions = names.into_iter().map(|name| {
+        let func = &wit.funcs[name];
+        let (block_size, block_align) = crate::param_block_layout(func);
+        Json::Obj(vec![
+            ("name", Json::str(name)),
+            ("export_name", Json::Str(func.export_name.to_string_lossy().into_owned())),
+            ("post_return", Json::Bool(func.post_return.is_some())),
+            ("param_block_size", Json::Num(block_size as u64)),
+            ("param_block_align", Json::Num(block_align as u64)),
+            ("result_size", Json::Num(func.align.size(&func.res.ty) as u64)),
+            ("result_align", Json::Num(func.align.align(&func.res.ty) as u64)),
+            ("export_signature", signature(&func.sig)),
+            ("import_signature", signature(&func.import_sig)),
+        ])
+    }).collect();
+    let mut types: Vec<(&String, Type)> = wit.iface.types.iter()
+        .filter_map(|(id, def)| def.name.as_ref().map(|n| (n, Type::Id(id))))
+        .collect();
+    types.sort_by(|a, b| a.0.cmp(b.0));
+    let types = types.into_iter().map(|(name, ty)| {
+        Json::Obj(vec![
+            ("name", Json::str(name)),
+            ("size", Json::Num(wit.align.size(&ty) as u64)),
+            ("align", Json::Num(wit.align.align(&ty) as u64)),
+        ])
+    }).collect();
+    Json::Obj(vec![
+        ("memory64", Json::Bool(wit.align.memory64())),
+        ("max_flat_params", Json::Num(wit.max_flat_params as u64)),
+        ("max_flat_results", Json::Num(wit.max_flat_results as u64)),
+        ("types", Json::Arr(types)),
+        ("functions", Json::Arr(functions)),
+    ])
+}
+
+fn kind(iface: &Interface, kind: &TypeDefKind) -> Vec<(&'static str, Json)> {
+    let named = |name: &str, ty: &Type| {
+        Json::Obj(vec![("name", Json::str(name)), ("type", type_ref(iface, ty))])
+    };
+    match kind {
+        TypeDefKind::Type(t) => vec![("kind", Json::str("type")), ("type", type_ref(iface, t))],
+        TypeDefKind::Record(r) => vec![
+            ("kind", Json::str("record")),
+            ("fields", Json::Arr(r.fields.iter().map(|f| named(&f.name, &f.ty)).collect())),
+        ],
+        TypeDefKind::Tuple(t) => vec![
+            ("kind", Json::str("tuple")),
+            ("types", Json::Arr(t.types.iter().map(|t| type_ref(iface, t)).collect())),
+        ],
+        TypeDefKind::Flags(f) => vec![
+            ("kind", Json::str("flags")),
+            ("flags", Json::Arr(f.flags.iter().map(|f| Json::str(&f.name)).collect())),
+        ],
+        TypeDefKind::Enum(e) => vec![
+            ("kind", Json::str("enum")),
+            ("cases", Json::Arr(e.cases.iter().map(|c| Json::str(&c.name)).collect())),
+        ],
+        TypeDefKind::Variant(v) => vec![
+            ("kind", Json::str("variant")),
+            ("cases", Json::Arr(v.cases.iter().map(|c| named(&c.name, &c.ty)).collect())),
+        ],
+        TypeDefKind::Union(u) => vec![
+            ("kind", Json::str("union")),
+            ("cases", Json::Arr(u.cases.iter().map(|c| type_ref(iface, &c.ty)).collect())),
+        ],
+        TypeDefKind::Option(t) => vec![("kind", Json::str("option")), ("type", type_ref(iface, t))],
+        TypeDefKind::Expected(e) => vec![
+            ("kind", Json::str("expected")),
+            ("ok", type_ref(iface, &e.ok)),
+            ("err", type_ref(iface, &e.err)),
+        ],
+        TypeDefKind::List(t) => vec![("kind", Json::str("list")), ("type", type_ref(iface, t))],
+        TypeDefKind::Future(t) => vec![("kind", Json::str("future")), ("type", type_ref(iface, t))],
+        TypeDefKind::Stream(s) => vec![
+            ("kind", Json::str("stream")),
+            ("element", type_ref(iface, &s.element)),
+            ("end", type_ref(iface, &s.end)),
+        ],
+    }
+}
+
+fn type_ref(iface: &Interface, ty: &Type) -> Json {
+    Json::str(match ty {
+        Type::Id(id) => return Json::Num(id.index() as u64),
+        Type::Handle(r) => return Json::Str(format!("handle<{}>", iface.resources[*r].name)),
+        Type::Unit => "unit",
+        Type::Bool => "bool",
+        Type::U8 => "u8",
+        Type::U16 => "u16",
+        Type::U32 => "u32",
+        Type::U64 => "u64",
+        Type::S8 => "s8",
+        Type::S16 => "s16",
+        Type::S32 => "s32",
+        Type::S64 => "s64",
+        Type::Float32 => "float32",
+        Type::Float64 => "float64",
+        Type::Char => "char",
+        Type::String => "string",
+    })
+}
+
+fn signature(sig: &WITSignature) -> Json {
+    let types = |tys: &[abi::WasmType]| {
+        Json::Arr(tys.iter().map(|t| Json::str(crate::wasm_type_name((*t).into()))).collect())
+    };
+    Json::Obj(vec![
+        ("params", types(&sig.sig.params)),
+        ("results", types(&sig.sig.results)),
+        ("indirect_params", Json::Bool(sig.sig.indirect_params)),
+        ("retptr", Json::Bool(sig.sig.retptr)),
+    ])
+}
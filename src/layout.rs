@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use parser::abi::WasmType;
+use parser::{FlagsRepr, Int, Interface, SizeAlign, Type, TypeDefKind};
+use std::sync::Arc;
+use crate::WITStringEncoding;
+
+// Sizes and alignments of types in guest memory.  wit-parser's SizeAlign
+// assumes 32-bit pointers; for memory64 guests, strings and lists (the only
+// types holding pointers) are a 64-bit pointer and a 64-bit length, and
+// everything containing them is laid out here instead.  The string encoding
+// doesn't change any type's layout, only that of the string data itself.
+pub(crate) struct Layout {
+    iface:    Arc<Interface>,
+    sizes:    SizeAlign,
+    memory64: bool,
+    encoding: WITStringEncoding,
+    table:    Option<Vec<(usize, usize)>>,  // precomputed, see `with_table`
+}
+
+impl Layout {
+    pub(crate) fn new(iface: &Arc<Interface>, memory64: bool, encoding: WITStringEncoding) -> Layout {
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+        Layout {
+            iface: iface.clone(),
+            sizes,
+            memory64,
+            encoding,
+            table: None,
+        }
+    }
+
+    // Takes the (size, align) of each type, indexed like `iface.types`, from
+    // a previous layout of the interface instead of computing them.  Fails if
+    // an entry doesn't follow from its type's kind and the entries of the
+    // types it refers to, so each is checked in a single pass.
+    pub(crate) fn with_table(iface: &Arc<Interface>, memory64: bool, encoding: WITStringEncoding, table: Vec<(usize, usize)>) -> Result<Layout> {
+        if table.len() != iface.types.len() {
+            return Err(anyhow!("Layout has {} entries for {} types", table.len(), iface.types.len()));
+        }
+        let layout = Layout {
+            iface: iface.clone(),
+            sizes: SizeAlign::default(),
+            memory64,
+            encoding,
+            table: Some(table),
+        };
+        for (id, def) in iface.types.iter() {
+            let entry = layout.precomputed(&Type::Id(id));
+            if entry != Some(layout.kind_layout(&def.kind)) {
+                return Err(anyhow!("Layout of type {} doesn't match its definition", id.index()));
+            }
+        }
+        Ok(layout)
+    }
+
+    fn precomputed(&self, ty: &Type) -> Option<(usize, usize)> {
+        match (&self.table, ty) {
+            (Some(table), Type::Id(id)) => table.get(id.index()).copied(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn memory64(&self) -> bool {
+        self.memory64
+    }
+
+    pub(crate) fn string_encoding(&self) -> WITStringEncoding {
+        self.encoding
+    }
+
+    // Core type of pointers and lengths.
+    pub(crate) fn ptr_type(&self) -> WasmType {
+        if self.memory64 {
+            WasmType::I64
+        } else {
+            WasmType::I32
+        }
+    }
+
+    pub(crate) fn size(&self, ty: &Type) -> usize {
+        if let Some((size, _)) = self.precomputed(ty) {
+            return size;
+        }
+        if !self.memory64 {
+            return self.sizes.size(ty);
+        }
+        match ty {
+            Type::String => 16,
+            Type::Id(id) => self.kind_layout(&self.iface.types[*id].kind).0,
+            _ => self.sizes.size(ty),
+        }
+    }
+
+    pub(crate) fn align(&self, ty: &Type) -> usize {
+        if let Some((_, align)) = self.precomputed(ty) {
+            return align;
+        }
+        if !self.memory64 {
+            return self.sizes.align(ty);
+        }
+        match ty {
+            Type::String => 8,
+            Type::Id(id) => self.kind_layout(&self.iface.types[*id].kind).1,
+            _ => self.sizes.align(ty),
+        }
+    }
+
+    // Returns the (size, align) of a type of `kind`, as wit-parser's SizeAlign
+    // computes them but with 64-bit pointers for memory64.
+    fn kind_layout(&self, kind: &TypeDefKind) -> (usize, usize) {
+        let ptr = if self.memory64 { 8 } else { 4 };
+        let variant = |(size, align, _): (usize, usize, usize)| (size, align);
+        match kind {
+            TypeDefKind::Type(t) => (self.size(t), self.align(t)),
+            TypeDefKind::List(_) => (2 * ptr, ptr),
+            TypeDefKind::Record(r) => self.record(r.fields.iter().map(|f| &f.ty)),
+            TypeDefKind::Tuple(t) => self.record(&t.types),
+            TypeDefKind::Flags(f) => match f.repr() {
+                FlagsRepr::U8 => (1, 1),
+                FlagsRepr::U16 => (2, 2),
+                FlagsRepr::U32(n) => (n * 4, 4),
+            },
+            TypeDefKind::Variant(v) => variant(self.variant(v.tag(), v.cases.iter().map(|c| &c.ty))),
+            TypeDefKind::Enum(e) => variant(self.variant(e.tag(), [])),
+            TypeDefKind::Union(u) => variant(self.variant(u.tag(), u.cases.iter().map(|c| &c.ty))),
+            TypeDefKind::Option(t) => variant(self.variant(Int::U8, Some(t))),
+            TypeDefKind::Expected(e) => variant(self.variant(Int::U8, [&e.ok, &e.err])),
+            TypeDefKind::Future(_) |
+            TypeDefKind::Stream(_) => (4, 4),
+        }
+    }
+
+    pub(crate) fn field_offsets<'a>(&self, types: impl IntoIterator<Item = &'a Type>) -> Vec<usize> {
+        if !self.memory64 && self.table.is_none() {
+            return self.sizes.field_offsets(types);
+        }
+        let mut offsets = Vec::new();
+        let mut size = 0;
+        for ty in types {
+            let off = crate::align_to(size, self.align(ty));
+            offsets.push(off);
+            size = off + self.size(ty);
+        }
+        offsets
+    }
+
+    pub(crate) fn payload_offset<'a>(&self, tag: Int, cases: impl IntoIterator<Item = &'a Type>) -> usize {
+        if !self.memory64 && self.table.is_none() {
+            return self.sizes.payload_offset(tag, cases);
+        }
+        self.variant(tag, cases).2
+    }
+
+    // Returns the (size, align) of a record with the given field types.
+    fn record<'a>(&self, types: impl IntoIterator<Item = &'a Type>) -> (usize, usize) {
+        let mut size = 0;
+        let mut align = 1;
+        for ty in types {
+            let a = self.align(ty);
+            size = crate::align_to(size, a) + self.size(ty);
+            align = align.max(a);
+        }
+        (crate::align_to(size, align), align)
+    }
+
+    // Returns the (size, align, payload offset) of a variant.
+    fn variant<'a>(&self, tag: Int, cases: impl IntoIterator<Item = &'a Type>) -> (usize, usize, usize) {
+        let tag_size = match tag {
+            Int::U8 => 1,
+            Int::U16 => 2,
+            Int::U32 => 4,
+            Int::U64 => 8,
+        };
+        let mut payload_size = 0;
+        let mut payload_align = 1;
+        for ty in cases {
+            payload_size = payload_size.max(self.size(ty));
+            payload_align = payload_align.max(self.align(ty));
+        }
+        let align = payload_align.max(tag_size);
+        let payload_offset = crate::align_to(tag_size, payload_align);
+        (crate::align_to(payload_offset + payload_size, align), align, payload_offset)
+    }
+}
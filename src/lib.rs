@@ -3,21 +3,51 @@ extern crate libc;
 use anyhow::{anyhow, Result};
 use core::slice;
 use core::slice::Iter;
+use core::iter::Enumerate;
 use core::iter::Iterator;
-use libc::c_char;
+use libc::{c_char, c_void};
 use std::collections::HashMap;
 use std::ffi::{CStr, CString};
+use std::fs;
+use std::path::PathBuf;
 use std::ptr;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::str;
-use parser::TypeDefKind;
+use parser::{Docs, FunctionKind, TypeDefKind};
 use parser::abi;
-use parser::{Interface, Int, Case, Field, Type, SizeAlign};
+use parser::{Interface, Int, Case, Field, Type, TypeId, UnionCase};
+use layout::Layout;
 
+mod avro;
+mod ddl;
+mod diff;
+mod dot;
+mod golang;
+mod guest;
+mod header;
+mod ident;
+mod json;
+mod layout;
+mod lift;
+mod lint;
+mod location;
+mod lower;
+mod markdown;
+mod modern;
+mod module;
 #[cfg(feature="catch_panics")]
-use std::panic::catch_unwind;
+mod panics;
+mod plan;
+mod project;
+mod proto;
+mod report;
+mod snapshot;
+mod schema;
+mod tl;
+mod typescript;
 
 #[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq)]
 #[repr(C)]
 pub enum WASMType {
     I32,
@@ -38,28 +68,494 @@ impl From<abi::WasmType> for WASMType {
 
 pub struct WITSession {
     error: Option<WITError>,
+    options: ParseOptions,
 }
 
+// Canonical ABI limits on the number of core params and results; beyond
+// them, params are passed indirectly and results through a return pointer.
+const MAX_FLAT_PARAMS: usize = 16;
+const MAX_FLAT_RESULTS: usize = 1;
+
+// Session-wide settings applied by the parse functions.
+#[derive(Clone)]
+struct ParseOptions {
+    resolver: Option<Resolver>,
+    string_encoding: WITStringEncoding,
+    memory64: bool,
+    max_flat_params: usize,
+    max_flat_results: usize,
+    renames: Arc<HashMap<String, String>>,  // WIT name to exposed name
+    warnings: Arc<Mutex<Vec<CString>>>,     // shared with the session
+    logger: Option<Logger>,
+}
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            resolver: None,
+            string_encoding: WITStringEncoding::Utf8,
+            memory64: false,
+            max_flat_params: MAX_FLAT_PARAMS,
+            max_flat_results: MAX_FLAT_RESULTS,
+            renames: Arc::default(),
+            warnings: Arc::default(),
+            logger: None,
+        }
+    }
+}
+impl ParseOptions {
+    // Passes the message to the session's logger if it wants `level`.
+    fn log(&self, level: WITLogLevel, msg: impl FnOnce() -> String) {
+        if let Some(logger) = &self.logger {
+            if level <= logger.level {
+                if let Ok(msg) = CString::new(msg()) {
+                    (logger.cb)(logger.user_data, level, msg.as_ptr());
+                }
+            }
+        }
+    }
+
+    // Reports the outcome of parsing an interface: the error, or a summary,
+    // types only partly supported and the warnings, which are also kept for
+    // wit_warning_get.
+    fn parsed(&self, res: Result<Interface>) -> Result<Interface> {
+        let iface = match res {
+            Ok(iface) => iface,
+            Err(e) => {
+                self.log(WITLogLevel::Error, || e.to_string());
+                return Err(e);
+            },
+        };
+        self.log(WITLogLevel::Info, || format!(
+            "Parsed `{}`: {} functions, {} types, {} resources",
+            iface.name, iface.functions.len(), iface.types.iter().count(), iface.resources.iter().count()));
+        for (id, def) in iface.types.iter() {
+            if let TypeDefKind::Future(_) | TypeDefKind::Stream(_) = def.kind {
+                self.log(WITLogLevel::Warn, || format!(
+                    "{}: `{}` is only supported as an opaque i32 handle",
+                    iface.name, markdown::type_expr(&iface, &Type::Id(id))));
+            }
+        }
+        let warnings = lint::lint(&iface);
+        for w in &warnings {
+            self.log(WITLogLevel::Warn, || w.clone());
+        }
+        self.warnings.lock().unwrap().extend(warnings.into_iter().filter_map(|w| CString::new(w).ok()));
+        Ok(iface)
+    }
+}
+
+// Severity of a message passed to a WITLogger.  A logger set for a level
+// gets messages of that level and the ones before it.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+#[repr(C)]
+pub enum WITLogLevel {
+    Error,  // parse failures
+    Warn,   // parse warnings (see wit_warning_get) and partly supported types
+    Info,   // interfaces parsed
+    Debug,  // modules loaded and ABI decisions, e.g. indirect parameters
+}
+
+// Called with each message at or above the session's log level.  `msg` is
+// only valid during the call.
+pub type WITLogger = extern "C" fn(user_data: *mut c_void, level: WITLogLevel, msg: *const c_char);
+
+#[derive(Clone, Copy)]
+struct Logger {
+    cb:        WITLogger,
+    user_data: *mut c_void,
+    level:     WITLogLevel,
+}
+
+// String encodings the guest may be compiled with (the canonical ABI
+// `string-encoding` option).
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum WITStringEncoding {
+    Utf8,
+    Utf16,
+    Latin1Utf16,
+}
+
+// Called to fetch the contents of a module pulled in with `use`.  Returns
+// false if the module can't be found.  On success, `content` and `len` must
+// describe UTF-8 text that stays valid until the parse call returns.
+pub type WITResolver = extern "C" fn(user_data: *mut c_void, module: *const c_char, content: *mut *const u8, len: *mut usize) -> bool;
+
+#[derive(Clone, Copy)]
+struct Resolver {
+    cb:        WITResolver,
+    user_data: *mut c_void,
+}
+impl Resolver {
+    fn load(&self, module: &str) -> Result<(PathBuf, String)> {
+        let c_module = CString::new(module)?;
+        let mut content: *const u8 = ptr::null();
+        let mut len: usize = 0;
+        if !(self.cb)(self.user_data, c_module.as_ptr(), &mut content, &mut len) || content.is_null() {
+            return Err(anyhow!("Could not resolve module `{}`", module));
+        }
+        let content = unsafe {
+            str::from_utf8(slice::from_raw_parts(content, len))?
+        };
+        Ok((PathBuf::from(format!("{}.wit", module)), content.to_string()))
+    }
+}
+
+// A parsed interface.  Nothing in it changes after parsing except caches
+// filled on first access, which are OnceLocks, so a WIT and everything
+// obtained from it may be used by several threads at once, each with its
+// own session (or the `_tl` entry points, see tl.rs).
 pub struct WIT {
-    iface: Rc<Interface>,
+    iface: Arc<Interface>,
+    name:  CString,
     funcs: HashMap<String, WITFunction>,    // Function name to index
-    align: Rc<SizeAlign>
+    types: Vec<WITTypeDef>,                 // Indexed like `iface.types`
+    resources: Vec<WITResource>,            // Indexed like `iface.resources`
+    align: Arc<Layout>,
+    string_encoding: WITStringEncoding,
+    max_flat_params: usize,
+    max_flat_results: usize,
 }
 impl<'a> WIT {
-    fn new(wit: &str) -> Result<WIT> {
-        let iface = Rc::new(Interface::parse("wit", &wit)?);
-        let mut align = SizeAlign::default();
-        align.fill(&iface);
-        Ok(
-            WIT { 
-                iface,
-                funcs: HashMap::new(),
-                align: Rc::new(align)
+    fn new(name: &str, wit: &str, opts: ParseOptions) -> Result<WIT> {
+        WIT::new_in(name, wit, &[], opts)
+    }
+
+    // Like new, but a `use` of one of `docs` (a module name and its text) is
+    // resolved to it rather than through the resolver.
+    fn new_in(name: &str, wit: &str, docs: &[(&str, &str)], opts: ParseOptions) -> Result<WIT> {
+        // Modules loaded with `use`, to locate errors in them.
+        let mut loaded: Vec<(PathBuf, String)> = Vec::new();
+        opts.log(WITLogLevel::Info, || format!("Parsing `{}` ({} bytes)", name, wit.len()));
+        let parsed = Interface::parse_with(name, &wit, |module| {
+            let (path, content) = match (docs.iter().find(|(doc, _)| *doc == module), opts.resolver) {
+                (Some((_, content)), _) => (PathBuf::from(format!("{}.wit", module)), content.to_string()),
+                (None, Some(r)) => {
+                    opts.log(WITLogLevel::Debug, || format!("Loading module `{}` through the resolver", module));
+                    r.load(module)?
+                },
+                (None, None) => return Err(anyhow!("Could not resolve module `{}`", module)),
+            };
+            loaded.push((path.clone(), content.clone()));
+            Ok((path, content))
+        });
+        let iface = opts.parsed(parsed.map_err(|e| location::locate(e, |file| {
+            if file == name {
+                return Some(wit.to_string());
             }
-        )
+            loaded.iter().find(|(path, _)| path.display().to_string() == file).map(|(_, content)| content.clone())
+        })))?;
+        WIT::from_interface(iface, opts)
+    }
+
+    fn from_interface(mut iface: Interface, opts: ParseOptions) -> Result<WIT> {
+        let export_names = project::rename(&mut iface, &opts.renames)?;
+        let iface = Arc::new(iface);
+        let align = Layout::new(&iface, opts.memory64, opts.string_encoding);
+        let abis = iface.functions.iter().zip(export_names)
+            .map(|(f, export_name)| FuncAbi {
+                sig: wasm_signature(&iface, &align, &opts, abi::AbiVariant::GuestExport, f),
+                import_sig: wasm_signature(&iface, &align, &opts, abi::AbiVariant::GuestImport, f),
+                post_return: if owns_memory(&iface, &f.result) {
+                    Some(format!("cabi_post_{}", export_name))
+                } else {
+                    None
+                },
+                export_name,
+            })
+            .collect::<Vec<_>>();
+        for (f, fabi) in iface.functions.iter().zip(&abis) {
+            opts.log(WITLogLevel::Debug, || {
+                let mut msg = format!("{}: `{}` is exported as `{}` with {} core params",
+                    iface.name, f.name, fabi.export_name, fabi.sig.params.len());
+                if fabi.sig.indirect_params {
+                    msg.push_str(", passed indirectly");
+                }
+                if fabi.sig.retptr {
+                    msg.push_str(", returning through a pointer");
+                }
+                if let Some(post) = &fabi.post_return {
+                    msg.push_str(&format!(", cleaned up by `{}`", post));
+                }
+                msg
+            });
+        }
+        WIT::build(iface, align, &opts, abis)
+    }
+
+    // Wraps an interface whose layout and function signatures are already
+    // computed, with `abis` indexed like `iface.functions`.
+    fn build(iface: Arc<Interface>, align: Layout, opts: &ParseOptions, abis: Vec<FuncAbi>) -> Result<WIT> {
+        let mut wit = WIT { 
+            name:  CString::new(iface.name.as_str())?,
+            iface,
+            funcs: HashMap::new(),
+            types: Vec::new(),
+            resources: Vec::new(),
+            align: Arc::new(align),
+            string_encoding: opts.string_encoding,
+            max_flat_params: opts.max_flat_params,
+            max_flat_results: opts.max_flat_results,
+        };
+
+        // Create a map of each function's name to its index into the interface,
+        // wrapping the WASM signature for each function along the way.
+        let funcs = &wit.iface.functions;
+        for (i, fabi) in abis.into_iter().enumerate() {
+            let sig = WITSignature::new(abi::AbiVariant::GuestExport, fabi.sig)?;
+            let import_sig = WITSignature::new(abi::AbiVariant::GuestImport, fabi.import_sig)?;
+            let res_ty = funcs[i].result.clone();
+            let results = match &res_ty {
+                Type::Unit => Vec::new(),
+                ty => vec![(String::new(), ty.clone())],
+            };
+            let mut params = Vec::new();
+            for (name, ty) in &funcs[i].params {
+                params.push(WITTypeDef::new(&wit.iface, &wit.align, name.as_str(), ty.clone())?);
+            }
+            wit.funcs.insert(
+                funcs[i].name.clone(), 
+                WITFunction {
+                    iface: wit.iface.clone(),
+                    align: wit.align.clone(),
+                    name:  CString::new(funcs[i].name.as_str())?,
+                    raw_name: CString::new(ident::escape(&funcs[i].name))?,
+                    docs:  docs_cstring(&funcs[i].docs)?,
+                    export_name: CString::new(fabi.export_name)?,
+                    post_return: fabi.post_return.map(CString::new).transpose()?,
+                    sig,
+                    import_sig,
+                    index: i,
+                    params,
+                    res:   WITTypeDef::new(&wit.iface, &wit.align, "", res_ty)?,
+                    results,
+                    lift_plan: OnceLock::new(),
+                    cost:  OnceLock::new(),
+                }
+            );
+        }
+
+        // Wrap every type defined in the interface, named or not.
+        for (id, td) in wit.iface.types.iter() {
+            let name = td.name.as_deref().unwrap_or("");
+            wit.types.push(WITTypeDef::new(&wit.iface, &wit.align, name, Type::Id(id))?);
+        }
+
+        // Group methods and statics by resource, in declaration order.  The
+        // functions are never moved once `funcs` is built, so it's safe to
+        // point into it.
+        for (rid, r) in wit.iface.resources.iter() {
+            let mut funcs = Vec::new();
+            for f in &wit.iface.functions {
+                match &f.kind {
+                    FunctionKind::Method { resource, .. } |
+                    FunctionKind::Static { resource, .. } if *resource == rid => {
+                        funcs.push(&wit.funcs[&f.name] as *const WITFunction);
+                    },
+                    _ => {}
+                }
+            }
+            wit.resources.push(
+                WITResource {
+                    name: CString::new(r.name.as_str())?,
+                    funcs,
+                }
+            );
+        }
+        Ok(wit)
+    }
+
+    // The options the interface was parsed with, less the resolver.
+    fn options(&self) -> ParseOptions {
+        ParseOptions {
+            resolver: None,
+            string_encoding: self.string_encoding,
+            memory64: self.align.memory64(),
+            max_flat_params: self.max_flat_params,
+            max_flat_results: self.max_flat_results,
+            renames: Arc::default(),
+            warnings: Arc::default(),
+            logger: None,
+        }
+    }
+
+    // Takes the core export names of functions also in `from`, for WITs
+    // built from the interface of another.
+    fn inherit_exports(&mut self, from: &WIT) {
+        for (name, f) in self.funcs.iter_mut() {
+            if let Some(src) = from.funcs.get(name) {
+                f.export_name = src.export_name.clone();
+                f.post_return = src.post_return.clone();
+            }
+        }
+    }
+}
+
+// A function's core signatures and exports, see WIT::build.
+struct FuncAbi {
+    sig:         abi::WasmSignature,
+    import_sig:  abi::WasmSignature,
+    export_name: String,
+    post_return: Option<String>,
+}
+
+pub struct WITResource {
+    name:  CString,
+    funcs: Vec<*const WITFunction>,  // methods and statics, owned by the WIT
+}
+// The functions belong to the WIT, which never changes them once built, so
+// sharing the pointers between threads is as safe as sharing the WIT.
+unsafe impl Send for WITResource {}
+unsafe impl Sync for WITResource {}
+
+// Fails to compile if anything not thread-safe gets into a WIT.
+const _: fn() = || {
+    fn thread_safe<T: Send + Sync>() {}
+    thread_safe::<WIT>();
+    thread_safe::<WITCollection>();
+    thread_safe::<WITDiff>();
+};
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub enum WITFuncKind {
+    Freestanding,
+    Method,
+    Static,
+}
+
+// The interfaces parsed by wit_parse_all or wit_parse_module.
+pub struct WITCollection {
+    wits: Vec<WIT>,
+}
+
+// Bytes laid out according to the canonical ABI, see wit_buffer_new.
+pub struct WITBuffer {
+    data:  Vec<u8>,
+    align: usize,  // largest alignment of any value appended
+    last:  Option<usize>,  // offset of the last value appended
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum WITChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum WITItemKind {
+    Function,
+    Type,
+}
+
+// How a new version of an interface affects callers of the old one, from
+// least to most severe; see wit_compat_check.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(C)]
+pub enum WITCompat {
+    Compatible,
+    Additive,
+    Breaking,
+}
+
+// One entry of a WITDiff.  `detail` is the signature or definition of an
+// added or removed item, or a description of what changed.
+pub struct WITChange {
+    kind:   WITChangeKind,
+    item:   WITItemKind,
+    name:   CString,
+    detail: CString,
+}
+
+// The differences between two interfaces, see wit_diff.
+pub struct WITDiff {
+    changes: Vec<WITChange>,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum WITAbiVariant {
+    GuestImport,
+    GuestExport,
+}
+impl From<WITAbiVariant> for abi::AbiVariant {
+    fn from(v: WITAbiVariant) -> Self {
+        match v {
+            WITAbiVariant::GuestImport => abi::AbiVariant::GuestImport,
+            WITAbiVariant::GuestExport => abi::AbiVariant::GuestExport,
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum WITIdentStyle {
+    C,
+    Cpp,
+    Sql,
+}
+impl From<WITIdentStyle> for ident::Style {
+    fn from(v: WITIdentStyle) -> Self {
+        match v {
+            WITIdentStyle::C => ident::Style::C,
+            WITIdentStyle::Cpp => ident::Style::Cpp,
+            WITIdentStyle::Sql => ident::Style::Sql,
+        }
     }
 }
 
+// Operations of a lifting plan, see wit_func_lift_plan_get.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum WITPlanOp {
+    Value,    // scalar, char, handle or enum discriminant
+    String,   // pointer and length
+    List,     // pointer and length; child is the element plan
+    Record,   // children are the fields
+    Tuple,    // children are the elements
+    Flags,    // `count` 32-bit words
+    Variant,  // discriminant; children are one Case per case
+    Case,     // `loc` is the discriminant; child is the payload plan
+}
+
+// One instruction of a lifting plan.  `loc` is an index into the core values
+// if `memory` is false, otherwise a byte offset from the current base address
+// (the return pointer, or the list element being read).  `size` is the byte
+// size of the value in memory, or the element stride for lists.  The next
+// `skip` instructions make up this instruction's `count` children.
+#[repr(C)]
+pub struct WITPlanInstr {
+    pub op:     WITPlanOp,
+    pub ty:     WITType,
+    pub memory: bool,
+    pub loc:    usize,
+    pub size:   usize,
+    pub count:  usize,
+    pub skip:   usize,
+}
+
+// Rough cost of lowering a function's params and lifting its results, see
+// wit_func_cost_get.  `instrs` counts plan instructions over all params and
+// results; lists and strings imply guest allocations and per-element work.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct WITFuncCost {
+    pub instrs:      usize,
+    pub has_strings: bool,
+    pub has_lists:   bool,
+}
+
 #[allow(non_camel_case_types)]
 #[repr(C)]
 pub enum WITSigPart {
@@ -68,46 +564,227 @@ pub enum WITSigPart {
 }
 
 pub struct WITSignature {
-    sig: abi::WasmSignature,
+    sig:  abi::WasmSignature,
+    text: CString,  // e.g. `(i32, i32) -> i32`
+    retptr_param: bool,  // true if the return pointer is the last param
+}
+impl WITSignature {
+    fn new(variant: abi::AbiVariant, sig: abi::WasmSignature) -> Result<WITSignature> {
+        let join = |tys: &[abi::WasmType]| {
+            tys.iter()
+                .map(|t| wasm_type_name((*t).into()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let text = match sig.results.as_slice() {
+            [] => format!("({})", join(&sig.params)),
+            [t] => format!("({}) -> {}", join(&sig.params), wasm_type_name((*t).into())),
+            ts => format!("({}) -> ({})", join(&sig.params), join(ts)),
+        };
+        let retptr_param = sig.retptr && variant == abi::AbiVariant::GuestImport;
+        Ok(WITSignature { sig, text: CString::new(text)?, retptr_param })
+    }
 }
 
 pub struct WITFunction {
-    iface: Rc<Interface>,
-    align: Rc<SizeAlign>,
+    iface: Arc<Interface>,
+    align: Arc<Layout>,
     name:  CString,
-    sig:   WITSignature,
+    raw_name: CString,  // `name` as spelled in WIT, %-escaped if needed
+    docs:  CString,
+    export_name: CString,  // symbol the guest module exports the function as
+    post_return: Option<CString>,  // cleanup export, if the result owns memory
+    sig:   WITSignature,  // GuestExport signature
+    import_sig: WITSignature,  // GuestImport signature
     index: usize,  // function index
+    params: Vec<WITTypeDef>,
     res:   WITTypeDef,
+    results: Vec<(String, Type)>,  // empty if the result is unit
+    lift_plan: OnceLock<Vec<WITPlanInstr>>,  // see `plan::lift_plan`
+    cost:  OnceLock<WITFuncCost>,  // see wit_func_cost_get
+}
+
+pub struct WITFuncIter<'a> {
+    funcs:       &'a HashMap<String, WITFunction>,
+    inner_iter:  Iter<'a, parser::Function>,
+    item:        Option<&'a WITFunction>
 }
 
 pub struct WITTypeDefIter<'a> {
-    iface:       Rc<Interface>,
-    align:       Rc<SizeAlign>,
+    iface:       Arc<Interface>,
+    align:       Arc<Layout>,
     inner_iter:  Iter<'a, (String, Type)>,
     item:        Option<WITTypeDef>
 }
 
 pub struct WITFieldIter<'a> {
-    iface:       Rc<Interface>,
-    align:       Rc<SizeAlign>,
+    iface:       Arc<Interface>,
+    align:       Arc<Layout>,
     inner_iter:  Iter<'a, Field>,
     item:        Option<WITTypeDef>
 }
 
 pub struct WITCaseIter<'a> {
-    iface:       Rc<Interface>,
-    align:       Rc<SizeAlign>,
-    inner_iter:  Iter<'a, Case>,
+    iface:       Arc<Interface>,
+    align:       Arc<Layout>,
+    inner_iter:  Enumerate<Iter<'a, Case>>,
+    item:        Option<WITTypeDef>
+}
+
+pub struct WITUnionCaseIter<'a> {
+    iface:       Arc<Interface>,
+    align:       Arc<Layout>,
+    inner_iter:  Enumerate<Iter<'a, UnionCase>>,
     item:        Option<WITTypeDef>
 }
 
+// Child types are resolved from the type id on first access and cached, so
+// building a typedef never recurses into the types it contains.  Once
+// resolved, every child getter is a constant-time lookup.
 pub struct WITTypeDef {
-    iface:       Rc<Interface>,
-    align:       Rc<SizeAlign>,
+    iface:       Arc<Interface>,
+    align:       Arc<Layout>,
     name:        CString,
+    raw_name:    CString,  // `name` as spelled in WIT, %-escaped if needed
+    typename:    CString,  // declared name of the type, if any
+    docs:        CString,  // field/case docs, or else the declared type's docs
     ty:          Type,
-    subty1:      Option<Box<WITTypeDef>>,
-    subty2:      Option<Box<WITTypeDef>>,
+    names:       Vec<CString>,  // flag or enum case names
+    children:    OnceLock<Vec<WITTypeDef>>,  // see `children_get`
+    discr:       Option<u32>,  // discriminant, for variant and union cases
+}
+impl WITTypeDef {
+    fn new(iface: &Arc<Interface>, align: &Arc<Layout>, name: &str, ty: Type) -> Result<WITTypeDef> {
+        let mut names = Vec::new();
+        let mut typename = CString::default();
+        let mut docs = CString::default();
+        if let Type::Handle(rid) = &ty {
+            typename = CString::new(iface.resources[*rid].name.as_str())?;
+            docs = docs_cstring(&iface.resources[*rid].docs)?;
+        }
+        if let Type::Id(id) = &ty {
+            if let Some(n) = &iface.types[*id].name {
+                typename = CString::new(n.as_str())?;
+            }
+            docs = docs_cstring(&iface.types[*id].docs)?;
+            match &iface.types[*id].kind {
+                TypeDefKind::Flags(f) => {
+                    for flag in &f.flags {
+                        names.push(CString::new(flag.name.as_str())?);
+                    }
+                },
+                TypeDefKind::Enum(e) => {
+                    for case in &e.cases {
+                        names.push(CString::new(case.name.as_str())?);
+                    }
+                },
+                _ => {}
+            }
+        }
+        Ok(
+            WITTypeDef {
+                iface:  iface.clone(),
+                align:  align.clone(),
+                name:   CString::new(name)?,
+                raw_name: CString::new(ident::escape(name))?,
+                typename,
+                docs,
+                ty,
+                names,
+                children: OnceLock::new(),
+                discr:  None,
+            }
+        )
+    }
+
+    fn with_discriminant(mut self, discr: usize) -> WITTypeDef {
+        self.discr = Some(discr as u32);
+        self
+    }
+
+    // Used for record fields and cases, which carry their own docs.
+    fn with_docs(mut self, docs: &Docs) -> Result<WITTypeDef> {
+        self.docs = docs_cstring(docs)?;
+        Ok(self)
+    }
+
+    fn children(&self) -> Result<&[WITTypeDef]> {
+        if self.children.get().is_none() {
+            let _ = self.children.set(children_get(&self.iface, &self.align, &self.ty)?);
+        }
+        Ok(self.children.get().map(|c| c.as_slice()).unwrap_or(&[]))
+    }
+
+    fn child(&self, index: usize) -> Result<Option<&WITTypeDef>> {
+        Ok(self.children()?.get(index))
+    }
+}
+
+fn docs_cstring(docs: &Docs) -> Result<CString> {
+    Ok(CString::new(docs.contents.as_deref().unwrap_or(""))?)
+}
+
+// Builds the direct child types of `ty`:
+//   list, option, future, type alias -> [element]
+//   expected                          -> [ok, err]
+//   stream                            -> [element, end]
+//   tuple                             -> elements
+//   record                            -> fields
+//   variant, union                    -> cases, with discriminants
+fn children_get(iface: &Arc<Interface>, align: &Arc<Layout>, ty: &Type) -> Result<Vec<WITTypeDef>> {
+    let mut children = Vec::new();
+    if let Type::Id(id) = ty {
+        match &iface.types[*id].kind {
+            TypeDefKind::List(t) |
+            TypeDefKind::Option(t) |
+            TypeDefKind::Future(t) |
+            TypeDefKind::Type(t) => {
+                children.push(WITTypeDef::new(iface, align, "", t.clone())?);
+            },
+            TypeDefKind::Expected(e) => {
+                children.push(WITTypeDef::new(iface, align, "ok", e.ok.clone())?);
+                children.push(WITTypeDef::new(iface, align, "err", e.err.clone())?);
+            },
+            TypeDefKind::Stream(st) => {
+                children.push(WITTypeDef::new(iface, align, "element", st.element.clone())?);
+                children.push(WITTypeDef::new(iface, align, "end", st.end.clone())?);
+            },
+            TypeDefKind::Tuple(t) => {
+                for elem_ty in &t.types {
+                    children.push(WITTypeDef::new(iface, align, "", elem_ty.clone())?);
+                }
+            },
+            TypeDefKind::Record(r) => {
+                for field in &r.fields {
+                    children.push(
+                        WITTypeDef::new(iface, align, field.name.as_str(), field.ty.clone())?
+                            .with_docs(&field.docs)?
+                    );
+                }
+            },
+            TypeDefKind::Variant(v) => {
+                for (i, case) in v.cases.iter().enumerate() {
+                    children.push(
+                        WITTypeDef::new(iface, align, case.name.as_str(), case.ty.clone())?
+                            .with_discriminant(i)
+                            .with_docs(&case.docs)?
+                    );
+                }
+            },
+            TypeDefKind::Union(u) => {
+                for (i, case) in u.cases.iter().enumerate() {
+                    children.push(
+                        WITTypeDef::new(iface, align, "", case.ty.clone())?
+                            .with_discriminant(i)
+                            .with_docs(&case.docs)?
+                    );
+                }
+            },
+            TypeDefKind::Flags(_) |
+            TypeDefKind::Enum(_) => {}
+        }
+    }
+    Ok(children)
 }
 
 #[allow(non_camel_case_types)]
@@ -143,24 +820,164 @@ pub enum WITType {
     Future,
 }
 
+#[repr(C)]
+pub struct WITIntInfo {
+    pub is_signed: bool,
+    pub is_float:  bool,
+    pub bits:      u32,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub enum WITLeafKind {
+    Value,         // scalar, char, handle, enum or flags
+    Pointer,       // address of string or list data
+    Length,        // length of string or list data
+    Discriminant,  // case index of a variant, union, option or expected
+    Payload,       // storage shared by all cases of a variant; `core` is unused
+}
+
+// A leaf of a type's memory layout, see wit_typedef_layout_get.
+#[repr(C)]
+pub struct WITLayoutLeaf {
+    pub kind:   WITLeafKind,
+    pub ty:     WITType,   // type the leaf belongs to
+    pub core:   WASMType,  // core type the leaf is loaded as
+    pub offset: usize,     // from the start of the outermost value
+    pub size:   usize,
+}
+
+// Callbacks through which the host supplies a value to be lowered, see
+// wit_typedef_lower.  They're called in the order of a depth-first walk of the
+// value's type, and return false to abort lowering.
+#[repr(C)]
+pub struct WITValueSource {
+    pub user_data:    *mut c_void,
+    // Bools, integers, chars, handles, futures and streams, zero-extended.
+    pub get_int:      extern "C" fn(user_data: *mut c_void, ty: WITType, res: *mut u64) -> bool,
+    pub get_float:    extern "C" fn(user_data: *mut c_void, ty: WITType, res: *mut f64) -> bool,
+    // UTF-8 bytes, transcoded to the WIT's string encoding (see
+    // wit_string_encoding_get); only needs to stay valid until the callback
+    // returns.
+    pub get_string:   extern "C" fn(user_data: *mut c_void, data: *mut *const u8, len: *mut usize) -> bool,
+    // Followed by `len` elements and end_list.
+    pub begin_list:   extern "C" fn(user_data: *mut c_void, elem_ty: WITType, len: *mut usize) -> bool,
+    pub end_list:     extern "C" fn(user_data: *mut c_void) -> bool,
+    // Records and tuples; followed by `count` fields and end_record.
+    pub begin_record: extern "C" fn(user_data: *mut c_void, ty: WITType, count: usize) -> bool,
+    pub end_record:   extern "C" fn(user_data: *mut c_void) -> bool,
+    // Enums, variants, unions, options and expecteds; followed by the payload
+    // of the chosen case, if any.
+    pub get_case:     extern "C" fn(user_data: *mut c_void, ty: WITType, count: usize, discr: *mut u32) -> bool,
+    pub get_flags:    extern "C" fn(user_data: *mut c_void, words: *mut u32, count: usize) -> bool,
+}
+
+// Callbacks through which the host receives a lifted value, see
+// wit_typedef_lift_flat.  They're called in the order of a depth-first walk
+// of the value's type, and return false to abort lifting.
+#[repr(C)]
+pub struct WITValueSink {
+    pub user_data:       *mut c_void,
+    // Bools, integers, chars, handles, futures and streams; signed integers
+    // are sign-extended.
+    pub on_int:          extern "C" fn(user_data: *mut c_void, ty: WITType, v: u64) -> bool,
+    pub on_float:        extern "C" fn(user_data: *mut c_void, ty: WITType, v: f64) -> bool,
+    // UTF-8 bytes, decoded from the WIT's string encoding; only valid until
+    // the callback returns.
+    pub on_string:       extern "C" fn(user_data: *mut c_void, data: *const u8, len: usize) -> bool,
+    // Followed by `len` elements and on_list_end.
+    pub on_list_begin:   extern "C" fn(user_data: *mut c_void, elem_ty: WITType, len: usize) -> bool,
+    pub on_list_end:     extern "C" fn(user_data: *mut c_void) -> bool,
+    // Records and tuples; followed by `count` fields and on_record_end.
+    pub on_record_begin: extern "C" fn(user_data: *mut c_void, ty: WITType, count: usize) -> bool,
+    pub on_record_end:   extern "C" fn(user_data: *mut c_void) -> bool,
+    // Enums, variants, unions, options and expecteds; followed by the
+    // payload of the case, if any.
+    pub on_case:         extern "C" fn(user_data: *mut c_void, ty: WITType, discr: u32) -> bool,
+    pub on_flags:        extern "C" fn(user_data: *mut c_void, words: *const u32, count: usize) -> bool,
+}
+
+// Callbacks through which the library accesses guest memory.  `realloc` has
+// the signature of the guest's `canonical_abi_realloc`.  Lowering uses
+// `realloc` and `write`, lifting only `read`.
+#[repr(C)]
+pub struct WITGuestMemory {
+    pub user_data: *mut c_void,
+    pub realloc:   extern "C" fn(user_data: *mut c_void, old: u64, old_size: usize, align: usize, new_size: usize, res: *mut u64) -> bool,
+    pub write:     extern "C" fn(user_data: *mut c_void, addr: u64, data: *const u8, len: usize) -> bool,
+    pub read:      extern "C" fn(user_data: *mut c_void, addr: u64, buf: *mut u8, len: usize) -> bool,
+}
+
+// Callbacks for wit_walk, each passed `user_data`.  Any may be NULL.  A
+// callback returns false to stop the walk, which then fails.
+#[repr(C)]
+pub struct WITWalker {
+    pub user_data:   *mut c_void,
+    // Named types, in definition order.  Records, variants and unions, enums
+    // and flags get their own callback, followed by one per member; other
+    // named types go to `on_alias`.  `on_type_end` follows the members.
+    pub on_record:   Option<extern "C" fn(user_data: *mut c_void, td: *const WITTypeDef) -> bool>,
+    pub on_field:    Option<extern "C" fn(user_data: *mut c_void, record: *const WITTypeDef, index: usize, field: *const WITTypeDef) -> bool>,
+    pub on_variant:  Option<extern "C" fn(user_data: *mut c_void, td: *const WITTypeDef) -> bool>,
+    pub on_case:     Option<extern "C" fn(user_data: *mut c_void, variant: *const WITTypeDef, index: usize, case: *const WITTypeDef) -> bool>,
+    pub on_enum:     Option<extern "C" fn(user_data: *mut c_void, td: *const WITTypeDef) -> bool>,
+    pub on_flags:    Option<extern "C" fn(user_data: *mut c_void, td: *const WITTypeDef) -> bool>,
+    pub on_name:     Option<extern "C" fn(user_data: *mut c_void, td: *const WITTypeDef, index: usize, name: *const c_char) -> bool>,
+    pub on_alias:    Option<extern "C" fn(user_data: *mut c_void, td: *const WITTypeDef) -> bool>,
+    pub on_type_end: Option<extern "C" fn(user_data: *mut c_void, td: *const WITTypeDef) -> bool>,
+    // Resources, then functions, in declaration order.
+    pub on_resource: Option<extern "C" fn(user_data: *mut c_void, r: *const WITResource) -> bool>,
+    pub on_function: Option<extern "C" fn(user_data: *mut c_void, func: *const WITFunction) -> bool>,
+    pub on_param:    Option<extern "C" fn(user_data: *mut c_void, func: *const WITFunction, index: usize, param: *const WITTypeDef) -> bool>,
+}
+
 pub struct WITError {
-    c_msg: CString
+    c_msg: CString,
+    location: Option<(CString, location::Location)>,  // with the file name
+}
+
+// Where in the source a parse error occurred.  `file` is the interface name
+// for wit_parse and the other in-memory parses, `<module>.wit` for modules
+// from the resolver, and the path for files.  `line` and `column` are
+// 1-based, with the column in bytes; `start` and `end` are the byte offsets
+// of the offending span in that source.
+#[repr(C)]
+pub struct WITErrorLocation {
+    pub file:   *const c_char,
+    pub line:   usize,
+    pub column: usize,
+    pub start:  usize,
+    pub end:    usize,
 }
 
 //////////////////////////////////////////////////////////////////////////
 
+// Calls an optional WITWalker callback, stopping the walk if it returns
+// false.
+macro_rules! walk_call {
+    ($cb: expr, $($arg: expr),*) => {
+        if let Some(cb) = $cb {
+            if !cb($($arg),*) {
+                return Err(anyhow!("Walk stopped by callback"));
+            }
+        }
+    }
+}
+
+// Returns the result of an entry point's body, recording an error in the
+// session.  The entry point is named after the function the body calls,
+// e.g. `wit_parse` for `_wit_parse(...)`, unless given.
 #[cfg(feature="catch_panics")]
 macro_rules! ffi_return {
-    ($s: expr, $e:expr) => {{
-        let res = catch_unwind(|| {
-            check($s, $e)
-        });
-        match res {
+    ($s: expr, $e:expr) => {
+        ffi_return!($s, panics::entry_point(stringify!($e)), $e)
+    };
+    ($s: expr, $name: expr, $e:expr) => {{
+        // Lazily-cached child types are left unset if a panic interrupts them,
+        // so it's safe to keep using the objects afterwards.
+        match panics::catch($name, || check($s, $e)) {
             Ok(r) => r,
-            Err(e) => {
-                error_set(unsafe { &mut *$s }, anyhow!("Caught Rust panic: {:?}", e));
-                false
-            },
+            Err(msg) => check($s, Err(anyhow!(msg))),
         }
     }}
 }
@@ -168,6 +985,9 @@ macro_rules! ffi_return {
 macro_rules! ffi_return {
     ($s: expr, $e:expr) => {
         check($s, $e)
+    };
+    ($s: expr, $name: expr, $e:expr) => {
+        check($s, $e)
     }
 }
 
@@ -185,6 +1005,33 @@ pub extern "C" fn wit_error_get(s: *const WITSession) -> *const c_char {
     }
 }
 
+// Returns where the last error occurred if it's a parse error wit-parser
+// reported a position for.  Returns false, leaving `res` untouched, if there
+// is no error or it has no location.  `res->file` is valid until the error
+// is cleared or replaced.
+#[no_mangle]
+pub extern "C" fn wit_error_location_get(s: *const WITSession, res: *mut WITErrorLocation) -> bool {
+    if s.is_null() || res.is_null() {
+        return false
+    }
+    let s = unsafe { &*s };
+    match s.error.as_ref().and_then(|e| e.location.as_ref()) {
+        Some((file, loc)) => {
+            unsafe {
+                *res = WITErrorLocation {
+                    file:   file.as_ptr(),
+                    line:   loc.line,
+                    column: loc.column,
+                    start:  loc.start,
+                    end:    loc.end,
+                };
+            }
+            true
+        },
+        None => false
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn wit_error_clear(s: *mut WITSession) {
     if s.is_null() {
@@ -199,9 +1046,12 @@ fn error_set(s: &mut WITSession, err: anyhow::Error) -> bool {
     match err_res {
         Ok(msg) => 
         {
+            let location = err.downcast_ref::<location::ParseError>()
+                .and_then(|e| Some((CString::new(e.location.file.as_str()).ok()?, e.location.clone())));
             s.error.replace(
                 WITError{ 
-                    c_msg: msg
+                    c_msg: msg,
+                    location,
                 }
             );
             true
@@ -228,6 +1078,7 @@ fn check(s: *mut WITSession, r: Result<()>) -> bool {
 pub extern "C" fn wit_session_new() -> *mut WITSession {
     let s = WITSession {
         error: None,
+        options: ParseOptions::default(),
     };
     let res = Box::new(s);
     Box::leak(res)
@@ -243,891 +1094,4638 @@ pub extern "C" fn wit_session_delete(s: *mut WITSession) {
     }
 }
 
+// Installs a callback used to load modules pulled in with `use` by
+// wit_parse, wit_parse_named and wit_parse_all (for modules not among the
+// documents passed to it).  Passing NULL removes it, in which case `use` of
+// another module fails to parse.
 #[no_mangle]
-pub extern "C" fn wit_parse(s: *mut WITSession, content: *const u8, len: usize, res: *mut *mut WIT) -> bool {
-    ffi_return!(s, _wit_parse(content, len, res))
-}
-fn _wit_parse(content: *const u8, len: usize, res: *mut *mut WIT) -> Result<()> {
-    if content.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid arguments"))
-    }
-    let content = unsafe {
-        str::from_utf8(slice::from_raw_parts(content, len))?
-    };
-
-    // Extract the WASM signature for each function.
-    let mut safe_res = WIT::new(content)?;
-
-    // Create a map of each function's name to its index into the interface.
-    let funcs = &safe_res.iface.functions;
-    for i in 0..funcs.len() {
-        let sig = WITSignature {
-            sig: safe_res.iface.wasm_signature(abi::AbiVariant::GuestExport, &funcs[i]),
-        };
-        let res_ty = funcs[i].result.clone();
-        safe_res.funcs.insert(
-            funcs[i].name.clone(), 
-            WITFunction {
-                iface: safe_res.iface.clone(),
-                align: safe_res.align.clone(),
-                name:  CString::new(funcs[i].name.as_str())?,
-                sig,
-                index: i,
-                res:   WITTypeDef { 
-                    iface: safe_res.iface.clone(), 
-                    align: safe_res.align.clone(), 
-                    name:  CString::new("")?,
-                    ty:    res_ty, 
-                    subty1: subtypedef_get_maybe(1, &safe_res.iface, &safe_res.align, Some(&funcs[i].result))?,
-                    subty2: subtypedef_get_maybe(2, &safe_res.iface, &safe_res.align, Some(&funcs[i].result))?,
-                },
-            }
-        );
-    }
-
-    let safe_res = Box::into_raw(Box::new(safe_res));
-    unsafe {
-        *res = safe_res;
+pub extern "C" fn wit_session_set_resolver(s: *mut WITSession, cb: Option<WITResolver>, user_data: *mut c_void) {
+    if s.is_null() {
+        return;
     }
-    Ok(())
+    let s = unsafe { &mut *s };
+    s.options.resolver = cb.map(|cb| Resolver { cb, user_data });
 }
 
+// Installs a callback receiving the library's diagnostics of `level` and
+// more severe while parsing: progress, failures, warnings, types only partly
+// supported and ABI decisions.  Passing NULL removes it.
 #[no_mangle]
-pub extern "C" fn wit_delete(_s: *mut WITSession, wit: *mut WIT) {
-    if wit.is_null() {
+pub extern "C" fn wit_session_set_logger(s: *mut WITSession, cb: Option<WITLogger>, user_data: *mut c_void, level: WITLogLevel) {
+    if s.is_null() {
         return;
     }
-    unsafe {
-        drop(Box::from_raw(wit));
-    }
+    let s = unsafe { &mut *s };
+    s.options.logger = cb.map(|cb| Logger { cb, user_data, level });
 }
 
+// Sets the string encoding assumed for WITs parsed from now on (UTF-8 by
+// default).  Strings are passed as a pointer and a length in every encoding,
+// so sizes, alignments and signatures don't depend on it; it determines the
+// units of the length and the alignment of string data (see
+// wit_string_data_align_get).
 #[no_mangle]
-pub extern "C" fn wit_func_name_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const c_char) -> bool {
-    ffi_return!(s, _wit_func_name_get(func, res))
+pub extern "C" fn wit_session_set_string_encoding(s: *mut WITSession, encoding: WITStringEncoding) {
+    if s.is_null() {
+        return;
+    }
+    let s = unsafe { &mut *s };
+    s.options.string_encoding = encoding;
 }
-fn _wit_func_name_get(func: *const WITFunction, res: *mut *const c_char) -> Result<()> {
-    if func.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid arguments"))
+
+// Makes WITs parsed from now on compute sizes, alignments and signatures for
+// a guest with a 64-bit linear memory (off by default).
+#[no_mangle]
+pub extern "C" fn wit_session_set_memory64(s: *mut WITSession, memory64: bool) {
+    if s.is_null() {
+        return;
     }
-    let func = unsafe {
-        &*func
-    };
-    unsafe {
-        *res = func.name.as_ptr();
+    let s = unsafe { &mut *s };
+    s.options.memory64 = memory64;
+}
+
+// Overrides the maximum number of core params and results a function may
+// have before its params are passed indirectly or its results through a
+// return pointer, for WITs parsed from now on.  Defaults to 16 and 1.
+#[no_mangle]
+pub extern "C" fn wit_session_set_max_flat(s: *mut WITSession, max_params: usize, max_results: usize) {
+    if s.is_null() {
+        return;
     }
-    Ok(())
+    let s = unsafe { &mut *s };
+    s.options.max_flat_params = max_params;
+    s.options.max_flat_results = max_results;
 }
 
+// Exposes the function or named type called `from` in WIT source as `to` in
+// WITs parsed from now on, e.g. to make names SQL-safe; passing NULL for `to`
+// removes the rename.  Renamed functions keep their core export names (see
+// wit_func_core_export_name_get).  Parsing fails if a rename makes two
+// functions or two types share a name.
 #[no_mangle]
-pub extern "C" fn wit_func_count_get(s: *mut WITSession, wit: *const WIT, res: *mut usize) -> bool {
-    ffi_return!(s, _wit_func_count_get(wit, res))
+pub extern "C" fn wit_session_set_rename(s: *mut WITSession, from: *const c_char, to: *const c_char) -> bool {
+    ffi_return!(s, _wit_session_set_rename(s, from, to))
 }
-fn _wit_func_count_get(wit: *const WIT, res: *mut usize) -> Result<()> {
-    if wit.is_null() || res.is_null() {
+fn _wit_session_set_rename(s: *mut WITSession, from: *const c_char, to: *const c_char) -> Result<()> {
+    if s.is_null() || from.is_null() {
         return Err(anyhow!("Invalid arguments"))
     }
-    let wit  = unsafe {
-        &*wit
+    let s = unsafe { &mut *s };
+    let from = unsafe { CStr::from_ptr(from) }.to_str()?.to_string();
+    let renames = Arc::make_mut(&mut s.options.renames);
+    match opt_str(to)? {
+        Some(to) => renames.insert(from, to.to_string()),
+        None => renames.remove(&from),
     };
-    unsafe {
-        *res = wit.iface.functions.len();
-    }
     Ok(())
 }
 
+// Removes all renames set with wit_session_set_rename.
 #[no_mangle]
-pub extern "C" fn wit_func_get_by_index(s: *mut WITSession, wit: *const WIT, index: usize, res: *mut *const WITFunction) -> bool {
-    ffi_return!(s, _wit_func_get_by_index(wit, index, res))
+pub extern "C" fn wit_session_clear_renames(s: *mut WITSession) {
+    if s.is_null() {
+        return;
+    }
+    let s = unsafe { &mut *s };
+    s.options.renames = Arc::default();
 }
-fn _wit_func_get_by_index(wit: *const WIT, index: usize, res: *mut *const WITFunction) -> Result<()> {
-    if wit.is_null() || res.is_null() {
+
+// Returns the number of warnings from the last parse call: named types no
+// function uses, names that aren't kebab-case, and names that are the same
+// as another's but for case and `_` vs. `-`.  Each parse call replaces the
+// previous one's warnings.
+#[no_mangle]
+pub extern "C" fn wit_warning_count_get(s: *mut WITSession, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_warning_count_get(s, res))
+}
+fn _wit_warning_count_get(s: *mut WITSession, res: *mut usize) -> Result<()> {
+    if s.is_null() || res.is_null() {
         return Err(anyhow!("Invalid arguments"))
     }
-    let wit  = unsafe {
-        &*wit
-    };
-    let name = &wit.iface.functions[index].name;
-    let func = wit.funcs.get(name);
-    if let Some(func) = func {
-        unsafe {
-            *res = func as *const WITFunction;
-        }
-        Ok(())
-    } else {
-        Err(anyhow!("Function `{}` not found", &name))
+    let s = unsafe { &*s };
+    unsafe {
+        *res = s.options.warnings.lock().unwrap().len();
     }
+    Ok(())
 }
 
+// Returns a warning from the last parse call, prefixed with the name of the
+// interface it's about.  Valid until the next parse call.
 #[no_mangle]
-pub extern "C" fn wit_func_get_by_name(s: *mut WITSession, wit: *const WIT, fname: *const c_char, res: *mut *const WITFunction) -> bool {
-    ffi_return!(s, _wit_func_get_by_name(wit, fname, res))
+pub extern "C" fn wit_warning_get(s: *mut WITSession, index: usize, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_warning_get(s, index, res))
 }
-fn _wit_func_get_by_name(wit: *const WIT, fname: *const c_char, res: *mut *const WITFunction) -> Result<()> {
-    if wit.is_null() || fname.is_null() || res.is_null() {
+fn _wit_warning_get(s: *mut WITSession, index: usize, res: *mut *const c_char) -> Result<()> {
+    if s.is_null() || res.is_null() {
         return Err(anyhow!("Invalid arguments"))
     }
-    let wit  = unsafe {
-        &*wit
-    };
-    let fname = unsafe {
-        CStr::from_ptr(fname)
-    };
-    let fname_str = fname.to_str()?;
-    if let Some(func) = wit.funcs.get(&fname_str.to_string()) {
-        unsafe {
-            *res = func as *const WITFunction;
-        }
-        Ok(())
-    } else {
-        Err(anyhow!("Function `{}` not found", &fname_str))
+    let s = unsafe { &*s };
+    let warnings = s.options.warnings.lock().unwrap();
+    let warning = warnings.get(index).ok_or_else(|| anyhow!("Warning index {} out of bounds!", index))?;
+    unsafe {
+        *res = warning.as_ptr();
+    }
+    Ok(())
+}
+
+fn warnings_clear(s: *mut WITSession) {
+    if !s.is_null() {
+        let s = unsafe { &*s };
+        s.options.warnings.lock().unwrap().clear();
+    }
+}
+
+fn options_get(s: *const WITSession) -> ParseOptions {
+    if s.is_null() {
+        return ParseOptions::default();
     }
+    let s = unsafe { &*s };
+    s.options.clone()
 }
 
 #[no_mangle]
-pub extern "C" fn wit_func_param_walk<'a>(s: *mut WITSession, func: *const WITFunction, res: *mut *mut WITTypeDefIter<'a>) -> bool {
-    ffi_return!(s, _wit_func_param_walk(func, res))
+pub extern "C" fn wit_parse(s: *mut WITSession, content: *const u8, len: usize, res: *mut *mut WIT) -> bool {
+    warnings_clear(s);
+    ffi_return!(s, _wit_parse(options_get(s), "wit", content, len, res))
 }
-fn _wit_func_param_walk<'a>(func: *const WITFunction, res: *mut *mut WITTypeDefIter<'a>) -> Result<()> {
-    if func.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"));
+
+// Like wit_parse, but names the interface (see wit_name_get).
+#[no_mangle]
+pub extern "C" fn wit_parse_named(s: *mut WITSession, name: *const c_char, content: *const u8, len: usize, res: *mut *mut WIT) -> bool {
+    warnings_clear(s);
+    ffi_return!(s, _wit_parse_named(options_get(s), name, content, len, res))
+}
+fn _wit_parse_named(opts: ParseOptions, name: *const c_char, content: *const u8, len: usize, res: *mut *mut WIT) -> Result<()> {
+    if name.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let func  = unsafe {
-        &*func
+    let name = unsafe {
+        CStr::from_ptr(name)
     };
-    let mut inner_iter = func.iface.functions[func.index].params.iter();
-    let next = inner_iter.next();
-    let item: Option<WITTypeDef> = match next {
-        Some(n) => {
-            Some(
-                WITTypeDef{ 
-                    iface: func.iface.clone(), 
-                    align: func.align.clone(),
-                    name:  CString::new(n.0.as_str())?,
-                    ty:    n.1.clone(),
-                    subty1: subtypedef_get_maybe(1, &func.iface, &func.align, Some(&n.1))?,
-                    subty2: subtypedef_get_maybe(2, &func.iface, &func.align, Some(&n.1))?,
-                }
-            )
-        },
-        _ => None
+    _wit_parse(opts, name.to_str()?, content, len, res)
+}
+
+fn _wit_parse(opts: ParseOptions, name: &str, content: *const u8, len: usize, res: *mut *mut WIT) -> Result<()> {
+    if content.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let content = unsafe {
+        str::from_utf8(slice::from_raw_parts(content, len))?
     };
-    let res_safe = 
-        Box::into_raw(
-            Box::new(
-                WITTypeDefIter {
-                    iface:      func.iface.clone(),
-                    align:      func.align.clone(),
-                    inner_iter,
-                    item,
-                }
-            )
-        );
+
+    let safe_res = Box::into_raw(Box::new(WIT::new(name, content, opts)?));
     unsafe {
-        *res = res_safe;
+        *res = safe_res;
     }
     Ok(())
 }
 
+// Parses the WIT file at `path`.  Modules pulled in with `use` are loaded
+// from files next to it, e.g. `use { t } from foo` reads `foo.wit`.  The
+// interface is named after the file stem.
 #[no_mangle]
-pub extern "C" fn wit_func_result_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const WITTypeDef) -> bool {
-    ffi_return!(s, _wit_func_result_get(func, res))
+pub extern "C" fn wit_parse_file(s: *mut WITSession, path: *const c_char, res: *mut *mut WIT) -> bool {
+    warnings_clear(s);
+    ffi_return!(s, _wit_parse_file(options_get(s), path, res))
 }
-fn _wit_func_result_get(func: *const WITFunction, res: *mut *const WITTypeDef) -> Result<()> {
-    if func.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"));
+fn _wit_parse_file(opts: ParseOptions, path: *const c_char, res: *mut *mut WIT) -> Result<()> {
+    if path.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let func = unsafe {
-        &*func
+    let path = unsafe {
+        CStr::from_ptr(path)
     };
+    let path = path.to_str()?;
+    opts.log(WITLogLevel::Info, || format!("Parsing file `{}`", path));
+    let iface = opts.parsed(Interface::parse_file(path)
+        .map_err(|e| location::locate(e, |file| fs::read_to_string(file).ok())))?;
+    let safe_res = Box::into_raw(Box::new(WIT::from_interface(iface, opts)?));
     unsafe {
-        *res = &func.res;
+        *res = safe_res;
     }
     Ok(())
 }
 
+// Parses `count` documents at once, e.g. the files of a package, into one
+// WIT per document, named `names[i]`, in the same order.  A document may
+// `use` another of them by its name; other modules go through the session's
+// resolver.  wit-parser has a single interface per document, so an API split
+// into several interfaces is passed as one document each.
 #[no_mangle]
-pub extern "C" fn wit_typedef_iter_off(_s: *mut WITSession, iter: *const WITTypeDefIter) -> bool {
-    if iter.is_null() {
-        return true;
+pub extern "C" fn wit_parse_all(s: *mut WITSession, names: *const *const c_char, contents: *const *const u8, lens: *const usize, count: usize, res: *mut *mut WITCollection) -> bool {
+    warnings_clear(s);
+    ffi_return!(s, _wit_parse_all(options_get(s), names, contents, lens, count, res))
+}
+fn _wit_parse_all(opts: ParseOptions, names: *const *const c_char, contents: *const *const u8, lens: *const usize, count: usize, res: *mut *mut WITCollection) -> Result<()> {
+    if (count > 0 && (names.is_null() || contents.is_null() || lens.is_null())) || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let iter = unsafe {
-        &*iter
-    };
-    iter.item.is_none()
+
+    let mut docs = Vec::with_capacity(count);
+    for i in 0..count {
+        let (name, content, len) = unsafe {
+            (*names.add(i), *contents.add(i), *lens.add(i))
+        };
+        if name.is_null() || content.is_null() {
+            return Err(anyhow!("Invalid argument"))
+        }
+        let name = unsafe {
+            CStr::from_ptr(name)
+        };
+        let content = unsafe {
+            str::from_utf8(slice::from_raw_parts(content, len))?
+        };
+        docs.push((name.to_str()?, content));
+    }
+    let mut wits = Vec::with_capacity(count);
+    for (name, content) in &docs {
+        wits.push(WIT::new_in(name, content, &docs, opts.clone())?);
+    }
+    let safe_res = Box::into_raw(Box::new(WITCollection { wits }));
+    unsafe {
+        *res = safe_res;
+    }
+    Ok(())
 }
 
+// Parses the WIT text embedded in the custom sections of a core wasm module
+// into one WIT per section.  `sections` holds the `count` names of the
+// sections to read, as written by the toolchain that built the module: a
+// name ending in `:` matches every section starting with it, named after
+// the rest of the section name, and any other name matches exactly, named
+// after itself.  No names are assumed, as toolchains differ (and newer
+// wit-bindgen embeds a binary encoding rather than WIT text).
 #[no_mangle]
-pub extern "C" fn wit_typedef_iter_next(s: *mut WITSession, iter: *mut WITTypeDefIter) -> bool {
-    ffi_return!(s, _wit_typedef_iter_next(iter))
+pub extern "C" fn wit_parse_module(s: *mut WITSession, module: *const u8, len: usize, sections: *const *const c_char, count: usize, res: *mut *mut WITCollection) -> bool {
+    warnings_clear(s);
+    ffi_return!(s, _wit_parse_module(options_get(s), module, len, sections, count, res))
 }
-fn _wit_typedef_iter_next(iter: *mut WITTypeDefIter) -> Result<()> {
-    if iter.is_null() {
-        return Err(anyhow!("Invalid argument"));
-    }
-    if wit_typedef_iter_off(ptr::null_mut(), iter) {
-        return Err(anyhow!("Iterator out of bounds!"));
+fn _wit_parse_module(opts: ParseOptions, module: *const u8, len: usize, sections: *const *const c_char, count: usize, res: *mut *mut WITCollection) -> Result<()> {
+    if module.is_null() || (count > 0 && sections.is_null()) || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let iter = unsafe {
-        &mut *iter
+    let module = unsafe {
+        slice::from_raw_parts(module, len)
     };
-    let next = iter.inner_iter.next();
-    iter.item = {
-        if let Some(next) = next {
-            Some(
-                WITTypeDef{ 
-                    iface: iter.iface.clone(), 
-                    align: iter.align.clone(),
-                    name:  CString::new(next.0.as_str())?, 
-                    ty:    next.1.clone(),
-                    subty1: subtypedef_get_maybe(1, &iter.iface, &iter.align, Some(&next.1))?,
-                    subty2: subtypedef_get_maybe(2, &iter.iface, &iter.align, Some(&next.1))?,
-                }
-            )
-        } else {
-            None
+    let mut names = Vec::with_capacity(count);
+    for i in 0..count {
+        let name = unsafe {
+            *sections.add(i)
+        };
+        if name.is_null() {
+            return Err(anyhow!("Invalid argument"))
         }
-    };
+        names.push(unsafe { CStr::from_ptr(name) }.to_str()?);
+    }
+
+    let sections = module::wit_sections(module, &names)?;
+    if sections.is_empty() {
+        return Err(anyhow!("Module has no embedded WIT in the given sections"));
+    }
+    let mut wits = Vec::new();
+    for (name, body) in sections {
+        wits.push(WIT::new(&name, &body, opts.clone())?);
+    }
+    let safe_res = Box::into_raw(Box::new(WITCollection { wits }));
+    unsafe {
+        *res = safe_res;
+    }
     Ok(())
 }
 
-fn subtypedef_get_maybe<'a>(which: i32, iface: &'a Rc<Interface>, align: &'a Rc<SizeAlign>, ty_opt: Option<&'a Type>) 
-    -> Result<Option<Box<WITTypeDef>>> 
-{
-    let ty: &'a Type;
-    if let Some(t) = ty_opt {
-        ty = t;
-    } else {
-        return Ok(None);
+#[no_mangle]
+pub extern "C" fn wit_collection_count_get(s: *mut WITSession, coll: *const WITCollection, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_collection_count_get(coll, res))
+}
+fn _wit_collection_count_get(coll: *const WITCollection, res: *mut usize) -> Result<()> {
+    if coll.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    if let Type::Id(id) = ty {
-        match which {
-            1 => match &iface.types[*id].kind {
-                TypeDefKind::List(subty) |
-                TypeDefKind::Type(subty) |
-                TypeDefKind::Option(subty) => Ok(
-                    Some(
-                        Box::new(
-                            WITTypeDef {
-                                iface: iface.clone(),
-                                align: align.clone(),
-                                name:  CString::new("").unwrap(),
-                                ty:    subty.clone(),
-                                subty1: subtypedef_get_maybe(1, iface, align, Some(&subty))?,
-                                subty2: subtypedef_get_maybe(2, iface, align, Some(&subty))?,
-                            }
-                        )
-                    )
-                ),
-                TypeDefKind::Expected(exp) => Ok(
-                    Some(
-                        Box::new(
-                            WITTypeDef { 
-                                iface: iface.clone(),
-                                align: align.clone(), 
-                                name:  CString::new("ok").unwrap(),
-                                ty:    exp.ok.clone(),
-                                subty1: subtypedef_get_maybe(1, iface, align, Some(&exp.ok))?,
-                                subty2: subtypedef_get_maybe(2, iface, align, Some(&exp.ok))?,
-                            }
-                        )
-                    )
-                ),
-                _ => Ok(None),
-            },
-            2 => match &iface.types[*id].kind {
-                TypeDefKind::Expected(exp) => Ok(
-                    Some(
-                        Box::new(
-                            WITTypeDef { 
-                                iface: iface.clone(),
-                                align: align.clone(), 
-                                name:  CString::new("err").unwrap(),
-                                ty:    exp.err.clone(),
-                                subty1: subtypedef_get_maybe(1, iface, align, Some(&exp.err))?,
-                                subty2: subtypedef_get_maybe(2, iface, align, Some(&exp.err))?,
-                            }
-                        )
-                    )
-                ),
-                _ => Ok(None),
-            },
-            _ => Ok(None),
-        }
-    } else {
-        Ok(None)
+    let coll = unsafe {
+        &*coll
+    };
+    unsafe {
+        *res = coll.wits.len();
     }
+    Ok(())
 }
 
+// The returned WIT is owned by the collection; do not wit_delete it.
 #[no_mangle]
-pub extern "C" fn wit_typedef_iter_at<'a>(s: *mut WITSession, iter: *const WITTypeDefIter, res: *mut *const WITTypeDef) -> bool {
-    ffi_return!(s, _wit_typedef_iter_at(iter, res))
+pub extern "C" fn wit_collection_get_by_index(s: *mut WITSession, coll: *const WITCollection, index: usize, res: *mut *const WIT) -> bool {
+    ffi_return!(s, _wit_collection_get_by_index(coll, index, res))
 }
-fn _wit_typedef_iter_at(iter: *const WITTypeDefIter, res: *mut *const WITTypeDef) -> Result<()> {
-    if iter.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"));
+fn _wit_collection_get_by_index(coll: *const WITCollection, index: usize, res: *mut *const WIT) -> Result<()> {
+    if coll.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let iter = unsafe {
-        &*iter
+    let coll = unsafe {
+        &*coll
     };
-    if let Some(item) = &iter.item {
+    if let Some(wit) = coll.wits.get(index) {
         unsafe {
-            *res = item as *const WITTypeDef;
+            *res = wit as *const WIT;
         }
         Ok(())
     } else {
-        Err(anyhow!("Iterator out of bounds!"))
+        Err(anyhow!("Interface index {} out of bounds!", index))
     }
 }
 
 #[no_mangle]
-pub extern "C" fn wit_typedef_iter_delete(_s: *mut WITSession, iter: *mut WITTypeDefIter) {
-    if !iter.is_null() {
-        unsafe {
-            drop(Box::from_raw(iter));
-        }
+pub extern "C" fn wit_collection_delete(_s: *mut WITSession, coll: *mut WITCollection) {
+    if coll.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(coll));
     }
 }
 
 #[no_mangle]
-pub extern "C" fn wit_record_field_walk<'a>(s: *mut WITSession, td: *const WITTypeDef, res: *mut *mut WITFieldIter<'a>) -> bool {
-    ffi_return!(s, _wit_record_field_walk(td, res))
+pub extern "C" fn wit_delete(_s: *mut WITSession, wit: *mut WIT) {
+    if wit.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(wit));
+    }
 }
-fn _wit_record_field_walk<'a>(td: *const WITTypeDef, res: *mut *mut WITFieldIter<'a>) -> Result<()> {
-    if td.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"));
+
+#[no_mangle]
+pub extern "C" fn wit_name_get(s: *mut WITSession, wit: *const WIT, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_name_get(wit, res))
+}
+fn _wit_name_get(wit: *const WIT, res: *mut *const c_char) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let td = unsafe {
-        &*td
+    let wit = unsafe {
+        &*wit
     };
-    if let Type::Id(id) = &td.ty {
-        if let TypeDefKind::Record(rec) = &td.iface.types[*id].kind {
-            let mut inner_iter = rec.fields.iter();
-            let next = inner_iter.next();
-            let item: Option<WITTypeDef> = match next {
-                Some(f) => 
-                    Some(
-                        WITTypeDef{ 
-                            iface: td.iface.clone(), 
-                            align: td.align.clone(),
-                            name:  CString::new(f.name.as_str())?, 
-                            ty:    f.ty.clone(),
-                            subty1: subtypedef_get_maybe(1, &td.iface, &td.align, Some(&f.ty))?,
-                            subty2: subtypedef_get_maybe(2, &td.iface, &td.align, Some(&f.ty))?,
-                        }
-                    ),
-                _ => None
+    unsafe {
+        *res = wit.name.as_ptr();
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_name_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_func_name_get(func, res))
+}
+fn _wit_func_name_get(func: *const WITFunction, res: *mut *const c_char) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = func.name.as_ptr();
+    }
+    Ok(())
+}
+
+// Returns the function's index within the interface, as used by
+// wit_func_get_by_index.
+#[no_mangle]
+pub extern "C" fn wit_func_index_get(s: *mut WITSession, func: *const WITFunction, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_func_index_get(func, res))
+}
+fn _wit_func_index_get(func: *const WITFunction, res: *mut usize) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = func.index;
+    }
+    Ok(())
+}
+
+// Like wit_func_name_get, but returns the name as it must be spelled in WIT
+// source, i.e. with a leading `%` if it collides with a keyword.
+#[no_mangle]
+pub extern "C" fn wit_func_name_raw_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_func_name_raw_get(func, res))
+}
+fn _wit_func_name_raw_get(func: *const WITFunction, res: *mut *const c_char) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = func.raw_name.as_ptr();
+    }
+    Ok(())
+}
+
+// Returns the name of the symbol the guest module exports for the function.
+// This is the WIT name verbatim (dashes and all), qualified with the resource
+// name for resource functions.
+#[no_mangle]
+pub extern "C" fn wit_func_core_export_name_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_func_core_export_name_get(func, res))
+}
+fn _wit_func_core_export_name_get(func: *const WITFunction, res: *mut *const c_char) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = func.export_name.as_ptr();
+    }
+    Ok(())
+}
+
+// Sets `res` to true if the guest expects the host to call a post-return
+// export once it has finished reading the function's result, so the guest
+// can free the memory backing any strings or lists in it.
+#[no_mangle]
+pub extern "C" fn wit_func_needs_post_return(s: *mut WITSession, func: *const WITFunction, res: *mut bool) -> bool {
+    ffi_return!(s, _wit_func_needs_post_return(func, res))
+}
+fn _wit_func_needs_post_return(func: *const WITFunction, res: *mut bool) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = func.post_return.is_some();
+    }
+    Ok(())
+}
+
+// Sets `res` to true if calling the function's guest export requires the
+// guest to export `canonical_abi_realloc`, i.e. if the host must allocate
+// guest memory for string or list params or for an indirect params block.
+#[no_mangle]
+pub extern "C" fn wit_func_needs_realloc(s: *mut WITSession, func: *const WITFunction, res: *mut bool) -> bool {
+    ffi_return!(s, _wit_func_needs_realloc(func, res))
+}
+fn _wit_func_needs_realloc(func: *const WITFunction, res: *mut bool) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = needs_realloc(func);
+    }
+    Ok(())
+}
+
+// Like wit_func_needs_realloc, but true if any function in the interface
+// needs it.
+#[no_mangle]
+pub extern "C" fn wit_needs_realloc(s: *mut WITSession, wit: *const WIT, res: *mut bool) -> bool {
+    ffi_return!(s, _wit_needs_realloc(wit, res))
+}
+fn _wit_needs_realloc(wit: *const WIT, res: *mut bool) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit = unsafe {
+        &*wit
+    };
+    unsafe {
+        *res = wit.funcs.values().any(needs_realloc);
+    }
+    Ok(())
+}
+
+fn needs_realloc(func: &WITFunction) -> bool {
+    func.sig.sig.indirect_params || func.params.iter().any(|p| owns_memory(&p.iface, &p.ty))
+}
+
+// Returns the name of the function's post-return export (`cabi_post_<name>`).
+// Fails if the function doesn't need one.
+#[no_mangle]
+pub extern "C" fn wit_func_post_return_name_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_func_post_return_name_get(func, res))
+}
+fn _wit_func_post_return_name_get(func: *const WITFunction, res: *mut *const c_char) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let func = unsafe {
+        &*func
+    };
+    let name = func.post_return.as_ref()
+        .ok_or_else(|| anyhow!("Function has no post-return export!"))?;
+    unsafe {
+        *res = name.as_ptr();
+    }
+    Ok(())
+}
+
+// Returns the doc comments attached to the function, or an empty string if
+// it has none.
+#[no_mangle]
+pub extern "C" fn wit_func_docs_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_func_docs_get(func, res))
+}
+fn _wit_func_docs_get(func: *const WITFunction, res: *mut *const c_char) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = func.docs.as_ptr();
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_kind_get(s: *mut WITSession, func: *const WITFunction, res: *mut WITFuncKind) -> bool {
+    ffi_return!(s, _wit_func_kind_get(func, res))
+}
+fn _wit_func_kind_get(func: *const WITFunction, res: *mut WITFuncKind) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let func = unsafe {
+        &*func
+    };
+    let kind = match &func.iface.functions[func.index].kind {
+        FunctionKind::Freestanding => WITFuncKind::Freestanding,
+        FunctionKind::Method { .. } => WITFuncKind::Method,
+        FunctionKind::Static { .. } => WITFuncKind::Static,
+    };
+    unsafe {
+        *res = kind;
+    }
+    Ok(())
+}
+
+// Returns the string encoding the WIT was parsed with.
+#[no_mangle]
+pub extern "C" fn wit_string_encoding_get(s: *mut WITSession, wit: *const WIT, res: *mut WITStringEncoding) -> bool {
+    ffi_return!(s, _wit_string_encoding_get(wit, res))
+}
+fn _wit_string_encoding_get(wit: *const WIT, res: *mut WITStringEncoding) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    unsafe {
+        *res = wit.string_encoding;
+    }
+    Ok(())
+}
+
+// Sets `res` to true if the WIT was parsed for a memory64 guest.
+#[no_mangle]
+pub extern "C" fn wit_is_memory64(s: *mut WITSession, wit: *const WIT, res: *mut bool) -> bool {
+    ffi_return!(s, _wit_is_memory64(wit, res))
+}
+fn _wit_is_memory64(wit: *const WIT, res: *mut bool) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    unsafe {
+        *res = wit.align.memory64();
+    }
+    Ok(())
+}
+
+// Return the flattening limits the WIT's signatures were computed with, see
+// wit_session_set_max_flat.
+#[no_mangle]
+pub extern "C" fn wit_max_flat_params_get(s: *mut WITSession, wit: *const WIT, res: *mut usize) -> bool {
+    ffi_return!(s, "wit_max_flat_params_get", _wit_max_flat_get(wit, true, res))
+}
+#[no_mangle]
+pub extern "C" fn wit_max_flat_results_get(s: *mut WITSession, wit: *const WIT, res: *mut usize) -> bool {
+    ffi_return!(s, "wit_max_flat_results_get", _wit_max_flat_get(wit, false, res))
+}
+fn _wit_max_flat_get(wit: *const WIT, params: bool, res: *mut usize) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    unsafe {
+        *res = if params { wit.max_flat_params } else { wit.max_flat_results };
+    }
+    Ok(())
+}
+
+// Returns the alignment of the buffer the host must allocate in guest memory
+// for string data: 1 for UTF-8, 2 for UTF-16 and Latin-1+UTF-16.
+#[no_mangle]
+pub extern "C" fn wit_string_data_align_get(s: *mut WITSession, wit: *const WIT, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_string_data_align_get(wit, res))
+}
+fn _wit_string_data_align_get(wit: *const WIT, res: *mut usize) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    unsafe {
+        *res = match wit.string_encoding {
+            WITStringEncoding::Utf8 => 1,
+            WITStringEncoding::Utf16 |
+            WITStringEncoding::Latin1Utf16 => 2,
+        };
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_count_get(s: *mut WITSession, wit: *const WIT, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_func_count_get(wit, res))
+}
+fn _wit_func_count_get(wit: *const WIT, res: *mut usize) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    unsafe {
+        *res = wit.iface.functions.len();
+    }
+    Ok(())
+}
+
+// Returns the size of the largest return area (see wit_sig_is_indirect) any
+// function in the interface needs, for either ABI variant, or 0 if no
+// function returns its results indirectly.
+#[no_mangle]
+pub extern "C" fn wit_max_retptr_size_get(s: *mut WITSession, wit: *const WIT, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_max_retptr_size_get(wit, res))
+}
+fn _wit_max_retptr_size_get(wit: *const WIT, res: *mut usize) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    let size = wit.funcs.values()
+        .filter(|f| f.sig.sig.retptr || f.import_sig.sig.retptr)
+        .map(|f| align_to(f.align.size(&f.res.ty), f.align.align(&f.res.ty)))
+        .max()
+        .unwrap_or(0);
+    unsafe {
+        *res = size;
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_get_by_index(s: *mut WITSession, wit: *const WIT, index: usize, res: *mut *const WITFunction) -> bool {
+    ffi_return!(s, _wit_func_get_by_index(wit, index, res))
+}
+fn _wit_func_get_by_index(wit: *const WIT, index: usize, res: *mut *const WITFunction) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    let name = &wit.iface.functions[index].name;
+    let func = wit.funcs.get(name);
+    if let Some(func) = func {
+        unsafe {
+            *res = func as *const WITFunction;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Function `{}` not found", &name))
+    }
+}
+
+// Walks the interface's functions in declaration order.
+#[no_mangle]
+pub extern "C" fn wit_func_walk<'a>(s: *mut WITSession, wit: *const WIT, res: *mut *mut WITFuncIter<'a>) -> bool {
+    ffi_return!(s, _wit_func_walk(wit, res))
+}
+fn _wit_func_walk<'a>(wit: *const WIT, res: *mut *mut WITFuncIter<'a>) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit: &'a WIT = unsafe {
+        &*wit
+    };
+    let mut inner_iter = wit.iface.functions.iter();
+    let item = func_iter_item(&wit.funcs, inner_iter.next())?;
+    let safe_res = Box::into_raw(
+        Box::new(
+            WITFuncIter {
+                funcs: &wit.funcs,
+                inner_iter,
+                item,
+            }
+        )
+    );
+    unsafe {
+        *res = safe_res;
+    }
+    Ok(())
+}
+
+fn func_iter_item<'a>(funcs: &'a HashMap<String, WITFunction>, next: Option<&parser::Function>) -> Result<Option<&'a WITFunction>> {
+    match next {
+        Some(f) => {
+            funcs.get(&f.name)
+                .map(Some)
+                .ok_or_else(|| anyhow!("Function `{}` not found", &f.name))
+        },
+        None => Ok(None)
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_iter_off(_s: *mut WITSession, iter: *const WITFuncIter) -> bool {
+    if iter.is_null() {
+        return true;
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    iter.item.is_none()
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_iter_next(s: *mut WITSession, iter: *mut WITFuncIter) -> bool {
+    ffi_return!(s, _wit_func_iter_next(iter))
+}
+fn _wit_func_iter_next(iter: *mut WITFuncIter) -> Result<()> {
+    if iter.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    if wit_func_iter_off(ptr::null_mut(), iter) {
+        return Err(anyhow!("Iterator out of bounds!"));
+    }
+    let iter = unsafe {
+        &mut *iter
+    };
+    iter.item = func_iter_item(iter.funcs, iter.inner_iter.next())?;
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_iter_at(s: *mut WITSession, iter: *const WITFuncIter, res: *mut *const WITFunction) -> bool {
+    ffi_return!(s, _wit_func_iter_at(iter, res))
+}
+fn _wit_func_iter_at(iter: *const WITFuncIter, res: *mut *const WITFunction) -> Result<()> {
+    if iter.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    if let Some(item) = iter.item {
+        unsafe {
+            *res = item as *const WITFunction;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Iterator out of bounds!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_iter_delete(_s: *mut WITSession, iter: *mut WITFuncIter) {
+    if !iter.is_null() {
+        unsafe {
+            drop(Box::from_raw(iter));
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_get_by_name(s: *mut WITSession, wit: *const WIT, fname: *const c_char, res: *mut *const WITFunction) -> bool {
+    ffi_return!(s, _wit_func_get_by_name(wit, fname, res))
+}
+fn _wit_func_get_by_name(wit: *const WIT, fname: *const c_char, res: *mut *const WITFunction) -> Result<()> {
+    if wit.is_null() || fname.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    let fname = unsafe {
+        CStr::from_ptr(fname)
+    };
+    let fname_str = fname.to_str()?;
+    if let Some(func) = wit.funcs.get(&fname_str.to_string()) {
+        unsafe {
+            *res = func as *const WITFunction;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Function `{}` not found", &fname_str))
+    }
+}
+
+// Like wit_func_get_by_name, but ignores case and treats `_` and `-` as
+// equal, so e.g. `My_Func` finds `my-func`.  An exact match always wins;
+// otherwise it is an error if more than one function matches.
+#[no_mangle]
+pub extern "C" fn wit_func_get_by_name_normalized(s: *mut WITSession, wit: *const WIT, fname: *const c_char, res: *mut *const WITFunction) -> bool {
+    ffi_return!(s, _wit_func_get_by_name_normalized(wit, fname, res))
+}
+fn _wit_func_get_by_name_normalized(wit: *const WIT, fname: *const c_char, res: *mut *const WITFunction) -> Result<()> {
+    if wit.is_null() || fname.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    let fname = unsafe {
+        CStr::from_ptr(fname)
+    };
+    let fname_str = fname.to_str()?;
+    let func = match wit.funcs.get(fname_str) {
+        Some(func) => func,
+        None => {
+            let key = ident::normalize(fname_str);
+            let mut matches = wit.iface.functions.iter()
+                .filter(|f| ident::normalize(&f.name) == key);
+            let name = &matches.next()
+                .ok_or_else(|| anyhow!("Function `{}` not found", &fname_str))?
+                .name;
+            if let Some(other) = matches.next() {
+                return Err(anyhow!("Function `{}` is ambiguous (matches `{}` and `{}`)", &fname_str, name, other.name));
+            }
+            wit.funcs.get(name)
+                .ok_or_else(|| anyhow!("Function `{}` not found", name))?
+        }
+    };
+    unsafe {
+        *res = func as *const WITFunction;
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_type_count_get(s: *mut WITSession, wit: *const WIT, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_type_count_get(wit, res))
+}
+fn _wit_type_count_get(wit: *const WIT, res: *mut usize) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    unsafe {
+        *res = wit.types.len();
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_type_get_by_index(s: *mut WITSession, wit: *const WIT, index: usize, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_type_get_by_index(wit, index, res))
+}
+fn _wit_type_get_by_index(wit: *const WIT, index: usize, res: *mut *const WITTypeDef) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    if let Some(td) = wit.types.get(index) {
+        unsafe {
+            *res = td as *const WITTypeDef;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Type index {} out of bounds!", index))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_resource_count_get(s: *mut WITSession, wit: *const WIT, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_resource_count_get(wit, res))
+}
+fn _wit_resource_count_get(wit: *const WIT, res: *mut usize) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    unsafe {
+        *res = wit.resources.len();
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_resource_get_by_index(s: *mut WITSession, wit: *const WIT, index: usize, res: *mut *const WITResource) -> bool {
+    ffi_return!(s, _wit_resource_get_by_index(wit, index, res))
+}
+fn _wit_resource_get_by_index(wit: *const WIT, index: usize, res: *mut *const WITResource) -> Result<()> {
+    if wit.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let wit  = unsafe {
+        &*wit
+    };
+    if let Some(r) = wit.resources.get(index) {
+        unsafe {
+            *res = r as *const WITResource;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Resource index {} out of bounds!", index))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_resource_name_get(s: *mut WITSession, r: *const WITResource, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_resource_name_get(r, res))
+}
+fn _wit_resource_name_get(r: *const WITResource, res: *mut *const c_char) -> Result<()> {
+    if r.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let r = unsafe {
+        &*r
+    };
+    unsafe {
+        *res = r.name.as_ptr();
+    }
+    Ok(())
+}
+
+// Returns the number of methods and statics of the resource.
+#[no_mangle]
+pub extern "C" fn wit_resource_func_count_get(s: *mut WITSession, r: *const WITResource, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_resource_func_count_get(r, res))
+}
+fn _wit_resource_func_count_get(r: *const WITResource, res: *mut usize) -> Result<()> {
+    if r.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let r = unsafe {
+        &*r
+    };
+    unsafe {
+        *res = r.funcs.len();
+    }
+    Ok(())
+}
+
+// Use wit_func_kind_get to tell methods from statics.
+#[no_mangle]
+pub extern "C" fn wit_resource_func_get_by_index(s: *mut WITSession, r: *const WITResource, index: usize, res: *mut *const WITFunction) -> bool {
+    ffi_return!(s, _wit_resource_func_get_by_index(r, index, res))
+}
+fn _wit_resource_func_get_by_index(r: *const WITResource, index: usize, res: *mut *const WITFunction) -> Result<()> {
+    if r.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let r = unsafe {
+        &*r
+    };
+    if let Some(func) = r.funcs.get(index) {
+        unsafe {
+            *res = *func;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Function index {} out of bounds!", index))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_walk<'a>(s: *mut WITSession, func: *const WITFunction, res: *mut *mut WITTypeDefIter<'a>) -> bool {
+    ffi_return!(s, _wit_func_param_walk(func, res))
+}
+fn _wit_func_param_walk<'a>(func: *const WITFunction, res: *mut *mut WITTypeDefIter<'a>) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func  = unsafe {
+        &*func
+    };
+    let inner_iter = func.iface.functions[func.index].params.iter();
+    let res_safe = typedef_iter_new(func, inner_iter)?;
+    unsafe {
+        *res = res_safe;
+    }
+    Ok(())
+}
+
+// Walks the function's results.  A function returning unit has no results.
+#[no_mangle]
+pub extern "C" fn wit_func_result_walk<'a>(s: *mut WITSession, func: *const WITFunction, res: *mut *mut WITTypeDefIter<'a>) -> bool {
+    ffi_return!(s, _wit_func_result_walk(func, res))
+}
+fn _wit_func_result_walk<'a>(func: *const WITFunction, res: *mut *mut WITTypeDefIter<'a>) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func  = unsafe {
+        &*func
+    };
+    let res_safe = typedef_iter_new(func, func.results.iter())?;
+    unsafe {
+        *res = res_safe;
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_result_count_get(s: *mut WITSession, func: *const WITFunction, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_func_result_count_get(func, res))
+}
+fn _wit_func_result_count_get(func: *const WITFunction, res: *mut usize) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = func.results.len();
+    }
+    Ok(())
+}
+
+fn typedef_iter_new<'a>(func: &WITFunction, mut inner_iter: Iter<'a, (String, Type)>) -> Result<*mut WITTypeDefIter<'a>> {
+    let next = inner_iter.next();
+    let item: Option<WITTypeDef> = match next {
+        Some(n) => {
+            Some(
+                WITTypeDef::new(&func.iface, &func.align, n.0.as_str(), n.1.clone())?
+            )
+        },
+        _ => None
+    };
+    Ok(
+        Box::into_raw(
+            Box::new(
+                WITTypeDefIter {
+                    iface:      func.iface.clone(),
+                    align:      func.align.clone(),
+                    inner_iter,
+                    item,
+                }
+            )
+        )
+    )
+}
+
+// Returns the instructions for lifting the function's results, as returned
+// from the guest export (see wit_func_sig_get), into structured values.  The
+// plan is empty for functions that return unit.
+#[no_mangle]
+pub extern "C" fn wit_func_lift_plan_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const WITPlanInstr, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_func_lift_plan_get(func, res, len))
+}
+fn _wit_func_lift_plan_get(func: *const WITFunction, res: *mut *const WITPlanInstr, len: *mut usize) -> Result<()> {
+    if func.is_null() || res.is_null() || len.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    if func.lift_plan.get().is_none() {
+        let plan = plan::lift_plan(&func.iface, &func.align, &func.res.ty, func.sig.sig.retptr)?;
+        let _ = func.lift_plan.set(plan);
+    }
+    let plan = func.lift_plan.get().map(|p| p.as_slice()).unwrap_or(&[]);
+    unsafe {
+        *res = plan.as_ptr();
+        *len = plan.len();
+    }
+    Ok(())
+}
+
+// Returns a rough estimate of the work needed to call the function, for
+// choosing between interpreting plans and generating dedicated code.
+#[no_mangle]
+pub extern "C" fn wit_func_cost_get(s: *mut WITSession, func: *const WITFunction, res: *mut WITFuncCost) -> bool {
+    ffi_return!(s, _wit_func_cost_get(func, res))
+}
+fn _wit_func_cost_get(func: *const WITFunction, res: *mut WITFuncCost) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    if func.cost.get().is_none() {
+        let mut cost = WITFuncCost {
+            instrs:      0,
+            has_strings: false,
+            has_lists:   false,
+        };
+        for td in func.params.iter().chain(Some(&func.res)) {
+            let plan = plan::lift_plan(&func.iface, &func.align, &td.ty, false)?;
+            cost.instrs += plan.len();
+            cost.has_strings |= plan.iter().any(|i| i.op == WITPlanOp::String);
+            cost.has_lists |= plan.iter().any(|i| i.op == WITPlanOp::List);
+        }
+        let _ = func.cost.set(cost);
+    }
+    unsafe {
+        *res = func.cost.get().copied().unwrap();
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_count_get(s: *mut WITSession, func: *const WITFunction, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_func_param_count_get(func, res))
+}
+fn _wit_func_param_count_get(func: *const WITFunction, res: *mut usize) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = func.params.len();
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_get_by_index(s: *mut WITSession, func: *const WITFunction, index: usize, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_func_param_get_by_index(func, index, res))
+}
+fn _wit_func_param_get_by_index(func: *const WITFunction, index: usize, res: *mut *const WITTypeDef) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    if let Some(param) = func.params.get(index) {
+        unsafe {
+            *res = param as *const WITTypeDef;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Parameter index {} out of bounds!", index))
+    }
+}
+
+// Returns the size of the block the host must allocate in guest memory to
+// pass the function's params indirectly (see wit_sig_is_indirect).  The
+// params are laid out in the block like the fields of a record.
+#[no_mangle]
+pub extern "C" fn wit_func_param_block_size_get(s: *mut WITSession, func: *const WITFunction, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_func_param_block_size_get(func, res))
+}
+fn _wit_func_param_block_size_get(func: *const WITFunction, res: *mut usize) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = param_block_layout(func).0;
+    }
+    Ok(())
+}
+
+// Returns the alignment of the indirect params block.
+#[no_mangle]
+pub extern "C" fn wit_func_param_block_align_get(s: *mut WITSession, func: *const WITFunction, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_func_param_block_align_get(func, res))
+}
+fn _wit_func_param_block_align_get(func: *const WITFunction, res: *mut usize) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = param_block_layout(func).1;
+    }
+    Ok(())
+}
+
+// Returns the byte offset of the param at `index` within the indirect params
+// block (see wit_func_param_block_size_get).
+#[no_mangle]
+pub extern "C" fn wit_func_param_offset_get(s: *mut WITSession, func: *const WITFunction, index: usize, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_func_param_offset_get(func, index, res))
+}
+fn _wit_func_param_offset_get(func: *const WITFunction, index: usize, res: *mut usize) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    let offsets = func.align.field_offsets(func.params.iter().map(|p| &p.ty));
+    if let Some(offset) = offsets.get(index) {
+        unsafe {
+            *res = *offset;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Parameter index {} out of bounds!", index))
+    }
+}
+
+// Sets `res` to a bitmask with bit i set if param i contains a string or
+// list, i.e. if lowering it requires allocating guest memory.  Fails for
+// functions with more than 64 params.
+#[no_mangle]
+pub extern "C" fn wit_func_param_alloc_mask_get(s: *mut WITSession, func: *const WITFunction, res: *mut u64) -> bool {
+    ffi_return!(s, _wit_func_param_alloc_mask_get(func, res))
+}
+fn _wit_func_param_alloc_mask_get(func: *const WITFunction, res: *mut u64) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    if func.params.len() > 64 {
+        return Err(anyhow!("Too many parameters for a 64-bit mask!"));
+    }
+    let mask = func.params.iter()
+        .enumerate()
+        .filter(|(_, p)| owns_memory(&p.iface, &p.ty))
+        .fold(0u64, |mask, (i, _)| mask | (1 << i));
+    unsafe {
+        *res = mask;
+    }
+    Ok(())
+}
+
+// For a function whose params are passed directly, maps the core param at
+// `core_index` to the WIT param it belongs to and the index of the core
+// value within that param's flattening (see wit_typedef_flatten).  Fails if
+// the params are passed indirectly or `core_index` is the return pointer.
+#[no_mangle]
+pub extern "C" fn wit_func_core_param_map_get(s: *mut WITSession, func: *const WITFunction, core_index: usize, param: *mut usize, leaf: *mut usize) -> bool {
+    ffi_return!(s, _wit_func_core_param_map_get(func, core_index, param, leaf))
+}
+fn _wit_func_core_param_map_get(func: *const WITFunction, core_index: usize, param: *mut usize, leaf: *mut usize) -> Result<()> {
+    if func.is_null() || param.is_null() || leaf.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    if func.sig.sig.indirect_params {
+        return Err(anyhow!("Function `{}` passes its params indirectly", func.name.to_str()?));
+    }
+    let mut start = 0;
+    for (i, p) in func.params.iter().enumerate() {
+        let mut flat = Vec::new();
+        flatten(&p.iface, &p.ty, func.align.ptr_type(), &mut flat);
+        if core_index < start + flat.len() {
+            unsafe {
+                *param = i;
+                *leaf = core_index - start;
+            }
+            return Ok(());
+        }
+        start += flat.len();
+    }
+    Err(anyhow!("Core param index {} does not belong to a WIT param!", core_index))
+}
+
+// Returns the (size, align) of the params when laid out as a record.
+fn param_block_layout(func: &WITFunction) -> (usize, usize) {
+    let align = func.params.iter()
+        .map(|p| func.align.align(&p.ty))
+        .fold(1, usize::max);
+    let end = func.align.field_offsets(func.params.iter().map(|p| &p.ty))
+        .iter()
+        .zip(&func.params)
+        .map(|(off, p)| off + func.align.size(&p.ty))
+        .last()
+        .unwrap_or(0);
+    (align_to(end, align), align)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_result_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_func_result_get(func, res))
+}
+fn _wit_func_result_get(func: *const WITFunction, res: *mut *const WITTypeDef) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    unsafe {
+        *res = &func.res;
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_iter_off(_s: *mut WITSession, iter: *const WITTypeDefIter) -> bool {
+    if iter.is_null() {
+        return true;
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    iter.item.is_none()
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_iter_next(s: *mut WITSession, iter: *mut WITTypeDefIter) -> bool {
+    ffi_return!(s, _wit_typedef_iter_next(iter))
+}
+fn _wit_typedef_iter_next(iter: *mut WITTypeDefIter) -> Result<()> {
+    if iter.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    if wit_typedef_iter_off(ptr::null_mut(), iter) {
+        return Err(anyhow!("Iterator out of bounds!"));
+    }
+    let iter = unsafe {
+        &mut *iter
+    };
+    let next = iter.inner_iter.next();
+    iter.item = {
+        if let Some(next) = next {
+            Some(
+                WITTypeDef::new(&iter.iface, &iter.align, next.0.as_str(), next.1.clone())?
+            )
+        } else {
+            None
+        }
+    };
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_iter_at<'a>(s: *mut WITSession, iter: *const WITTypeDefIter, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_typedef_iter_at(iter, res))
+}
+fn _wit_typedef_iter_at(iter: *const WITTypeDefIter, res: *mut *const WITTypeDef) -> Result<()> {
+    if iter.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    if let Some(item) = &iter.item {
+        unsafe {
+            *res = item as *const WITTypeDef;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Iterator out of bounds!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_iter_delete(_s: *mut WITSession, iter: *mut WITTypeDefIter) {
+    if !iter.is_null() {
+        unsafe {
+            drop(Box::from_raw(iter));
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_record_field_walk<'a>(s: *mut WITSession, td: *const WITTypeDef, res: *mut *mut WITFieldIter<'a>) -> bool {
+    ffi_return!(s, _wit_record_field_walk(td, res))
+}
+fn _wit_record_field_walk<'a>(td: *const WITTypeDef, res: *mut *mut WITFieldIter<'a>) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Record(rec) = &td.iface.types[*id].kind {
+            let mut inner_iter = rec.fields.iter();
+            let next = inner_iter.next();
+            let item: Option<WITTypeDef> = match next {
+                Some(f) => 
+                    Some(
+                        WITTypeDef::new(&td.iface, &td.align, f.name.as_str(), f.ty.clone())?
+                            .with_docs(&f.docs)?
+                    ),
+                _ => None
+            };
+            let safe_res = 
+                Box::into_raw(
+                    Box::new(
+                        WITFieldIter {
+                            iface:      td.iface.clone(),
+                            align:      td.align.clone(),
+                            inner_iter,
+                            item
+                        }
+                    )
+                );
+            unsafe {
+                *res = safe_res;
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be record type!"))
+        }
+    } else {
+        Err(anyhow!("Iterator out of bounds!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_record_field_count_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_record_field_count_get(td, res))
+}
+fn _wit_record_field_count_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Record(rec) = &td.iface.types[*id].kind {
+            unsafe {
+                *res = rec.fields.len();
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be record type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be record type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_record_field_get_by_index(s: *mut WITSession, td: *const WITTypeDef, index: usize, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_record_field_get_by_index(td, index, res))
+}
+fn _wit_record_field_get_by_index(td: *const WITTypeDef, index: usize, res: *mut *const WITTypeDef) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Record(_) = &td.iface.types[*id].kind {
+            // Return cached child type, if it exists.
+            match td.child(index)? {
+                Some(field) => {
+                    unsafe {
+                        *res = field as *const WITTypeDef;
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Field index {} out of bounds!", index))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be record type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be record type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_field_iter_off(_s: *mut WITSession, iter: *const WITFieldIter) -> bool {
+    if iter.is_null() {
+        return true;
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    iter.item.is_none()
+}
+
+#[no_mangle]
+pub extern "C" fn wit_field_iter_next(s: *mut WITSession, iter: *mut WITFieldIter) -> bool {
+    ffi_return!(s, _wit_field_iter_next(iter))
+}
+fn _wit_field_iter_next(iter: *mut WITFieldIter) -> Result<()> {
+    if iter.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    if wit_field_iter_off(ptr::null_mut(), iter) {
+        return Err(anyhow!("Iterator out of bounds"));
+    }
+    let iter = unsafe {
+        &mut *iter
+    };
+    let next = iter.inner_iter.next();
+    iter.item = {
+        if let Some(next) = next {
+            Some(
+                WITTypeDef::new(&iter.iface, &iter.align, next.name.as_str(), next.ty.clone())?
+                    .with_docs(&next.docs)?
+            )
+        } else {
+            None
+        }
+    };
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_field_iter_at<'a>(s: *mut WITSession, iter: *const WITFieldIter<'a>, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_field_iter_at(iter, res))
+}
+fn _wit_field_iter_at<'a>(iter: *const WITFieldIter<'a>, res: *mut *const WITTypeDef) -> Result<()> {
+    if iter.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    if let Some(item) = &iter.item {
+        unsafe {
+            *res = item as *const WITTypeDef;
+            Ok(())
+        }
+    } else {
+        Err(anyhow!("Iterator out of bounds!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_field_iter_delete(_s: *mut WITSession, iter: *mut WITFieldIter) {
+    if !iter.is_null() {
+        unsafe {
+            drop(Box::from_raw(iter));
+        }
+    }
+}
+
+// Returns the size in bytes of the discriminant.  Works for every tagged
+// type: variant, enum, union, option and expected.
+#[no_mangle]
+pub extern "C" fn wit_variant_tag_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut u8) -> bool {
+    ffi_return!(s, _wit_variant_tag_get(td, res))
+}
+fn _wit_variant_tag_get(td: *const WITTypeDef, res: *mut u8) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Some(tag) = tag_of(&td.iface, &td.ty) {
+        let bits = match tag {
+            Int::U8 => 1,
+            Int::U16 => 2,
+            Int::U32 => 4,
+            Int::U64 => 8,
+        };
+        unsafe {
+            *res = bits;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid argument; must be a Variant, Enum, Union, Option or Expected type"))
+    }
+}
+
+fn tag_of(iface: &Interface, ty: &Type) -> Option<Int> {
+    if let Type::Id(id) = ty {
+        match &iface.types[*id].kind {
+            TypeDefKind::Variant(v) => Some(v.tag()),
+            TypeDefKind::Enum(e) => Some(e.tag()),
+            TypeDefKind::Union(u) => Some(u.tag()),
+            TypeDefKind::Option(_) |
+            TypeDefKind::Expected(_) => Some(Int::U8),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+// Returns the discriminant type and case payload types of variant-like types
+// (variant, union, option and expected).
+fn cases_of<'a>(iface: &'a Interface, ty: &'a Type) -> Option<(Int, Vec<&'a Type>)> {
+    if let Type::Id(id) = ty {
+        match &iface.types[*id].kind {
+            TypeDefKind::Variant(v) => Some((v.tag(), v.cases.iter().map(|c| &c.ty).collect())),
+            TypeDefKind::Union(u) => Some((u.tag(), u.cases.iter().map(|c| &c.ty).collect())),
+            TypeDefKind::Option(t) => Some((Int::U8, vec![&Type::Unit, t])),
+            TypeDefKind::Expected(e) => Some((Int::U8, vec![&e.ok, &e.err])),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+// Returns the byte offset of the payload of a variant, union, option or
+// expected from the start of the value, i.e. after the discriminant and any
+// padding.  All cases share this offset.
+#[no_mangle]
+pub extern "C" fn wit_variant_payload_offset_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_variant_payload_offset_get(td, res))
+}
+fn _wit_variant_payload_offset_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Some((tag, cases)) = cases_of(&td.iface, &td.ty) {
+        unsafe {
+            *res = td.align.payload_offset(tag, cases);
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid argument; must be a Variant, Union, Option or Expected type"))
+    }
+}
+
+// Returns the size of the largest case payload of a variant, union, option
+// or expected.
+#[no_mangle]
+pub extern "C" fn wit_variant_max_payload_size_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_variant_max_payload_size_get(td, res))
+}
+fn _wit_variant_max_payload_size_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Some((_, cases)) = cases_of(&td.iface, &td.ty) {
+        unsafe {
+            *res = cases.iter().map(|t| td.align.size(t)).max().unwrap_or(0);
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid argument; must be a Variant, Union, Option or Expected type"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_variant_case_walk<'a>(s: *mut WITSession, td: *const WITTypeDef, res: *mut *mut WITCaseIter<'a>) -> bool {
+    ffi_return!(s, _wit_variant_case_walk(td, res))
+}
+fn _wit_variant_case_walk<'a>(td: *const WITTypeDef, res: *mut *mut WITCaseIter<'a>) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Variant(v) = &td.iface.types[*id].kind {
+            let mut inner_iter = v.cases.iter().enumerate();
+            let next = inner_iter.next();
+            let item: Option<WITTypeDef> = match next {
+                Some((i, c)) => {
+                    Some(
+                        WITTypeDef::new(&td.iface, &td.align, c.name.as_str(), c.ty.clone())?
+                            .with_discriminant(i)
+                            .with_docs(&c.docs)?
+                    )
+                },
+                _ => None
+            };
+            let safe_res = 
+                Box::into_raw(
+                    Box::new(
+                        WITCaseIter {
+                            iface:   td.iface.clone(),
+                            align:   td.align.clone(),
+                            inner_iter,
+                            item
+                        }
+                    )
+                );
+            unsafe {
+                *res = safe_res;
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid argument.  Must be a variant type!"))
+        }
+    } else {
+        Err(anyhow!("Iterator out of bounds!"))
+    }
+}
+#[no_mangle]
+pub extern "C" fn wit_variant_case_count_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_variant_case_count_get(td, res))
+}
+fn _wit_variant_case_count_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Variant(v) = &td.iface.types[*id].kind {
+            unsafe {
+                *res = v.cases.len();
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid argument; must be a Variant type"))
+        }
+    } else {
+        Err(anyhow!("Invalid argument; must be a Variant type"))
+    }
+}
+
+// Cases are indexed by discriminant, so the tag read from guest memory can be
+// passed directly as `index`.
+#[no_mangle]
+pub extern "C" fn wit_variant_case_get_by_index(s: *mut WITSession, td: *const WITTypeDef, index: usize, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_variant_case_get_by_index(td, index, res))
+}
+fn _wit_variant_case_get_by_index(td: *const WITTypeDef, index: usize, res: *mut *const WITTypeDef) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Variant(_) = &td.iface.types[*id].kind {
+            // Return cached child type, if it exists.
+            match td.child(index)? {
+                Some(case) => {
+                    unsafe {
+                        *res = case as *const WITTypeDef;
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Case index {} out of bounds!", index))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid argument; must be a Variant type"))
+        }
+    } else {
+        Err(anyhow!("Invalid argument; must be a Variant type"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_case_iter_off(_s: *mut WITSession, iter: *const WITCaseIter) -> bool {
+    if iter.is_null() {
+        return true;
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    iter.item.is_none()
+}
+
+#[no_mangle]
+pub extern "C" fn wit_case_iter_next(s: *mut WITSession, iter: *mut WITCaseIter) -> bool {
+    ffi_return!(s, _wit_case_iter_next(iter))
+}
+fn _wit_case_iter_next(iter: *mut WITCaseIter) -> Result<()> {
+    if iter.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    if wit_case_iter_off(ptr::null_mut(), iter) {
+        return Err(anyhow!("Iterator out of bounds"));
+    }
+    let iter = unsafe {
+        &mut *iter
+    };
+    let next = iter.inner_iter.next();
+    iter.item = {
+        if let Some((i, next)) = next {
+            Some(
+                WITTypeDef::new(&iter.iface, &iter.align, next.name.as_str(), next.ty.clone())?
+                    .with_discriminant(i)
+                    .with_docs(&next.docs)?
+            )
+        } else {
+            None
+        }
+    };
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_case_iter_at<'a>(s: *mut WITSession, iter: *const WITCaseIter<'a>, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_case_iter_at(iter, res))
+}
+fn _wit_case_iter_at<'a>(iter: *const WITCaseIter<'a>, res: *mut *const WITTypeDef) -> Result<()> {
+    if iter.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    if let Some(item) = &iter.item {
+        unsafe {
+            *res = item as *const WITTypeDef;
+            Ok(())
+        }
+    } else {
+        Err(anyhow!("Iterator out of bounds!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_case_iter_delete(_s: *mut WITSession, iter: *mut WITCaseIter) {
+    if !iter.is_null() {
+        unsafe {
+            drop(Box::from_raw(iter));
+        }
+    }
+}
+
+// Returns the discriminant value written to the tag when lowering this case.
+// Only valid for variant or union case typedefs.
+#[no_mangle]
+pub extern "C" fn wit_case_discriminant_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut u32) -> bool {
+    ffi_return!(s, _wit_case_discriminant_get(td, res))
+}
+fn _wit_case_discriminant_get(td: *const WITTypeDef, res: *mut u32) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    match td.discr {
+        Some(discr) => {
+            unsafe {
+                *res = discr;
+            }
+            Ok(())
+        },
+        _ => {
+            Err(anyhow!("Invalid parameter.  Must be a variant case!"))
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_count_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_union_case_count_get(td, res))
+}
+fn _wit_union_case_count_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Union(u) = &td.iface.types[*id].kind {
+            unsafe {
+                *res = u.cases.len();
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'union' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'union' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_walk<'a>(s: *mut WITSession, td: *const WITTypeDef, res: *mut *mut WITUnionCaseIter<'a>) -> bool {
+    ffi_return!(s, _wit_union_case_walk(td, res))
+}
+fn _wit_union_case_walk<'a>(td: *const WITTypeDef, res: *mut *mut WITUnionCaseIter<'a>) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Union(u) = &td.iface.types[*id].kind {
+            let mut inner_iter = u.cases.iter().enumerate();
+            let next = inner_iter.next();
+            let item: Option<WITTypeDef> = match next {
+                Some((i, c)) => {
+                    Some(
+                        WITTypeDef::new(&td.iface, &td.align, "", c.ty.clone())?
+                            .with_discriminant(i)
+                            .with_docs(&c.docs)?
+                    )
+                },
+                _ => None
+            };
+            let safe_res = 
+                Box::into_raw(
+                    Box::new(
+                        WITUnionCaseIter {
+                            iface:   td.iface.clone(),
+                            align:   td.align.clone(),
+                            inner_iter,
+                            item
+                        }
+                    )
+                );
+            unsafe {
+                *res = safe_res;
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid argument.  Must be a union type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid argument.  Must be a union type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_iter_off(_s: *mut WITSession, iter: *const WITUnionCaseIter) -> bool {
+    if iter.is_null() {
+        return true;
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    iter.item.is_none()
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_iter_next(s: *mut WITSession, iter: *mut WITUnionCaseIter) -> bool {
+    ffi_return!(s, _wit_union_case_iter_next(iter))
+}
+fn _wit_union_case_iter_next(iter: *mut WITUnionCaseIter) -> Result<()> {
+    if iter.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    if wit_union_case_iter_off(ptr::null_mut(), iter) {
+        return Err(anyhow!("Iterator out of bounds"));
+    }
+    let iter = unsafe {
+        &mut *iter
+    };
+    let next = iter.inner_iter.next();
+    iter.item = {
+        if let Some((i, next)) = next {
+            Some(
+                WITTypeDef::new(&iter.iface, &iter.align, "", next.ty.clone())?
+                    .with_discriminant(i)
+                    .with_docs(&next.docs)?
+            )
+        } else {
+            None
+        }
+    };
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_iter_at<'a>(s: *mut WITSession, iter: *const WITUnionCaseIter<'a>, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_union_case_iter_at(iter, res))
+}
+fn _wit_union_case_iter_at<'a>(iter: *const WITUnionCaseIter<'a>, res: *mut *const WITTypeDef) -> Result<()> {
+    if iter.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let iter = unsafe {
+        &*iter
+    };
+    if let Some(item) = &iter.item {
+        unsafe {
+            *res = item as *const WITTypeDef;
+            Ok(())
+        }
+    } else {
+        Err(anyhow!("Iterator out of bounds!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_iter_delete(_s: *mut WITSession, iter: *mut WITUnionCaseIter) {
+    if !iter.is_null() {
+        unsafe {
+            drop(Box::from_raw(iter));
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_expected_ok_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, "wit_expected_ok_typedef_get", _wit_expected_typedef_get(true, td, res))
+}
+#[no_mangle]
+pub extern "C" fn wit_expected_err_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, "wit_expected_err_typedef_get", _wit_expected_typedef_get(false, td, res))
+}
+fn _wit_expected_typedef_get(get_ok: bool, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"))
+    }
+    let td = unsafe { &*td };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Expected(_) = &td.iface.types[*id].kind {
+            let subty_opt = {
+                if get_ok {
+                    td.child(0)?
+                } else {
+                    td.child(1)?
+                }
             };
-            let safe_res = 
-                Box::into_raw(
-                    Box::new(
-                        WITFieldIter {
-                            iface:      td.iface.clone(),
-                            align:      td.align.clone(),
-                            inner_iter,
-                            item
-                        }
-                    )
-                );
+            match subty_opt {
+                Some(subty) => {
+                    unsafe {
+                        *res = subty as *const WITTypeDef;
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Could not determine 'expected' payload type!"))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'expected' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'expected' type!"))
+    }
+}
+
+// Return the byte offsets of the ok and err payloads of an expected from the
+// start of the value.  Both payloads share the same storage, so the offsets
+// are equal; they're exposed separately to pair with the typedef getters.
+#[no_mangle]
+pub extern "C" fn wit_expected_ok_offset_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, "wit_expected_ok_offset_get", _wit_expected_offset_get(td, res))
+}
+#[no_mangle]
+pub extern "C" fn wit_expected_err_offset_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, "wit_expected_err_offset_get", _wit_expected_offset_get(td, res))
+}
+fn _wit_expected_offset_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"))
+    }
+    let td = unsafe { &*td };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Expected(e) = &td.iface.types[*id].kind {
+            unsafe {
+                *res = td.align.payload_offset(Int::U8, [&e.ok, &e.err]);
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'expected' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'expected' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_flags_count_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_flags_count_get(td, res))
+}
+fn _wit_flags_count_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Flags(f) = &td.iface.types[*id].kind {
+            unsafe {
+                *res = f.flags.len();
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'flags' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'flags' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_flags_name_get_by_index(s: *mut WITSession, td: *const WITTypeDef, index: usize, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_flags_name_get_by_index(td, index, res))
+}
+fn _wit_flags_name_get_by_index(td: *const WITTypeDef, index: usize, res: *mut *const c_char) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Flags(_) = &td.iface.types[*id].kind {
+            match td.names.get(index) {
+                Some(name) => {
+                    unsafe {
+                        *res = name.as_ptr();
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Flag index {} out of bounds!", index))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'flags' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'flags' type!"))
+    }
+}
+
+// Returns the number of i32 core values a flags type flattens to.  Flags with
+// more than 32 entries need one i32 per 32 flags.
+#[no_mangle]
+pub extern "C" fn wit_flags_word_count_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_flags_word_count_get(td, res))
+}
+fn _wit_flags_word_count_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Flags(f) = &td.iface.types[*id].kind {
+            unsafe {
+                *res = f.repr().count();
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'flags' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'flags' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_enum_case_count_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_enum_case_count_get(td, res))
+}
+fn _wit_enum_case_count_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Enum(e) = &td.iface.types[*id].kind {
+            unsafe {
+                *res = e.cases.len();
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'enum' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'enum' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_enum_case_name_get_by_index(s: *mut WITSession, td: *const WITTypeDef, index: usize, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_enum_case_name_get_by_index(td, index, res))
+}
+fn _wit_enum_case_name_get_by_index(td: *const WITTypeDef, index: usize, res: *mut *const c_char) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Enum(_) = &td.iface.types[*id].kind {
+            match td.names.get(index) {
+                Some(name) => {
+                    unsafe {
+                        *res = name.as_ptr();
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Enum case index {} out of bounds!", index))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'enum' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'enum' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_tuple_elem_count_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_tuple_elem_count_get(td, res))
+}
+fn _wit_tuple_elem_count_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Tuple(t) = &td.iface.types[*id].kind {
+            unsafe {
+                *res = t.types.len();
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'tuple' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'tuple' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_tuple_elem_typedef_get_by_index(s: *mut WITSession, td: *const WITTypeDef, index: usize, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_tuple_elem_typedef_get_by_index(td, index, res))
+}
+fn _wit_tuple_elem_typedef_get_by_index(td: *const WITTypeDef, index: usize, res: *mut *const WITTypeDef) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Tuple(_) = &td.iface.types[*id].kind {
+            // Return cached child type, if it exists.
+            match td.child(index)? {
+                Some(elem) => {
+                    unsafe {
+                        *res = elem as *const WITTypeDef;
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Tuple element index {} out of bounds!", index))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'tuple' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'tuple' type!"))
+    }
+}
+
+// Returns the byte offset of the tuple element at `index` from the start of
+// the tuple.  Elements are laid out like the fields of a record.
+#[no_mangle]
+pub extern "C" fn wit_tuple_elem_offset_get_by_index(s: *mut WITSession, td: *const WITTypeDef, index: usize, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_tuple_elem_offset_get_by_index(td, index, res))
+}
+fn _wit_tuple_elem_offset_get_by_index(td: *const WITTypeDef, index: usize, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Tuple(t) = &td.iface.types[*id].kind {
+            match td.align.field_offsets(&t.types).get(index) {
+                Some(offset) => {
+                    unsafe {
+                        *res = *offset;
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Tuple element index {} out of bounds!", index))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'tuple' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'tuple' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_option_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_option_typedef_get(td, res))
+}
+// Same as wit_option_typedef_get; named to pair with the expected ok/err getters.
+#[no_mangle]
+pub extern "C" fn wit_option_some_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, "wit_option_some_typedef_get", _wit_option_typedef_get(td, res))
+}
+fn _wit_option_typedef_get(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"))
+    }
+    let td = unsafe { &*td };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Option(_) = &td.iface.types[*id].kind {
+            // Return cached child type, if it exists.
+            match td.child(0)? {
+                Some(subty) => {
+                    unsafe {
+                        *res = subty as *const WITTypeDef;
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Could not determine option type!"))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'option' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'option' type!"))
+    }
+}
+
+// Returns the byte offset of the payload of an option from the start of the
+// value, i.e. the discriminant (see wit_variant_tag_get for its size) plus
+// any padding.
+#[no_mangle]
+pub extern "C" fn wit_option_payload_offset_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_option_payload_offset_get(td, res))
+}
+fn _wit_option_payload_offset_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"))
+    }
+    let td = unsafe { &*td };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Option(t) = &td.iface.types[*id].kind {
             unsafe {
-                *res = safe_res;
+                *res = td.align.payload_offset(Int::U8, [&Type::Unit, t]);
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'option' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'option' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_type_aliased_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_type_aliased_typedef_get(td, res))
+}
+fn _wit_type_aliased_typedef_get(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::Type(_) = &td.iface.types[*id].kind {
+            // Return cached child type, if it exists.
+            match td.child(0)? {
+                Some(subty) => {
+                    unsafe {
+                        *res = subty as *const WITTypeDef;
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Could not determine aliased type!"))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be 'type' type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'type' type!"))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wit_list_elem_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    ffi_return!(s, _wit_list_elem_typedef_get(td, res))
+}
+fn _wit_list_elem_typedef_get(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::List(_) = &td.iface.types[*id].kind {
+            // Return cached child type, if it exists.
+            match td.child(0)? {
+                Some(subty) => {
+                    unsafe {
+                        *res = subty as *const WITTypeDef;
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err(anyhow!("Could not determine array element type!"))
+                }
+            }
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be list type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be list type!"))
+    }
+}
+
+// Returns the distance in bytes between consecutive elements of a list,
+// i.e. the element size rounded up to the element alignment.
+#[no_mangle]
+pub extern "C" fn wit_array_elem_stride_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_array_elem_stride_get(td, res))
+}
+fn _wit_array_elem_stride_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Id(id) = &td.ty {
+        if let TypeDefKind::List(elem) = &td.iface.types[*id].kind {
+            unsafe {
+                *res = align_to(td.align.size(elem), td.align.align(elem));
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid parameter.  Must be list type!"))
+        }
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be list type!"))
+    }
+}
+
+// For a list of records (or tuples), fills `offsets` with up to `cap` field
+// offsets within an element, sets `len` to the number of fields and `stride`
+// to the distance between consecutive elements.
+#[no_mangle]
+pub extern "C" fn wit_list_elem_layout_get(s: *mut WITSession, td: *const WITTypeDef, offsets: *mut usize, cap: usize, len: *mut usize, stride: *mut usize) -> bool {
+    ffi_return!(s, _wit_list_elem_layout_get(td, offsets, cap, len, stride))
+}
+fn _wit_list_elem_layout_get(td: *const WITTypeDef, offsets: *mut usize, cap: usize, len: *mut usize, stride: *mut usize) -> Result<()> {
+    if td.is_null() || len.is_null() || stride.is_null() || (offsets.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    let elem = match resolve_alias(&td.iface, &td.ty) {
+        Type::Id(id) => match &td.iface.types[*id].kind {
+            TypeDefKind::List(elem) => resolve_alias(&td.iface, elem),
+            _ => return Err(anyhow!("Invalid parameter.  Must be list type!")),
+        },
+        _ => return Err(anyhow!("Invalid parameter.  Must be list type!")),
+    };
+    let field_tys: Vec<&Type> = match elem {
+        Type::Id(id) => match &td.iface.types[*id].kind {
+            TypeDefKind::Record(r) => r.fields.iter().map(|f| &f.ty).collect(),
+            TypeDefKind::Tuple(t) => t.types.iter().collect(),
+            _ => return Err(anyhow!("Invalid parameter.  Must be list of record or tuple type!")),
+        },
+        _ => return Err(anyhow!("Invalid parameter.  Must be list of record or tuple type!")),
+    };
+    let field_offsets = td.align.field_offsets(field_tys);
+    for (i, off) in field_offsets.iter().take(cap).enumerate() {
+        unsafe {
+            *offsets.add(i) = *off;
+        }
+    }
+    unsafe {
+        *len = field_offsets.len();
+        *stride = align_to(td.align.size(elem), td.align.align(elem));
+    }
+    Ok(())
+}
+
+fn align_to(val: usize, align: usize) -> usize {
+    if align == 0 {
+        return val;
+    }
+    (val + align - 1) / align * align
+}
+
+// Returns the maximum nesting depth of the type.  Scalars, strings, flags and
+// enums have depth 0; each enclosing list, record, variant, etc. adds one.
+// Type aliases are transparent.
+#[no_mangle]
+pub extern "C" fn wit_typedef_depth_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_typedef_depth_get(td, res))
+}
+fn _wit_typedef_depth_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    let depth = type_depth(&td.iface, &td.ty, &mut Vec::new())?;
+    unsafe {
+        *res = depth;
+    }
+    Ok(())
+}
+
+fn type_depth(iface: &Interface, ty: &Type, stack: &mut Vec<TypeId>) -> Result<usize> {
+    let id = match ty {
+        Type::Id(id) => id,
+        _ => return Ok(0),
+    };
+    if stack.contains(id) {
+        return Err(anyhow!("Recursive type has unbounded depth!"));
+    }
+    stack.push(*id);
+    let depth = match &iface.types[*id].kind {
+        TypeDefKind::Type(t) => type_depth(iface, t, stack)?,
+        TypeDefKind::Flags(_) |
+        TypeDefKind::Enum(_) => 0,
+        TypeDefKind::Record(r) => 1 + max_depth(iface, r.fields.iter().map(|f| &f.ty), stack)?,
+        TypeDefKind::Tuple(t) => 1 + max_depth(iface, &t.types, stack)?,
+        TypeDefKind::Variant(v) => 1 + max_depth(iface, v.cases.iter().map(|c| &c.ty), stack)?,
+        TypeDefKind::Union(u) => 1 + max_depth(iface, u.cases.iter().map(|c| &c.ty), stack)?,
+        TypeDefKind::Expected(e) => 1 + max_depth(iface, [&e.ok, &e.err], stack)?,
+        TypeDefKind::Stream(st) => 1 + max_depth(iface, [&st.element, &st.end], stack)?,
+        TypeDefKind::Option(t) |
+        TypeDefKind::List(t) |
+        TypeDefKind::Future(t) => 1 + type_depth(iface, t, stack)?,
+    };
+    stack.pop();
+    Ok(depth)
+}
+
+fn max_depth<'a>(iface: &Interface, tys: impl IntoIterator<Item = &'a Type>, stack: &mut Vec<TypeId>) -> Result<usize> {
+    let mut depth = 0;
+    for t in tys {
+        depth = depth.max(type_depth(iface, t, stack)?);
+    }
+    Ok(depth)
+}
+
+// Fills in signedness and bit width for integer and float types (looking
+// through type aliases).  Fails for any other type.
+#[no_mangle]
+pub extern "C" fn wit_typedef_int_info_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut WITIntInfo) -> bool {
+    ffi_return!(s, _wit_typedef_int_info_get(td, res))
+}
+fn _wit_typedef_int_info_get(td: *const WITTypeDef, res: *mut WITIntInfo) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    let (is_signed, is_float, bits) = match resolve_alias(&td.iface, &td.ty) {
+        Type::U8 => (false, false, 8),
+        Type::U16 => (false, false, 16),
+        Type::U32 => (false, false, 32),
+        Type::U64 => (false, false, 64),
+        Type::S8 => (true, false, 8),
+        Type::S16 => (true, false, 16),
+        Type::S32 => (true, false, 32),
+        Type::S64 => (true, false, 64),
+        Type::Float32 => (true, true, 32),
+        Type::Float64 => (true, true, 64),
+        _ => return Err(anyhow!("Invalid parameter.  Must be integer or float type!")),
+    };
+    unsafe {
+        *res = WITIntInfo {
+            is_signed,
+            is_float,
+            bits,
+        };
+    }
+    Ok(())
+}
+
+// True for bool, integer, float and char types (looking through aliases),
+// whose values can be copied directly without further marshaling.
+#[no_mangle]
+pub extern "C" fn wit_typedef_is_scalar(s: *mut WITSession, td: *const WITTypeDef, res: *mut bool) -> bool {
+    ffi_return!(s, _wit_typedef_is_scalar(td, res))
+}
+fn _wit_typedef_is_scalar(td: *const WITTypeDef, res: *mut bool) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    let scalar = matches!(
+        resolve_alias(&td.iface, &td.ty),
+        Type::Bool |
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 |
+        Type::S8 | Type::S16 | Type::S32 | Type::S64 |
+        Type::Float32 | Type::Float64 |
+        Type::Char
+    );
+    unsafe {
+        *res = scalar;
+    }
+    Ok(())
+}
+
+// Follows `type x = y` aliases down to the underlying type.
+fn resolve_alias<'a>(iface: &'a Interface, ty: &'a Type) -> &'a Type {
+    if let Type::Id(id) = ty {
+        if let TypeDefKind::Type(t) = &iface.types[*id].kind {
+            return resolve_alias(iface, t);
+        }
+    }
+    ty
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_name_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_typedef_name_get(td, res))
+}
+fn _wit_typedef_name_get(td: *const WITTypeDef, res: *mut *const c_char) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = td.name.as_ptr()
+    }
+    Ok(())
+}
+
+// Like wit_typedef_name_get, but returns the name as it must be spelled in
+// WIT source, i.e. with a leading `%` if it collides with a keyword.
+#[no_mangle]
+pub extern "C" fn wit_typedef_name_raw_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_typedef_name_raw_get(td, res))
+}
+fn _wit_typedef_name_raw_get(td: *const WITTypeDef, res: *mut *const c_char) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = td.raw_name.as_ptr()
+    }
+    Ok(())
+}
+
+// Returns the doc comments of a record field or variant/union case typedef.
+// For any other typedef, returns the docs of its declared type.  Empty if
+// there are none.
+#[no_mangle]
+pub extern "C" fn wit_typedef_docs_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_typedef_docs_get(td, res))
+}
+fn _wit_typedef_docs_get(td: *const WITTypeDef, res: *mut *const c_char) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = td.docs.as_ptr()
+    }
+    Ok(())
+}
+
+// Returns the declared name of the type (e.g. `point` for `record point`,
+// or the resource name for handles).  Anonymous and primitive types yield an
+// empty string.
+#[no_mangle]
+pub extern "C" fn wit_typedef_typename_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_typedef_typename_get(td, res))
+}
+fn _wit_typedef_typename_get(td: *const WITTypeDef, res: *mut *const c_char) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = td.typename.as_ptr()
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_handle_resource_name_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_handle_resource_name_get(td, res))
+}
+fn _wit_handle_resource_name_get(td: *const WITTypeDef, res: *mut *const c_char) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    if let Type::Handle(_) = &td.ty {
+        unsafe {
+            *res = td.typename.as_ptr()
+        }
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid parameter.  Must be 'handle' type!"))
+    }
+}
+
+// True if the type is `unit`, or an alias that resolves to it, e.g. the ok
+// arm of `expected<_, string>`.  Such types have no representation and can
+// be skipped when lowering.
+#[no_mangle]
+pub extern "C" fn wit_typedef_is_unit(s: *mut WITSession, td: *const WITTypeDef, res: *mut bool) -> bool {
+    ffi_return!(s, _wit_typedef_is_unit(td, res))
+}
+fn _wit_typedef_is_unit(td: *const WITTypeDef, res: *mut bool) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = is_unit(&td.iface, &td.ty);
+    }
+    Ok(())
+}
+
+// True if the type transitively contains a string or list, i.e. if values of
+// it own guest allocations that must be cleaned up after a call.
+#[no_mangle]
+pub extern "C" fn wit_typedef_contains_allocations(s: *mut WITSession, td: *const WITTypeDef, res: *mut bool) -> bool {
+    ffi_return!(s, _wit_typedef_contains_allocations(td, res))
+}
+fn _wit_typedef_contains_allocations(td: *const WITTypeDef, res: *mut bool) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = owns_memory(&td.iface, &td.ty);
+    }
+    Ok(())
+}
+
+// True if lowering or lifting the type passes a float through an integer
+// core value, which requires an f32/f64 <-> i32/i64 bitcast.  This happens
+// when a case of a variant, union, option or expected has a float where
+// another case has an integer.
+#[no_mangle]
+pub extern "C" fn wit_typedef_needs_bitcast(s: *mut WITSession, td: *const WITTypeDef, res: *mut bool) -> bool {
+    ffi_return!(s, _wit_typedef_needs_bitcast(td, res))
+}
+fn _wit_typedef_needs_bitcast(td: *const WITTypeDef, res: *mut bool) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = needs_bitcast(&td.iface, &td.ty, td.align.ptr_type());
+    }
+    Ok(())
+}
+
+// Like wit_typedef_needs_bitcast, but true if any of the function's params
+// or results need one.
+#[no_mangle]
+pub extern "C" fn wit_func_needs_bitcast(s: *mut WITSession, func: *const WITFunction, res: *mut bool) -> bool {
+    ffi_return!(s, _wit_func_needs_bitcast(func, res))
+}
+fn _wit_func_needs_bitcast(func: *const WITFunction, res: *mut bool) -> Result<()> {
+    if func.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let func = unsafe {
+        &*func
+    };
+    let ptr = func.align.ptr_type();
+    unsafe {
+        *res = func.params.iter().chain(Some(&func.res))
+            .any(|td| needs_bitcast(&td.iface, &td.ty, ptr));
+    }
+    Ok(())
+}
+
+fn needs_bitcast(iface: &Interface, ty: &Type, ptr: abi::WasmType) -> bool {
+    let id = match ty {
+        Type::Id(id) => id,
+        _ => return false,
+    };
+    let cases: Vec<&Type> = match &iface.types[*id].kind {
+        TypeDefKind::Type(t) => return needs_bitcast(iface, t, ptr),
+        TypeDefKind::Record(r) => return r.fields.iter().any(|f| needs_bitcast(iface, &f.ty, ptr)),
+        TypeDefKind::Tuple(t) => return t.types.iter().any(|t| needs_bitcast(iface, t, ptr)),
+        TypeDefKind::Variant(v) => v.cases.iter().map(|c| &c.ty).collect(),
+        TypeDefKind::Union(u) => u.cases.iter().map(|c| &c.ty).collect(),
+        TypeDefKind::Option(t) => vec![t],
+        TypeDefKind::Expected(e) => vec![&e.ok, &e.err],
+        _ => return false,
+    };
+    let mut joined = Vec::new();
+    flatten(iface, ty, ptr, &mut joined);
+    cases.into_iter().any(|case| {
+        let mut flat = Vec::new();
+        flatten(iface, case, ptr, &mut flat);
+        let float_in_int = flat.iter().zip(&joined[1..]).any(|(c, j)| {
+            matches!(c, abi::WasmType::F32 | abi::WasmType::F64)
+                && matches!(j, abi::WasmType::I32 | abi::WasmType::I64)
+        });
+        float_in_int || needs_bitcast(iface, case, ptr)
+    })
+}
+
+// Returns true if a value of type `ty` refers to separately allocated guest
+// memory, i.e. if it contains a string or list.
+fn owns_memory(iface: &Interface, ty: &Type) -> bool {
+    let id = match ty {
+        Type::String => return true,
+        Type::Id(id) => id,
+        _ => return false,
+    };
+    match &iface.types[*id].kind {
+        TypeDefKind::List(_) => true,
+        TypeDefKind::Flags(_) |
+        TypeDefKind::Enum(_) |
+        TypeDefKind::Future(_) |
+        TypeDefKind::Stream(_) => false,
+        TypeDefKind::Type(t) |
+        TypeDefKind::Option(t) => owns_memory(iface, t),
+        TypeDefKind::Record(r) => r.fields.iter().any(|f| owns_memory(iface, &f.ty)),
+        TypeDefKind::Tuple(t) => t.types.iter().any(|t| owns_memory(iface, t)),
+        TypeDefKind::Variant(v) => v.cases.iter().any(|c| owns_memory(iface, &c.ty)),
+        TypeDefKind::Union(u) => u.cases.iter().any(|c| owns_memory(iface, &c.ty)),
+        TypeDefKind::Expected(e) => owns_memory(iface, &e.ok) || owns_memory(iface, &e.err),
+    }
+}
+
+fn is_unit(iface: &Interface, ty: &Type) -> bool {
+    matches!(resolve_alias(iface, ty), Type::Unit)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_align_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_typedef_align_get(td, res))
+}
+fn _wit_typedef_align_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = td.align.align(&td.ty);
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_size_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_typedef_size_get(td, res))
+}
+fn _wit_typedef_size_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = td.align.size(&td.ty);
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_type_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut WITType) -> bool {
+    ffi_return!(s, _wit_typedef_type_get(td, res))
+}
+fn _wit_typedef_type_get(td: *const WITTypeDef, res: *mut WITType) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    unsafe {
+        *res = wit_type_of(&td.iface, &td.ty);
+    }
+    Ok(())
+}
+
+// Returns the type's canonical ABI "despecialized" kind, looking through
+// type aliases: tuples are records, and enums, options, expecteds and unions
+// are variants.  Other kinds are returned unchanged.  Layout getters such as
+// wit_variant_payload_offset_get accept all of the specialized forms.
+#[no_mangle]
+pub extern "C" fn wit_typedef_despecialize_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut WITType) -> bool {
+    ffi_return!(s, _wit_typedef_despecialize_get(td, res))
+}
+fn _wit_typedef_despecialize_get(td: *const WITTypeDef, res: *mut WITType) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    let ty = match wit_type_of(&td.iface, resolve_alias(&td.iface, &td.ty)) {
+        WITType::Tuple => WITType::Record,
+        WITType::Enum |
+        WITType::Option |
+        WITType::Expected |
+        WITType::Union => WITType::Variant,
+        ty => ty,
+    };
+    unsafe {
+        *res = ty;
+    }
+    Ok(())
+}
+
+fn wit_type_of(iface: &Interface, ty: &Type) -> WITType {
+    match ty {
+        Type::Unit => WITType::Unit,
+        Type::Bool => WITType::Bool,
+        Type::U8 => WITType::U8,
+        Type::U16 => WITType::U16,
+        Type::U32 => WITType::U32,
+        Type::U64 => WITType::U64,
+        Type::S8 => WITType::S8,
+        Type::S16 => WITType::S16,
+        Type::S32 => WITType::S32,
+        Type::S64 => WITType::S64,
+        Type::Float32 => WITType::Float32,
+        Type::Float64 => WITType::Float64,
+        Type::Char => WITType::Char,
+        Type::String => WITType::String,
+        Type::Handle(_) => WITType::Handle,
+        Type::Id(id) => match iface.types[*id].kind {
+            TypeDefKind::Flags(_) => WITType::Flags,
+            TypeDefKind::Expected(_) => WITType::Expected,
+            TypeDefKind::Option(_) => WITType::Option,
+            TypeDefKind::Union(_) => WITType::Union,
+            TypeDefKind::Enum(_) => WITType::Enum,
+            TypeDefKind::Tuple(_) => WITType::Tuple,
+            TypeDefKind::Record(_) => WITType::Record,
+            TypeDefKind::List(_) => WITType::List,
+            TypeDefKind::Variant(_) => WITType::Variant,
+            TypeDefKind::Type(_) => WITType::Type,
+            TypeDefKind::Stream(_) => WITType::Stream,
+            TypeDefKind::Future(_) => WITType::Future,
+        },
+    }
+}
+
+// Returns a 64-bit hash of the type's structure: its kind, declared names and
+// nested types.  The hash is FNV-1a based, so it is stable across re-parses,
+// processes and library builds.
+#[no_mangle]
+pub extern "C" fn wit_typedef_fingerprint_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut u64) -> bool {
+    ffi_return!(s, _wit_typedef_fingerprint_get(td, res))
+}
+fn _wit_typedef_fingerprint_get(td: *const WITTypeDef, res: *mut u64) -> Result<()> {
+    if td.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    let mut hasher = Fnv64::new();
+    fingerprint(&td.iface, &td.ty, &mut hasher, &mut Vec::new());
+    unsafe {
+        *res = hasher.finish();
+    }
+    Ok(())
+}
+
+struct Fnv64(u64);
+impl Fnv64 {
+    fn new() -> Fnv64 {
+        Fnv64(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    // Strings are length-prefixed so that adjacent names can't run together.
+    fn write_str(&mut self, s: &str) {
+        self.write(&(s.len() as u64).to_le_bytes());
+        self.write(s.as_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn fingerprint(iface: &Interface, ty: &Type, h: &mut Fnv64, stack: &mut Vec<TypeId>) {
+    h.write(&[wit_type_of(iface, ty) as u8]);
+    let id = match ty {
+        Type::Handle(rid) => {
+            h.write_str(&iface.resources[*rid].name);
+            return;
+        },
+        Type::Id(id) => id,
+        _ => return,
+    };
+    // A type that refers back to one currently being hashed is recorded by its
+    // position on the stack rather than recursed into.
+    if let Some(pos) = stack.iter().position(|t| t == id) {
+        h.write(&[0xff]);
+        h.write(&(pos as u64).to_le_bytes());
+        return;
+    }
+    stack.push(*id);
+    let def = &iface.types[*id];
+    h.write_str(def.name.as_deref().unwrap_or(""));
+    match &def.kind {
+        TypeDefKind::Record(r) => {
+            h.write(&(r.fields.len() as u64).to_le_bytes());
+            for f in &r.fields {
+                h.write_str(&f.name);
+                fingerprint(iface, &f.ty, h, stack);
+            }
+        },
+        TypeDefKind::Flags(f) => {
+            h.write(&(f.flags.len() as u64).to_le_bytes());
+            for flag in &f.flags {
+                h.write_str(&flag.name);
+            }
+        },
+        TypeDefKind::Tuple(t) => {
+            h.write(&(t.types.len() as u64).to_le_bytes());
+            for ty in &t.types {
+                fingerprint(iface, ty, h, stack);
+            }
+        },
+        TypeDefKind::Variant(v) => {
+            h.write(&(v.cases.len() as u64).to_le_bytes());
+            for c in &v.cases {
+                h.write_str(&c.name);
+                fingerprint(iface, &c.ty, h, stack);
+            }
+        },
+        TypeDefKind::Enum(e) => {
+            h.write(&(e.cases.len() as u64).to_le_bytes());
+            for c in &e.cases {
+                h.write_str(&c.name);
+            }
+        },
+        TypeDefKind::Union(u) => {
+            h.write(&(u.cases.len() as u64).to_le_bytes());
+            for c in &u.cases {
+                fingerprint(iface, &c.ty, h, stack);
+            }
+        },
+        TypeDefKind::Expected(e) => {
+            fingerprint(iface, &e.ok, h, stack);
+            fingerprint(iface, &e.err, h, stack);
+        },
+        TypeDefKind::Stream(st) => {
+            fingerprint(iface, &st.element, h, stack);
+            fingerprint(iface, &st.end, h, stack);
+        },
+        TypeDefKind::Option(ty) |
+        TypeDefKind::List(ty) |
+        TypeDefKind::Future(ty) |
+        TypeDefKind::Type(ty) => {
+            fingerprint(iface, ty, h, stack);
+        },
+    }
+    stack.pop();
+}
+
+// Writes the core wasm types `td` flattens to under the canonical ABI into
+// `out`, up to `cap` entries.  `len` receives the full count, so calling with
+// `cap` set to 0 can be used to size the buffer.
+#[no_mangle]
+pub extern "C" fn wit_typedef_flatten(s: *mut WITSession, td: *const WITTypeDef, out: *mut WASMType, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_typedef_flatten(td, out, cap, len))
+}
+fn _wit_typedef_flatten(td: *const WITTypeDef, out: *mut WASMType, cap: usize, len: *mut usize) -> Result<()> {
+    if td.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    let mut flat = Vec::new();
+    flatten(&td.iface, &td.ty, td.align.ptr_type(), &mut flat);
+    for (i, t) in flat.iter().take(cap).enumerate() {
+        unsafe {
+            *out.add(i) = From::from(*t);
+        }
+    }
+    unsafe {
+        *len = flat.len();
+    }
+    Ok(())
+}
+
+// Writes up to `cap` leaves of the memory layout of `td` into `out`, in
+// order of offset, and sets `len` to the total number of leaves.  Records and
+// tuples are expanded recursively.  Since the cases of a variant overlap, its
+// payload is a single opaque Payload leaf; use the typedefs of the cases to
+// lay out the payload of a particular case.
+#[no_mangle]
+pub extern "C" fn wit_typedef_layout_get(s: *mut WITSession, td: *const WITTypeDef, out: *mut WITLayoutLeaf, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_typedef_layout_get(td, out, cap, len))
+}
+fn _wit_typedef_layout_get(td: *const WITTypeDef, out: *mut WITLayoutLeaf, cap: usize, len: *mut usize) -> Result<()> {
+    if td.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
+    };
+    let mut leaves = Vec::new();
+    layout_leaves(&td.iface, &td.align, &td.ty, 0, &mut leaves);
+    let count = leaves.len();
+    for (i, leaf) in leaves.into_iter().enumerate() {
+        if i >= cap {
+            break;
+        }
+        unsafe {
+            out.add(i).write(leaf);
+        }
+    }
+    unsafe {
+        *len = count;
+    }
+    Ok(())
+}
+
+fn layout_leaves(iface: &Interface, layout: &Layout, ty: &Type, offset: usize, res: &mut Vec<WITLayoutLeaf>) {
+    let ty = resolve_alias(iface, ty);
+    let ptr = layout.ptr_type();
+    let ptr_size = layout.size(ty) / 2;
+    let leaf = |kind, core: abi::WasmType, offset, size| WITLayoutLeaf {
+        kind,
+        ty: wit_type_of(iface, ty),
+        core: core.into(),
+        offset,
+        size,
+    };
+    let id = match ty {
+        Type::Unit => return,
+        Type::String => {
+            res.push(leaf(WITLeafKind::Pointer, ptr, offset, ptr_size));
+            res.push(leaf(WITLeafKind::Length, ptr, offset + ptr_size, ptr_size));
+            return;
+        },
+        Type::Id(id) => id,
+        _ => {
+            let mut flat = Vec::new();
+            flatten(iface, ty, ptr, &mut flat);
+            res.push(leaf(WITLeafKind::Value, flat[0], offset, layout.size(ty)));
+            return;
+        },
+    };
+    match &iface.types[*id].kind {
+        TypeDefKind::List(_) => {
+            res.push(leaf(WITLeafKind::Pointer, ptr, offset, ptr_size));
+            res.push(leaf(WITLeafKind::Length, ptr, offset + ptr_size, ptr_size));
+        },
+        TypeDefKind::Record(r) => {
+            let tys: Vec<&Type> = r.fields.iter().map(|f| &f.ty).collect();
+            for (t, off) in tys.iter().zip(layout.field_offsets(tys.iter().copied())) {
+                layout_leaves(iface, layout, t, offset + off, res);
             }
-            Ok(())
-        } else {
-            Err(anyhow!("Invalid parameter.  Must be record type!"))
-        }
+        },
+        TypeDefKind::Tuple(t) => {
+            for (t, off) in t.types.iter().zip(layout.field_offsets(&t.types)) {
+                layout_leaves(iface, layout, t, offset + off, res);
+            }
+        },
+        TypeDefKind::Flags(_) |
+        TypeDefKind::Enum(_) |
+        TypeDefKind::Future(_) |
+        TypeDefKind::Stream(_) => {
+            res.push(leaf(WITLeafKind::Value, abi::WasmType::I32, offset, layout.size(ty)));
+        },
+        TypeDefKind::Type(_) => unreachable!(),
+        TypeDefKind::Variant(_) |
+        TypeDefKind::Union(_) |
+        TypeDefKind::Option(_) |
+        TypeDefKind::Expected(_) => {
+            if let Some((tag, cases)) = cases_of(iface, ty) {
+                let (core, tag_size) = match tag {
+                    Int::U8 => (abi::WasmType::I32, 1),
+                    Int::U16 => (abi::WasmType::I32, 2),
+                    Int::U32 => (abi::WasmType::I32, 4),
+                    Int::U64 => (abi::WasmType::I64, 8),
+                };
+                let payload_size = cases.iter().map(|t| layout.size(t)).max().unwrap_or(0);
+                res.push(leaf(WITLeafKind::Discriminant, core, offset, tag_size));
+                if payload_size > 0 {
+                    let payload_offset = layout.payload_offset(tag, cases);
+                    res.push(leaf(WITLeafKind::Payload, abi::WasmType::I32, offset + payload_offset, payload_size));
+                }
+            }
+        },
+    }
+}
+
+// Pulls a value of type `td` from `src` and writes it to guest memory at
+// `addr` in its canonical ABI representation.  `addr` must point to
+// wit_typedef_size_get bytes; strings and lists are allocated through
+// `mem.realloc`.  On failure, memory allocated so far is not freed.
+#[no_mangle]
+pub extern "C" fn wit_typedef_lower(s: *mut WITSession, td: *const WITTypeDef, src: *const WITValueSource, mem: *const WITGuestMemory, addr: u64) -> bool {
+    ffi_return!(s, _wit_typedef_lower(td, src, mem, addr))
+}
+fn _wit_typedef_lower(td: *const WITTypeDef, src: *const WITValueSource, mem: *const WITGuestMemory, addr: u64) -> Result<()> {
+    if td.is_null() || src.is_null() || mem.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let (td, src, mem) = unsafe {
+        (&*td, &*src, &*mem)
+    };
+    let lowerer = lower::Lowerer {
+        iface:  &td.iface,
+        layout: &td.align,
+        src,
+        mem,
+    };
+    lowerer.store(&td.ty, addr)
+}
+
+// Like wit_typedef_lower, but lowers all of the function's params, as a
+// record, into the indirect params block at `addr` (see
+// wit_func_param_block_size_get).  The params are bracketed by begin_record,
+// with type Unit, and end_record.
+#[no_mangle]
+pub extern "C" fn wit_func_params_lower(s: *mut WITSession, func: *const WITFunction, src: *const WITValueSource, mem: *const WITGuestMemory, addr: u64) -> bool {
+    ffi_return!(s, _wit_func_params_lower(func, src, mem, addr))
+}
+fn _wit_func_params_lower(func: *const WITFunction, src: *const WITValueSource, mem: *const WITGuestMemory, addr: u64) -> Result<()> {
+    if func.is_null() || src.is_null() || mem.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let (func, src, mem) = unsafe {
+        (&*func, &*src, &*mem)
+    };
+    let lowerer = lower::Lowerer {
+        iface:  &func.iface,
+        layout: &func.align,
+        src,
+        mem,
+    };
+    lowerer.store_record(&Type::Unit, func.params.iter().map(|p| &p.ty).collect(), addr)
+}
+
+// Lifts a value of type `td` from the `count` core values in `values` (as
+// bit patterns, see wit_typedef_flatten for their types), reading strings and
+// lists from guest memory, and delivers it to `sink`.
+#[no_mangle]
+pub extern "C" fn wit_typedef_lift_flat(s: *mut WITSession, td: *const WITTypeDef, values: *const u64, count: usize, mem: *const WITGuestMemory, sink: *const WITValueSink) -> bool {
+    ffi_return!(s, _wit_typedef_lift_flat(td, values, count, mem, sink))
+}
+fn _wit_typedef_lift_flat(td: *const WITTypeDef, values: *const u64, count: usize, mem: *const WITGuestMemory, sink: *const WITValueSink) -> Result<()> {
+    if td.is_null() || (values.is_null() && count > 0) || mem.is_null() || sink.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let (td, mem, sink) = unsafe {
+        (&*td, &*mem, &*sink)
+    };
+    let values = if count > 0 {
+        unsafe { slice::from_raw_parts(values, count) }
     } else {
-        Err(anyhow!("Iterator out of bounds!"))
+        &[]
+    };
+    let lifter = lift::Lifter {
+        iface:  &td.iface,
+        layout: &td.align,
+        mem,
+        sink,
+    };
+    lifter.lift_flat(&td.ty, values, &mut 0)
+}
+
+// Like wit_typedef_lift_flat, but reads the value from guest memory at
+// `addr`.
+#[no_mangle]
+pub extern "C" fn wit_typedef_lift_from_memory(s: *mut WITSession, td: *const WITTypeDef, addr: u64, mem: *const WITGuestMemory, sink: *const WITValueSink) -> bool {
+    ffi_return!(s, _wit_typedef_lift_from_memory(td, addr, mem, sink))
+}
+fn _wit_typedef_lift_from_memory(td: *const WITTypeDef, addr: u64, mem: *const WITGuestMemory, sink: *const WITValueSink) -> Result<()> {
+    if td.is_null() || mem.is_null() || sink.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let (td, mem, sink) = unsafe {
+        (&*td, &*mem, &*sink)
+    };
+    let lifter = lift::Lifter {
+        iface:  &td.iface,
+        layout: &td.align,
+        mem,
+        sink,
+    };
+    lifter.load(&td.ty, addr)
+}
+
+// Lifts the result of a call to the guest export of `func` from the core
+// values it returned, following the return pointer if the signature has one.
+#[no_mangle]
+pub extern "C" fn wit_func_results_lift(s: *mut WITSession, func: *const WITFunction, values: *const u64, count: usize, mem: *const WITGuestMemory, sink: *const WITValueSink) -> bool {
+    ffi_return!(s, _wit_func_results_lift(func, values, count, mem, sink))
+}
+fn _wit_func_results_lift(func: *const WITFunction, values: *const u64, count: usize, mem: *const WITGuestMemory, sink: *const WITValueSink) -> Result<()> {
+    if func.is_null() || (values.is_null() && count > 0) || mem.is_null() || sink.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let (func, mem, sink) = unsafe {
+        (&*func, &*mem, &*sink)
+    };
+    let values = if count > 0 {
+        unsafe { slice::from_raw_parts(values, count) }
+    } else {
+        &[]
+    };
+    let lifter = lift::Lifter {
+        iface:  &func.iface,
+        layout: &func.align,
+        mem,
+        sink,
+    };
+    if func.sig.sig.retptr {
+        let addr = *values.first()
+            .ok_or_else(|| anyhow!("Missing return pointer"))?;
+        lifter.load(&func.res.ty, addr)
+    } else {
+        lifter.lift_flat(&func.res.ty, values, &mut 0)
+    }
+}
+
+// True if `c` is a valid WIT `char`, i.e. a Unicode scalar value: at most
+// 0x10FFFF and not a surrogate.
+#[no_mangle]
+pub extern "C" fn wit_char_is_valid(_s: *mut WITSession, c: u32) -> bool {
+    char::from_u32(c).is_some()
+}
+
+// Encodes the char `c` as UTF-8 into `out`, which must have room for 4
+// bytes, and sets `len` to the number of bytes written.
+#[no_mangle]
+pub extern "C" fn wit_char_to_utf8(s: *mut WITSession, c: u32, out: *mut u8, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_char_to_utf8(c, out, len))
+}
+fn _wit_char_to_utf8(c: u32, out: *mut u8, len: *mut usize) -> Result<()> {
+    if out.is_null() || len.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let c = char::from_u32(c)
+        .ok_or_else(|| anyhow!("Invalid char {:#x}", c))?;
+    let out = unsafe {
+        slice::from_raw_parts_mut(out, 4)
+    };
+    let encoded = c.encode_utf8(out);
+    unsafe {
+        *len = encoded.len();
+    }
+    Ok(())
+}
+
+// Decodes the first char of the UTF-8 text `data`, setting `res` to it and
+// `consumed` to the number of bytes it takes up.
+#[no_mangle]
+pub extern "C" fn wit_char_from_utf8(s: *mut WITSession, data: *const u8, len: usize, res: *mut u32, consumed: *mut usize) -> bool {
+    ffi_return!(s, _wit_char_from_utf8(data, len, res, consumed))
+}
+fn _wit_char_from_utf8(data: *const u8, len: usize, res: *mut u32, consumed: *mut usize) -> Result<()> {
+    if data.is_null() || res.is_null() || consumed.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let data = unsafe {
+        slice::from_raw_parts(data, len)
+    };
+    // A char is at most 4 bytes; don't let invalid bytes after it fail.
+    let head = &data[..len.min(4)];
+    let valid = match str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) => str::from_utf8(&head[..e.valid_up_to()])?,
+    };
+    let c = valid.chars().next()
+        .ok_or_else(|| anyhow!("Invalid or empty UTF-8 text"))?;
+    unsafe {
+        *res = c as u32;
+        *consumed = c.len_utf8();
+    }
+    Ok(())
+}
+
+// Creates an empty buffer for building canonical ABI values, e.g. a record
+// of params, one field at a time: each value is appended at the next offset
+// suitably aligned for its type.
+#[no_mangle]
+pub extern "C" fn wit_buffer_new(s: *mut WITSession, res: *mut *mut WITBuffer) -> bool {
+    ffi_return!(s, _wit_buffer_new(res))
+}
+fn _wit_buffer_new(res: *mut *mut WITBuffer) -> Result<()> {
+    if res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let safe_res = Box::into_raw(Box::new(WITBuffer { data: Vec::new(), align: 1, last: None }));
+    unsafe {
+        *res = safe_res;
+    }
+    Ok(())
+}
+
+// Pads the buffer to the alignment of `td` and appends the value in `data`,
+// which must be exactly wit_typedef_size_get bytes long.
+#[no_mangle]
+pub extern "C" fn wit_buffer_append_typed(s: *mut WITSession, buf: *mut WITBuffer, td: *const WITTypeDef, data: *const u8, len: usize) -> bool {
+    ffi_return!(s, _wit_buffer_append_typed(buf, td, data, len))
+}
+fn _wit_buffer_append_typed(buf: *mut WITBuffer, td: *const WITTypeDef, data: *const u8, len: usize) -> Result<()> {
+    if buf.is_null() || td.is_null() || (data.is_null() && len > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let (buf, td) = unsafe {
+        (&mut *buf, &*td)
+    };
+    let size = td.align.size(&td.ty);
+    if len != size {
+        return Err(anyhow!("Value is {} bytes, but its type is {} bytes", len, size));
+    }
+    let align = td.align.align(&td.ty).max(1);
+    let offset = align_to(buf.data.len(), align);
+    buf.data.resize(offset, 0);
+    if len > 0 {
+        buf.data.extend_from_slice(unsafe { slice::from_raw_parts(data, len) });
+    }
+    buf.align = buf.align.max(align);
+    buf.last = Some(offset);
+    Ok(())
+}
+
+// Returns the offset at which the last value was appended.
+#[no_mangle]
+pub extern "C" fn wit_buffer_offset_of_last(s: *mut WITSession, buf: *const WITBuffer, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_buffer_offset_of_last(buf, res))
+}
+fn _wit_buffer_offset_of_last(buf: *const WITBuffer, res: *mut usize) -> Result<()> {
+    if buf.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let buf = unsafe {
+        &*buf
+    };
+    let last = buf.last.ok_or_else(|| anyhow!("Buffer is empty!"))?;
+    unsafe {
+        *res = last;
+    }
+    Ok(())
+}
+
+// Pads the buffer to the largest alignment of the values in it, as for a
+// record, and returns its contents and alignment.  The contents remain valid
+// until the buffer is appended to or deleted.
+#[no_mangle]
+pub extern "C" fn wit_buffer_finish(s: *mut WITSession, buf: *mut WITBuffer, data: *mut *const u8, len: *mut usize, align: *mut usize) -> bool {
+    ffi_return!(s, _wit_buffer_finish(buf, data, len, align))
+}
+fn _wit_buffer_finish(buf: *mut WITBuffer, data: *mut *const u8, len: *mut usize, align: *mut usize) -> Result<()> {
+    if buf.is_null() || data.is_null() || len.is_null() || align.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let buf = unsafe {
+        &mut *buf
+    };
+    let size = align_to(buf.data.len(), buf.align);
+    buf.data.resize(size, 0);
+    unsafe {
+        *data = buf.data.as_ptr();
+        *len = buf.data.len();
+        *align = buf.align;
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_buffer_delete(_s: *mut WITSession, buf: *mut WITBuffer) {
+    if buf.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(buf));
+    }
+}
+
+// Writes the scalar `bits` of type `ty` into `out` in the little-endian byte
+// order of the canonical ABI, regardless of the host's byte order, and sets
+// `len` to the number of bytes written (at most 8).  Floats are passed as
+// their bit patterns (e.g. from memcpy of a float into a uint32_t).
+#[no_mangle]
+pub extern "C" fn wit_scalar_encode(s: *mut WITSession, ty: WITType, bits: u64, out: *mut u8, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_scalar_encode(ty, bits, out, len))
+}
+fn _wit_scalar_encode(ty: WITType, bits: u64, out: *mut u8, len: *mut usize) -> Result<()> {
+    if out.is_null() || len.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let size = scalar_size(&ty)?;
+    let bytes = bits.to_le_bytes();
+    unsafe {
+        ptr::copy_nonoverlapping(bytes.as_ptr(), out, size);
+        *len = size;
+    }
+    Ok(())
+}
+
+// Reads a scalar of type `ty` from the little-endian bytes in `data`, which
+// must hold at least its size, into `bits`.  Signed integers are
+// sign-extended to 64 bits.
+#[no_mangle]
+pub extern "C" fn wit_scalar_decode(s: *mut WITSession, ty: WITType, data: *const u8, len: usize, bits: *mut u64) -> bool {
+    ffi_return!(s, _wit_scalar_decode(ty, data, len, bits))
+}
+fn _wit_scalar_decode(ty: WITType, data: *const u8, len: usize, bits: *mut u64) -> Result<()> {
+    if data.is_null() || bits.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let size = scalar_size(&ty)?;
+    if len < size {
+        return Err(anyhow!("Need {} bytes, got {}", size, len));
+    }
+    let mut bytes = [0u8; 8];
+    bytes[..size].copy_from_slice(unsafe { slice::from_raw_parts(data, size) });
+    let v = u64::from_le_bytes(bytes);
+    unsafe {
+        *bits = match ty {
+            WITType::S8 => v as i8 as i64 as u64,
+            WITType::S16 => v as i16 as i64 as u64,
+            WITType::S32 => v as i32 as i64 as u64,
+            _ => v,
+        };
+    }
+    Ok(())
+}
+
+fn scalar_size(ty: &WITType) -> Result<usize> {
+    match ty {
+        WITType::Bool |
+        WITType::U8 |
+        WITType::S8 => Ok(1),
+        WITType::U16 |
+        WITType::S16 => Ok(2),
+        WITType::U32 |
+        WITType::S32 |
+        WITType::Float32 |
+        WITType::Char |
+        WITType::Handle => Ok(4),
+        WITType::U64 |
+        WITType::S64 |
+        WITType::Float64 => Ok(8),
+        _ => Err(anyhow!("Invalid parameter.  Must be a scalar type!")),
+    }
+}
+
+// Converts a WIT identifier into a valid C, C++ or SQL identifier.  Writes
+// the result, NUL-terminated, to `out` if it fits in `cap` bytes, and sets
+// `len` to its length without the terminator.  If `len` >= `cap`, call again
+// with a larger buffer.
+#[no_mangle]
+pub extern "C" fn wit_ident_mangle(s: *mut WITSession, name: *const c_char, style: WITIdentStyle, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, "wit_ident_mangle", _wit_ident_convert(name, style, ident::mangle, out, cap, len))
+}
+
+// Reverses wit_ident_mangle, recovering the WIT identifier.  Same buffer
+// protocol as wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_ident_demangle(s: *mut WITSession, name: *const c_char, style: WITIdentStyle, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, "wit_ident_demangle", _wit_ident_convert(name, style, ident::demangle, out, cap, len))
+}
+
+fn _wit_ident_convert(name: *const c_char, style: WITIdentStyle, f: fn(&str, ident::Style) -> String, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if name.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let name = unsafe {
+        CStr::from_ptr(name)
+    };
+    copy_out(&f(name.to_str()?, style.into()), out, cap, len);
+    Ok(())
+}
+
+// Writes `text`, NUL-terminated, to `out` if it fits in `cap` bytes, and sets
+// `len` to its length without the terminator.
+fn copy_out(text: &str, out: *mut c_char, cap: usize, len: *mut usize) {
+    if text.len() < cap {
+        unsafe {
+            ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, out, text.len());
+            *out.add(text.len()) = 0;
+        }
+    }
+    unsafe {
+        *len = text.len();
+    }
+}
+
+// Files generated by wit_emit_c_guest.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub enum WITGuestFile {
+    Header,  // `<name>.h`
+    Source,  // `<name>.c`, includes `<name>.h`
+}
+
+// Generates guest-side C bindings like wit-bindgen's C backend: type
+// declarations, `cabi_realloc`, and an export shim per function calling a
+// `<name>_<func>` the guest implements.  Same buffer protocol as
+// wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_emit_c_guest(s: *mut WITSession, wit: *const WIT, file: WITGuestFile, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_emit_c_guest(wit, file, out, cap, len))
+}
+fn _wit_emit_c_guest(wit: *const WIT, file: WITGuestFile, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let wit = unsafe {
+        &*wit
+    };
+    let ns = ident::mangle(wit.name.to_str()?, ident::Style::C);
+    let text = match file {
+        WITGuestFile::Header => guest::header(wit, &ns),
+        WITGuestFile::Source => guest::source(wit, &ns),
+    };
+    copy_out(&text, out, cap, len);
+    Ok(())
+}
+
+// Options for wit_emit_c_header.  `prefix` is prepended to every emitted
+// identifier; `guard` is the include guard macro, derived from the interface
+// name if null.
+#[repr(C)]
+pub struct WITHeaderOptions {
+    pub prefix: *const c_char,
+    pub guard:  *const c_char,
+}
+
+// Generates a C header with a typedef per type, laid out as in guest memory,
+// and a prototype per function.  `options` may be null.  Same buffer protocol
+// as wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_emit_c_header(s: *mut WITSession, wit: *const WIT, options: *const WITHeaderOptions, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_emit_c_header(wit, options, out, cap, len))
+}
+fn _wit_emit_c_header(wit: *const WIT, options: *const WITHeaderOptions, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let wit = unsafe {
+        &*wit
+    };
+    let (prefix, guard) = match unsafe { options.as_ref() } {
+        Some(o) => (opt_str(o.prefix)?, opt_str(o.guard)?),
+        None => (None, None),
+    };
+    let guard = match guard {
+        Some(g) => g.to_string(),
+        None => {
+            let name: String = wit.name.to_str()?.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect();
+            format!("{}_H", name)
+        },
+    };
+    let header = header::emit(&wit.iface, &wit.align, prefix.unwrap_or(""), &guard);
+    copy_out(&header, out, cap, len);
+    Ok(())
+}
+
+// Dumps the interface's types, resources and functions, with sizes,
+// alignments and core signatures, as a JSON document.  Same buffer protocol
+// as wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_to_json(s: *mut WITSession, wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_to_json(wit, out, cap, len))
+}
+fn _wit_to_json(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let wit = unsafe {
+        &*wit
+    };
+    copy_out(&json::full(wit).render(), out, cap, len);
+    Ok(())
+}
+
+// Like wit_to_json, but with only the computed ABI facts: core signatures,
+// sizes, alignments and indirection.  Stable enough to diff between builds.
+#[no_mangle]
+pub extern "C" fn wit_abi_to_json(s: *mut WITSession, wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_abi_to_json(wit, out, cap, len))
+}
+fn _wit_abi_to_json(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let wit = unsafe {
+        &*wit
+    };
+    copy_out(&json::abi(wit).render(), out, cap, len);
+    Ok(())
+}
+
+// Converts the interface to component-model WIT: a package named `package`
+// (`local:<name>` if null) holding the interface and a world exporting it.
+// Same buffer protocol as wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_to_modern_wit(s: *mut WITSession, wit: *const WIT, package: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_to_modern_wit(wit, package, out, cap, len))
+}
+fn _wit_to_modern_wit(wit: *const WIT, package: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
     }
+    let wit = unsafe {
+        &*wit
+    };
+    let name = wit.name.to_str()?;
+    let package = match opt_str(package)? {
+        Some(p) => p.to_string(),
+        None => format!("local:{}", name),
+    };
+    copy_out(&modern::emit(&wit.iface, &package, name), out, cap, len);
+    Ok(())
+}
+
+// Generates SingleStore `CREATE FUNCTION ... AS WASM` statements for the
+// interface's functions, loading `<module>.wasm` and `<module>.wit`.  Same
+// buffer protocol as wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_emit_create_function(s: *mut WITSession, wit: *const WIT, module: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_emit_create_function(wit, module, out, cap, len))
+}
+fn _wit_emit_create_function(wit: *const WIT, module: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || module.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let wit = unsafe {
+        &*wit
+    };
+    let module = unsafe {
+        CStr::from_ptr(module)
+    };
+    copy_out(&ddl::emit(&wit.iface, module.to_str()?), out, cap, len);
+    Ok(())
+}
+
+// Renders a Markdown reference page for the interface's functions, types
+// and resources, including doc comments.  Same buffer protocol as
+// wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_emit_markdown(s: *mut WITSession, wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_emit_markdown(wit, out, cap, len))
+}
+fn _wit_emit_markdown(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let wit = unsafe {
+        &*wit
+    };
+    copy_out(&markdown::emit(&wit.iface, wit.name.to_str()?), out, cap, len);
+    Ok(())
+}
+
+// Emits a GraphViz DOT graph of the dependencies between functions, named
+// types and resources.  Same buffer protocol as wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_emit_dot(s: *mut WITSession, wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_emit_dot(wit, out, cap, len))
+}
+fn _wit_emit_dot(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let wit = unsafe {
+        &*wit
+    };
+    copy_out(&dot::emit(&wit.iface, wit.name.to_str()?), out, cap, len);
+    Ok(())
 }
 
+// Emits a proto3 schema mirroring the interface's named types, in protobuf
+// package `package` (the interface name if null).  Same buffer protocol as
+// wit_ident_mangle.
 #[no_mangle]
-pub extern "C" fn wit_field_iter_off(_s: *mut WITSession, iter: *const WITFieldIter) -> bool {
-    if iter.is_null() {
-        return true;
+pub extern "C" fn wit_emit_proto(s: *mut WITSession, wit: *const WIT, package: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_emit_proto(wit, package, out, cap, len))
+}
+fn _wit_emit_proto(wit: *const WIT, package: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
     }
-    let iter = unsafe {
-        &*iter
+    let wit = unsafe {
+        &*wit
     };
-    iter.item.is_none()
+    let package = match opt_str(package)? {
+        Some(p) => p,
+        None => wit.name.to_str()?,
+    };
+    copy_out(&proto::emit(&wit.iface, package), out, cap, len);
+    Ok(())
 }
 
+// Generates a Go (cgo) package wrapping this library for the interface,
+// named `package` (derived from the interface name if null).  Same buffer
+// protocol as wit_ident_mangle.
 #[no_mangle]
-pub extern "C" fn wit_field_iter_next(s: *mut WITSession, iter: *mut WITFieldIter) -> bool {
-    ffi_return!(s, _wit_field_iter_next(iter))
+pub extern "C" fn wit_emit_go(s: *mut WITSession, wit: *const WIT, package: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_emit_go(wit, package, out, cap, len))
 }
-fn _wit_field_iter_next(iter: *mut WITFieldIter) -> Result<()> {
-    if iter.is_null() {
+fn _wit_emit_go(wit: *const WIT, package: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
         return Err(anyhow!("Invalid argument"));
     }
-    if wit_field_iter_off(ptr::null_mut(), iter) {
-        return Err(anyhow!("Iterator out of bounds"));
+    let wit = unsafe {
+        &*wit
+    };
+    let name = wit.name.to_str()?;
+    let package = match opt_str(package)? {
+        Some(p) => p.to_string(),
+        None => name.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase(),
+    };
+    copy_out(&golang::emit(&wit.iface, name, &package), out, cap, len);
+    Ok(())
+}
+
+// Emits a JSON Schema describing the JSON form of values of the type.  Same
+// buffer protocol as wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_typedef_to_json_schema(s: *mut WITSession, td: *const WITTypeDef, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_typedef_to_json_schema(td, out, cap, len))
+}
+fn _wit_typedef_to_json_schema(td: *const WITTypeDef, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if td.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
     }
-    let iter = unsafe {
-        &mut *iter
+    let td = unsafe {
+        &*td
     };
-    let next = iter.inner_iter.next();
-    iter.item = {
-        if let Some(next) = next {
-            Some(
-                WITTypeDef{ 
-                    iface: iter.iface.clone(), 
-                    align: iter.align.clone(),
-                    name:  CString::new(next.name.as_str())?,
-                    ty:    next.ty.clone(),
-                    subty1: subtypedef_get_maybe(1, &iter.iface, &iter.align, Some(&next.ty))?,
-                    subty2: subtypedef_get_maybe(2, &iter.iface, &iter.align, Some(&next.ty))?,
-                }
-            )
-        } else {
-            None
-        }
+    copy_out(&schema::document(&td.iface, &td.ty).render(), out, cap, len);
+    Ok(())
+}
+
+// Emits the Avro schema for values of the type, with named types in the
+// interface's namespace.  Same buffer protocol as wit_ident_mangle.
+#[no_mangle]
+pub extern "C" fn wit_typedef_to_avro(s: *mut WITSession, td: *const WITTypeDef, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_typedef_to_avro(td, out, cap, len))
+}
+fn _wit_typedef_to_avro(td: *const WITTypeDef, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if td.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let td = unsafe {
+        &*td
     };
+    copy_out(&avro::document(&td.iface, &td.iface.name, &td.ty).render(), out, cap, len);
     Ok(())
 }
 
+// Emits TypeScript declarations (`.d.ts`) for the interface's types and
+// functions.  Same buffer protocol as wit_ident_mangle.
 #[no_mangle]
-pub extern "C" fn wit_field_iter_at<'a>(s: *mut WITSession, iter: *const WITFieldIter<'a>, res: *mut *const WITTypeDef) -> bool {
-    ffi_return!(s, _wit_field_iter_at(iter, res))
+pub extern "C" fn wit_emit_typescript(s: *mut WITSession, wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_emit_typescript(wit, out, cap, len))
 }
-fn _wit_field_iter_at<'a>(iter: *const WITFieldIter<'a>, res: *mut *const WITTypeDef) -> Result<()> {
-    if iter.is_null() || res.is_null() {
+fn _wit_emit_typescript(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
         return Err(anyhow!("Invalid argument"));
     }
-    let iter = unsafe {
-        &*iter
+    let wit = unsafe {
+        &*wit
     };
-    if let Some(item) = &iter.item {
+    copy_out(&typescript::emit(&wit.iface), out, cap, len);
+    Ok(())
+}
+
+// Compares two interfaces and returns the functions and named types added,
+// removed or changed going from `old` to `newer`.  Named types are compared by
+// name where they are used, so a change to one is reported only once.
+// Delete the result with wit_diff_delete.
+#[no_mangle]
+pub extern "C" fn wit_diff(s: *mut WITSession, old: *const WIT, newer: *const WIT, res: *mut *mut WITDiff) -> bool {
+    ffi_return!(s, _wit_diff(old, newer, res))
+}
+fn _wit_diff(old: *const WIT, newer: *const WIT, res: *mut *mut WITDiff) -> Result<()> {
+    if old.is_null() || newer.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let (old, newer) = unsafe {
+        (&*old, &*newer)
+    };
+    let changes = diff::diff(&old.iface, &newer.iface)
+        .into_iter()
+        .map(|c| Ok(WITChange {
+            kind:   c.kind,
+            item:   c.item,
+            name:   CString::new(c.name)?,
+            detail: CString::new(c.detail)?,
+        }))
+        .collect::<Result<Vec<_>>>()?;
+    let safe_res = Box::into_raw(Box::new(WITDiff { changes }));
+    unsafe {
+        *res = safe_res;
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn wit_diff_count_get(s: *mut WITSession, diff: *const WITDiff, res: *mut usize) -> bool {
+    ffi_return!(s, _wit_diff_count_get(diff, res))
+}
+fn _wit_diff_count_get(diff: *const WITDiff, res: *mut usize) -> Result<()> {
+    if diff.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let diff = unsafe {
+        &*diff
+    };
+    unsafe {
+        *res = diff.changes.len();
+    }
+    Ok(())
+}
+
+// The returned change is owned by the diff.
+#[no_mangle]
+pub extern "C" fn wit_diff_get_by_index(s: *mut WITSession, diff: *const WITDiff, index: usize, res: *mut *const WITChange) -> bool {
+    ffi_return!(s, _wit_diff_get_by_index(diff, index, res))
+}
+fn _wit_diff_get_by_index(diff: *const WITDiff, index: usize, res: *mut *const WITChange) -> Result<()> {
+    if diff.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let diff = unsafe {
+        &*diff
+    };
+    if let Some(change) = diff.changes.get(index) {
         unsafe {
-            *res = item as *const WITTypeDef;
-            Ok(())
+            *res = change as *const WITChange;
         }
+        Ok(())
     } else {
-        Err(anyhow!("Iterator out of bounds!"))
+        Err(anyhow!("Change index {} out of bounds!", index))
     }
 }
 
 #[no_mangle]
-pub extern "C" fn wit_field_iter_delete(_s: *mut WITSession, iter: *mut WITFieldIter) {
-    if !iter.is_null() {
-        unsafe {
-            drop(Box::from_raw(iter));
-        }
+pub extern "C" fn wit_diff_delete(_s: *mut WITSession, diff: *mut WITDiff) {
+    if diff.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(diff));
     }
 }
 
 #[no_mangle]
-pub extern "C" fn wit_variant_tag_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut u8) -> bool {
-    ffi_return!(s, _wit_variant_tag_get(td, res))
+pub extern "C" fn wit_change_kind_get(s: *mut WITSession, change: *const WITChange, res: *mut WITChangeKind) -> bool {
+    ffi_return!(s, _wit_change_kind_get(change, res))
 }
-fn _wit_variant_tag_get(td: *const WITTypeDef, res: *mut u8) -> Result<()> {
-    if td.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"));
+fn _wit_change_kind_get(change: *const WITChange, res: *mut WITChangeKind) -> Result<()> {
+    if change.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let td = unsafe {
-        &*td
+    let change = unsafe {
+        &*change
     };
-    if let Type::Id(id) = &td.ty {
-        if let TypeDefKind::Variant(v) = &td.iface.types[*id].kind {
-            let bits = match v.tag() {
-                Int::U8 => 1,
-                Int::U16 => 2,
-                Int::U32 => 4,
-                Int::U64 => 8,
-            };
-            unsafe {
-                *res = bits;
-            }
-            Ok(())
-        } else {
-            Err(anyhow!("Invalid argument; must be a Variant type"))
-        }
-    } else {
-        Err(anyhow!("Invalid argument; must be a Variant type"))
+    unsafe {
+        *res = change.kind;
     }
+    Ok(())
 }
 
 #[no_mangle]
-pub extern "C" fn wit_variant_case_walk<'a>(s: *mut WITSession, td: *const WITTypeDef, res: *mut *mut WITCaseIter<'a>) -> bool {
-    ffi_return!(s, _wit_variant_case_walk(td, res))
+pub extern "C" fn wit_change_item_get(s: *mut WITSession, change: *const WITChange, res: *mut WITItemKind) -> bool {
+    ffi_return!(s, _wit_change_item_get(change, res))
 }
-fn _wit_variant_case_walk<'a>(td: *const WITTypeDef, res: *mut *mut WITCaseIter<'a>) -> Result<()> {
-    if td.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"));
+fn _wit_change_item_get(change: *const WITChange, res: *mut WITItemKind) -> Result<()> {
+    if change.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let td = unsafe {
-        &*td
+    let change = unsafe {
+        &*change
     };
-    if let Type::Id(id) = &td.ty {
-        if let TypeDefKind::Variant(v) = &td.iface.types[*id].kind {
-            let mut inner_iter = v.cases.iter();
-            let next = inner_iter.next();
-            let item: Option<WITTypeDef> = match next {
-                Some(c) => {
-                    Some(
-                        WITTypeDef{
-                            iface: td.iface.clone(),
-                            align: td.align.clone(),
-                            name:  CString::new(c.name.as_str())?,
-                            ty:    c.ty.clone(),
-                            subty1: subtypedef_get_maybe(1, &td.iface, &td.align, Some(&c.ty))?,
-                            subty2: subtypedef_get_maybe(2, &td.iface, &td.align, Some(&c.ty))?,
-                        }
-                    )
-                },
-                _ => None
-            };
-            let safe_res = 
-                Box::into_raw(
-                    Box::new(
-                        WITCaseIter {
-                            iface:   td.iface.clone(),
-                            align:   td.align.clone(),
-                            inner_iter,
-                            item
-                        }
-                    )
-                );
-            unsafe {
-                *res = safe_res;
-            }
-            Ok(())
-        } else {
-            Err(anyhow!("Invalid argument.  Must be a variant type!"))
-        }
-    } else {
-        Err(anyhow!("Iterator out of bounds!"))
+    unsafe {
+        *res = change.item;
     }
+    Ok(())
 }
+
 #[no_mangle]
-pub extern "C" fn wit_case_iter_off(_s: *mut WITSession, iter: *const WITCaseIter) -> bool {
-    if iter.is_null() {
-        return true;
+pub extern "C" fn wit_change_name_get(s: *mut WITSession, change: *const WITChange, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_change_name_get(change, res))
+}
+fn _wit_change_name_get(change: *const WITChange, res: *mut *const c_char) -> Result<()> {
+    if change.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let iter = unsafe {
-        &*iter
+    let change = unsafe {
+        &*change
     };
-    iter.item.is_none()
+    unsafe {
+        *res = change.name.as_ptr();
+    }
+    Ok(())
 }
 
 #[no_mangle]
-pub extern "C" fn wit_case_iter_next(s: *mut WITSession, iter: *mut WITCaseIter) -> bool {
-    ffi_return!(s, _wit_case_iter_next(iter))
+pub extern "C" fn wit_change_detail_get(s: *mut WITSession, change: *const WITChange, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_change_detail_get(change, res))
 }
-fn _wit_case_iter_next(iter: *mut WITCaseIter) -> Result<()> {
-    if iter.is_null() {
-        return Err(anyhow!("Invalid argument"));
+fn _wit_change_detail_get(change: *const WITChange, res: *mut *const c_char) -> Result<()> {
+    if change.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    if wit_case_iter_off(ptr::null_mut(), iter) {
-        return Err(anyhow!("Iterator out of bounds"));
+    let change = unsafe {
+        &*change
+    };
+    unsafe {
+        *res = change.detail.as_ptr();
     }
-    let iter = unsafe {
-        &mut *iter
+    Ok(())
+}
+
+// Classifies the changes from `old` to `newer` for callers of the functions in
+// `old`: Compatible if nothing they depend on changed (e.g. only parameters
+// were renamed), Additive if functions or types were added or an enum, flags
+// or variant only used as a parameter gained cases without changing its
+// layout, and Breaking otherwise (e.g. a removed parameter or function).
+// Parsing the two with different ABI options is breaking.
+#[no_mangle]
+pub extern "C" fn wit_compat_check(s: *mut WITSession, old: *const WIT, newer: *const WIT, res: *mut WITCompat) -> bool {
+    ffi_return!(s, _wit_compat_check(old, newer, res))
+}
+fn _wit_compat_check(old: *const WIT, newer: *const WIT, res: *mut WITCompat) -> Result<()> {
+    if old.is_null() || newer.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let (old, newer) = unsafe {
+        (&*old, &*newer)
     };
-    let next = iter.inner_iter.next();
-    iter.item = {
-        if let Some(next) = next {
-            Some(
-                WITTypeDef{ 
-                    iface: iter.iface.clone(), 
-                    align: iter.align.clone(),
-                    name:  CString::new(next.name.as_str())?,
-                    ty:    next.ty.clone(),
-                    subty1: subtypedef_get_maybe(1, &iter.iface, &iter.align, Some(&next.ty))?,
-                    subty2: subtypedef_get_maybe(2, &iter.iface, &iter.align, Some(&next.ty))?,
-                }
-            )
-        } else {
-            None
-        }
+    let same_abi = old.string_encoding == newer.string_encoding
+        && old.align.memory64() == newer.align.memory64()
+        && old.max_flat_params == newer.max_flat_params
+        && old.max_flat_results == newer.max_flat_results;
+    let compat = if same_abi {
+        diff::compat(&old.iface, &old.align, &newer.iface, &newer.align)
+    } else {
+        WITCompat::Breaking
     };
+    unsafe {
+        *res = compat;
+    }
     Ok(())
 }
 
+// Returns a new interface with only the `count` functions named in `names`
+// and the types and resources they need, parsed with the same options as
+// `wit`.  Fails if a name isn't a function of `wit`.  Delete the result
+// with wit_delete.
 #[no_mangle]
-pub extern "C" fn wit_case_iter_at<'a>(s: *mut WITSession, iter: *const WITCaseIter<'a>, res: *mut *const WITTypeDef) -> bool {
-    ffi_return!(s, _wit_case_iter_at(iter, res))
+pub extern "C" fn wit_project(s: *mut WITSession, wit: *const WIT, names: *const *const c_char, count: usize, res: *mut *mut WIT) -> bool {
+    ffi_return!(s, _wit_project(wit, names, count, res))
 }
-fn _wit_case_iter_at<'a>(iter: *const WITCaseIter<'a>, res: *mut *const WITTypeDef) -> Result<()> {
-    if iter.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"));
+fn _wit_project(wit: *const WIT, names: *const *const c_char, count: usize, res: *mut *mut WIT) -> Result<()> {
+    if wit.is_null() || res.is_null() || (names.is_null() && count > 0) {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let iter = unsafe {
-        &*iter
+    let wit = unsafe {
+        &*wit
     };
-    if let Some(item) = &iter.item {
-        unsafe {
-            *res = item as *const WITTypeDef;
-            Ok(())
+    let mut selected = Vec::new();
+    for i in 0..count {
+        let name = unsafe {
+            *names.add(i)
+        };
+        if name.is_null() {
+            return Err(anyhow!("Invalid arguments"))
         }
-    } else {
-        Err(anyhow!("Iterator out of bounds!"))
+        selected.push(unsafe { CStr::from_ptr(name) }.to_str()?);
+    }
+    let iface = project::project(&wit.iface, &selected)?;
+    let mut projected = WIT::from_interface(iface, wit.options())?;
+    projected.inherit_exports(wit);
+    let safe_res = Box::into_raw(Box::new(projected));
+    unsafe {
+        *res = safe_res;
     }
+    Ok(())
 }
 
+// Combines `a` and `b` into a new interface named after `a`, e.g. when an
+// API is split across several documents.  Types, resources and functions
+// defined in both must be the same in both.  Fails if they aren't, or if
+// the two were parsed with different options.  Delete the result with
+// wit_delete.
 #[no_mangle]
-pub extern "C" fn wit_case_iter_delete(_s: *mut WITSession, iter: *mut WITCaseIter) {
-    if !iter.is_null() {
-        unsafe {
-            drop(Box::from_raw(iter));
-        }
+pub extern "C" fn wit_merge(s: *mut WITSession, a: *const WIT, b: *const WIT, res: *mut *mut WIT) -> bool {
+    ffi_return!(s, _wit_merge(a, b, res))
+}
+fn _wit_merge(a: *const WIT, b: *const WIT, res: *mut *mut WIT) -> Result<()> {
+    if a.is_null() || b.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
+    }
+    let (a, b) = unsafe {
+        (&*a, &*b)
+    };
+    let (opts, other) = (a.options(), b.options());
+    if opts.string_encoding != other.string_encoding
+        || opts.memory64 != other.memory64
+        || opts.max_flat_params != other.max_flat_params
+        || opts.max_flat_results != other.max_flat_results {
+        return Err(anyhow!("Interfaces {} and {} were parsed with different options", a.iface.name, b.iface.name));
+    }
+    let iface = project::merge(&a.iface, &b.iface)?;
+    let mut merged = WIT::from_interface(iface, opts)?;
+    merged.inherit_exports(b);
+    merged.inherit_exports(a);
+    let safe_res = Box::into_raw(Box::new(merged));
+    unsafe {
+        *res = safe_res;
     }
+    Ok(())
 }
 
+// Emits a text table of the size and alignment of every type of the
+// interface and the offsets of their fields, discriminants and payloads, as
+// laid out in the guest's memory.  Same buffer protocol as wit_ident_mangle.
 #[no_mangle]
-pub extern "C" fn wit_expected_ok_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
-    ffi_return!(s, _wit_expected_typedef_get(true, td, res))
+pub extern "C" fn wit_layout_report(s: *mut WITSession, wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_layout_report(wit, out, cap, len))
+}
+fn _wit_layout_report(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let wit = unsafe {
+        &*wit
+    };
+    copy_out(&report::emit(&wit.iface, &wit.align), out, cap, len);
+    Ok(())
 }
+
+// Walks the interface, calling the callbacks of `walker` for its named
+// types and their members, its resources and its functions and their
+// params, so custom generators needn't re-implement the traversal.  The
+// objects passed to the callbacks are owned by `wit` and may be inspected
+// with the rest of this API.
 #[no_mangle]
-pub extern "C" fn wit_expected_err_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
-    ffi_return!(s, _wit_expected_typedef_get(false, td, res))
+pub extern "C" fn wit_walk(s: *mut WITSession, wit: *const WIT, walker: *const WITWalker) -> bool {
+    ffi_return!(s, _wit_walk(wit, walker))
 }
-fn _wit_expected_typedef_get(get_ok: bool, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> Result<()> {
-    if td.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"))
+fn _wit_walk(wit: *const WIT, walker: *const WITWalker) -> Result<()> {
+    if wit.is_null() || walker.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let td = unsafe { &*td };
-    if let Type::Id(id) = &td.ty {
-        if let TypeDefKind::Expected(_) = &td.iface.types[*id].kind {
-            let subty_opt = {
-                if get_ok {
-                    &td.subty1
+    let (wit, w) = unsafe {
+        (&*wit, &*walker)
+    };
+    let ud = w.user_data;
+    for (td, def) in wit.types.iter().zip(wit.iface.types.iter().map(|(_, def)| def)) {
+        if def.name.is_none() {
+            continue;
+        }
+        let p = td as *const WITTypeDef;
+        match &def.kind {
+            TypeDefKind::Record(_) => {
+                walk_call!(w.on_record, ud, p);
+                for (i, field) in td.children()?.iter().enumerate() {
+                    walk_call!(w.on_field, ud, p, i, field as *const WITTypeDef);
+                }
+            },
+            TypeDefKind::Variant(_) |
+            TypeDefKind::Union(_) => {
+                walk_call!(w.on_variant, ud, p);
+                for (i, case) in td.children()?.iter().enumerate() {
+                    walk_call!(w.on_case, ud, p, i, case as *const WITTypeDef);
+                }
+            },
+            TypeDefKind::Enum(_) |
+            TypeDefKind::Flags(_) => {
+                if let TypeDefKind::Enum(_) = &def.kind {
+                    walk_call!(w.on_enum, ud, p);
                 } else {
-                    &td.subty2
+                    walk_call!(w.on_flags, ud, p);
                 }
-            };
-            match subty_opt {
-                Some(subty) => {
-                    unsafe {
-                        *res = &**subty as *const WITTypeDef;
-                    }
-                    Ok(())
-                },
-                _ => {
-                    Err(anyhow!("Could not determine array element type!"))
+                for (i, name) in td.names.iter().enumerate() {
+                    walk_call!(w.on_name, ud, p, i, name.as_ptr());
                 }
-            }
-        } else {
-            Err(anyhow!("Invalid parameter.  Must be list type!"))
+            },
+            _ => walk_call!(w.on_alias, ud, p),
+        }
+        walk_call!(w.on_type_end, ud, p);
+    }
+    for r in &wit.resources {
+        walk_call!(w.on_resource, ud, r as *const WITResource);
+    }
+    for f in &wit.iface.functions {
+        let func = &wit.funcs[&f.name];
+        let p = func as *const WITFunction;
+        walk_call!(w.on_function, ud, p);
+        for (i, param) in func.params.iter().enumerate() {
+            walk_call!(w.on_param, ud, p, i, param as *const WITTypeDef);
         }
-    } else {
-        Err(anyhow!("Invalid parameter.  Must be 'expected' type!"))
     }
+    Ok(())
 }
 
+// Serializes the interface, the options it was parsed with and its computed
+// layout and signatures into a compact binary snapshot, to be loaded with
+// wit_snapshot_load instead of parsing again.  Copies the snapshot to `out` if it fits in
+// `cap` bytes, and always sets `len` to its size; if `len` > `cap`, call
+// again with a larger buffer.  Modules pulled in with `use` are
+// not kept, but the types imported from them are.
 #[no_mangle]
-pub extern "C" fn wit_option_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
-    ffi_return!(s, _wit_option_typedef_get(td, res))
+pub extern "C" fn wit_snapshot_save(s: *mut WITSession, wit: *const WIT, out: *mut u8, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_snapshot_save(wit, out, cap, len))
 }
-fn _wit_option_typedef_get(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> Result<()> {
-    if td.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"))
+fn _wit_snapshot_save(wit: *const WIT, out: *mut u8, cap: usize, len: *mut usize) -> Result<()> {
+    if wit.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
     }
-    let td = unsafe { &*td };
-    if let Type::Id(id) = &td.ty {
-        if let TypeDefKind::Option(_) = &td.iface.types[*id].kind {
-            // Return cached subtype, if it exists.
-            match &td.subty1 {
-                Some(subty) => {
-                    unsafe {
-                        *res = &**subty as *const WITTypeDef;
-                    }
-                    Ok(())
-                },
-                _ => {
-                    Err(anyhow!("Could not determine option type!"))
-                }
-            }
-        } else {
-            Err(anyhow!("Invalid parameter.  Must be 'option' type!"))
+    let wit = unsafe {
+        &*wit
+    };
+    let snapshot = snapshot::save(wit);
+    if snapshot.len() <= cap {
+        unsafe {
+            ptr::copy_nonoverlapping(snapshot.as_ptr(), out, snapshot.len());
         }
-    } else {
-        Err(anyhow!("Invalid parameter.  Must be 'option' type!"))
     }
+    unsafe {
+        *len = snapshot.len();
+    }
+    Ok(())
 }
 
+// Loads a snapshot saved with wit_snapshot_save, with the options it was
+// saved with rather than the session's.  Much faster than parsing, as the
+// layout and signatures are taken from the snapshot, which is checked to fit
+// its types.  Names and docs are copied, so `data` needn't outlive the call.
+// Delete the result with wit_delete.
 #[no_mangle]
-pub extern "C" fn wit_type_aliased_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
-    ffi_return!(s, _wit_type_aliased_typedef_get(td, res))
+pub extern "C" fn wit_snapshot_load(s: *mut WITSession, data: *const u8, len: usize, res: *mut *mut WIT) -> bool {
+    ffi_return!(s, _wit_snapshot_load(data, len, res))
 }
-fn _wit_type_aliased_typedef_get(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> Result<()> {
-    if td.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"));
+fn _wit_snapshot_load(data: *const u8, len: usize, res: *mut *mut WIT) -> Result<()> {
+    if data.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let td = unsafe {
-        &*td
+    let data = unsafe {
+        slice::from_raw_parts(data, len)
     };
-    if let Type::Id(id) = &td.ty {
-        if let TypeDefKind::Type(_) = &td.iface.types[*id].kind {
-            // Return cached subtype, if it exists.
-            match &td.subty1 {
-                Some(subty) => {
-                    unsafe {
-                        *res = &**subty as *const WITTypeDef;
-                    }
-                    Ok(())
-                },
-                _ => {
-                    Err(anyhow!("Could not determine aliased type!"))
-                }
-            }
-        } else {
-            Err(anyhow!("Invalid parameter.  Must be 'type' type!"))
-        }
-    } else {
-        Err(anyhow!("Invalid parameter.  Must be 'type' type!"))
+    let safe_res = Box::into_raw(Box::new(snapshot::load(data)?));
+    unsafe {
+        *res = safe_res;
     }
+    Ok(())
 }
 
+// Like wit_snapshot_load, but reads the snapshot from the file at `path`.
 #[no_mangle]
-pub extern "C" fn wit_list_elem_typedef_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
-    ffi_return!(s, _wit_list_elem_typedef_get(td, res))
+pub extern "C" fn wit_snapshot_load_file(s: *mut WITSession, path: *const c_char, res: *mut *mut WIT) -> bool {
+    ffi_return!(s, _wit_snapshot_load_file(path, res))
 }
-fn _wit_list_elem_typedef_get(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> Result<()> {
-    if td.is_null() || res.is_null() {
-        return Err(anyhow!("Invalid argument"));
+fn _wit_snapshot_load_file(path: *const c_char, res: *mut *mut WIT) -> Result<()> {
+    if path.is_null() || res.is_null() {
+        return Err(anyhow!("Invalid arguments"))
     }
-    let td = unsafe {
-        &*td
+    let path = unsafe {
+        CStr::from_ptr(path)
     };
-    if let Type::Id(id) = &td.ty {
-        if let TypeDefKind::List(_) = &td.iface.types[*id].kind {
-            // Return cached subtype, if it exists.
-            match &td.subty1 {
-                Some(subty) => {
-                    unsafe {
-                        *res = &**subty as *const WITTypeDef;
-                    }
-                    Ok(())
-                },
-                _ => {
-                    Err(anyhow!("Could not determine array element type!"))
+    let safe_res = Box::into_raw(Box::new(snapshot::load(&fs::read(path.to_str()?)?)?));
+    unsafe {
+        *res = safe_res;
+    }
+    Ok(())
+}
+
+fn opt_str<'a>(p: *const c_char) -> Result<Option<&'a str>> {
+    if p.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(unsafe { CStr::from_ptr(p) }.to_str()?))
+}
+
+// Like Interface::wasm_signature, which assumes 32-bit pointers and the
+// default flattening limits, but honoring the parse options.
+fn wasm_signature(iface: &Interface, layout: &Layout, opts: &ParseOptions, variant: abi::AbiVariant, func: &parser::Function) -> abi::WasmSignature {
+    if !layout.memory64() && opts.max_flat_params == MAX_FLAT_PARAMS && opts.max_flat_results == MAX_FLAT_RESULTS {
+        return iface.wasm_signature(variant, func);
+    }
+    let ptr = layout.ptr_type();
+    let mut params = Vec::new();
+    for (_, ty) in &func.params {
+        flatten(iface, ty, ptr, &mut params);
+    }
+    let indirect_params = params.len() > opts.max_flat_params;
+    if indirect_params {
+        params = vec![ptr];
+    }
+    let mut results = Vec::new();
+    flatten(iface, &func.result, ptr, &mut results);
+    let retptr = results.len() > opts.max_flat_results;
+    if retptr {
+        results.clear();
+        match variant {
+            abi::AbiVariant::GuestImport => params.push(ptr),
+            abi::AbiVariant::GuestExport => results.push(ptr),
+        }
+    }
+    abi::WasmSignature {
+        params,
+        results,
+        indirect_params,
+        retptr,
+    }
+}
+
+// Canonical ABI flattening of a type into core wasm types.  Pointers and
+// lengths flatten to `ptr`.
+fn flatten(iface: &Interface, ty: &Type, ptr: abi::WasmType, res: &mut Vec<abi::WasmType>) {
+    match ty {
+        Type::Unit => {},
+        Type::Bool |
+        Type::U8 |
+        Type::U16 |
+        Type::U32 |
+        Type::S8 |
+        Type::S16 |
+        Type::S32 |
+        Type::Char |
+        Type::Handle(_) => res.push(abi::WasmType::I32),
+        Type::U64 |
+        Type::S64 => res.push(abi::WasmType::I64),
+        Type::Float32 => res.push(abi::WasmType::F32),
+        Type::Float64 => res.push(abi::WasmType::F64),
+        Type::String => {
+            res.push(ptr);
+            res.push(ptr);
+        },
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Type(t) => flatten(iface, t, ptr, res),
+            TypeDefKind::Record(r) => {
+                for f in &r.fields {
+                    flatten(iface, &f.ty, ptr, res);
                 }
+            },
+            TypeDefKind::Tuple(t) => {
+                for ty in &t.types {
+                    flatten(iface, ty, ptr, res);
+                }
+            },
+            TypeDefKind::Flags(f) => {
+                for _ in 0..f.repr().count() {
+                    res.push(abi::WasmType::I32);
+                }
+            },
+            TypeDefKind::List(_) => {
+                res.push(ptr);
+                res.push(ptr);
+            },
+            TypeDefKind::Variant(v) => {
+                flatten_variant(iface, v.tag(), v.cases.iter().map(|c| &c.ty), ptr, res)
+            },
+            TypeDefKind::Enum(e) => {
+                flatten_variant(iface, e.tag(), None, ptr, res)
+            },
+            TypeDefKind::Option(t) => {
+                flatten_variant(iface, Int::U8, Some(t), ptr, res)
+            },
+            TypeDefKind::Expected(e) => {
+                flatten_variant(iface, Int::U8, [&e.ok, &e.err], ptr, res)
+            },
+            TypeDefKind::Union(u) => {
+                flatten_variant(iface, u.tag(), u.cases.iter().map(|c| &c.ty), ptr, res)
+            },
+            TypeDefKind::Future(_) |
+            TypeDefKind::Stream(_) => res.push(abi::WasmType::I32),
+        },
+    }
+}
+
+// A variant flattens to its tag followed by the element-wise join of its
+// cases' flattened payloads.
+fn flatten_variant<'a>(iface: &Interface, tag: Int, cases: impl IntoIterator<Item = &'a Type>, ptr: abi::WasmType, res: &mut Vec<abi::WasmType>) {
+    res.push(match tag {
+        Int::U8 | Int::U16 | Int::U32 => abi::WasmType::I32,
+        Int::U64 => abi::WasmType::I64,
+    });
+    let start = res.len();
+    let mut temp = Vec::new();
+    for ty in cases {
+        flatten(iface, ty, ptr, &mut temp);
+        for (i, t) in temp.drain(..).enumerate() {
+            match res.get_mut(start + i) {
+                Some(prev) => *prev = join(*prev, t),
+                None => res.push(t),
             }
-        } else {
-            Err(anyhow!("Invalid parameter.  Must be list type!"))
         }
-    } else {
-        Err(anyhow!("Invalid parameter.  Must be list type!"))
+    }
+}
+
+fn join(a: abi::WasmType, b: abi::WasmType) -> abi::WasmType {
+    use abi::WasmType::*;
+    match (a, b) {
+        (I32, I32) | (I64, I64) | (F32, F32) | (F64, F64) => a,
+        (I32, F32) | (F32, I32) => I32,
+        (_, I64) | (_, F64) | (I64, _) | (F64, _) => I64,
     }
 }
 
 #[no_mangle]
-pub extern "C" fn wit_typedef_name_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut *const c_char) -> bool {
-    ffi_return!(s, _wit_typedef_name_get(td, res))
+pub extern "C" fn wit_func_sig_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const WITSignature) -> bool {
+    ffi_return!(s, _wit_func_sig_get(func, res))
 }
-fn _wit_typedef_name_get(td: *const WITTypeDef, res: *mut *const c_char) -> Result<()> {
-    if td.is_null() || res.is_null() {
+fn _wit_func_sig_get(func: *const WITFunction, res: *mut *const WITSignature) -> Result<()> {
+    if func.is_null() || res.is_null() {
         return Err(anyhow!("Invalid argument"));
     }
-    let td = unsafe {
-        &*td
+    let func  = unsafe {
+        &*func
     };
     unsafe {
-        *res = td.name.as_ptr()
+        *res = &func.sig as *const WITSignature; 
     }
     Ok(())
 }
 
+// Like wit_func_sig_get, but for the given ABI variant.  Use GuestImport when
+// implementing a function the guest imports from the host.
 #[no_mangle]
-pub extern "C" fn wit_typedef_align_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
-    ffi_return!(s, _wit_typedef_align_get(td, res))
+pub extern "C" fn wit_func_sig_get_by_variant(s: *mut WITSession, func: *const WITFunction, variant: WITAbiVariant, res: *mut *const WITSignature) -> bool {
+    ffi_return!(s, _wit_func_sig_get_by_variant(func, variant, res))
 }
-fn _wit_typedef_align_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
-    if td.is_null() || res.is_null() {
+fn _wit_func_sig_get_by_variant(func: *const WITFunction, variant: WITAbiVariant, res: *mut *const WITSignature) -> Result<()> {
+    if func.is_null() || res.is_null() {
         return Err(anyhow!("Invalid argument"));
     }
-    let td = unsafe {
-        &*td
+    let func  = unsafe {
+        &*func
+    };
+    let sig = match variant {
+        WITAbiVariant::GuestImport => &func.import_sig,
+        WITAbiVariant::GuestExport => &func.sig,
     };
     unsafe {
-        *res = td.align.align(&td.ty);
+        *res = sig as *const WITSignature;
     }
     Ok(())
 }
 
+// Returns the function's core signature (see wit_func_sig_get) rendered as
+// text, e.g. `(i32, i32, f64) -> i32`, for logging and error messages.
 #[no_mangle]
-pub extern "C" fn wit_typedef_size_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut usize) -> bool {
-    ffi_return!(s, _wit_typedef_size_get(td, res))
+pub extern "C" fn wit_func_core_sig_string_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_func_core_sig_string_get(func, res))
 }
-fn _wit_typedef_size_get(td: *const WITTypeDef, res: *mut usize) -> Result<()> {
-    if td.is_null() || res.is_null() {
+fn _wit_func_core_sig_string_get(func: *const WITFunction, res: *mut *const c_char) -> Result<()> {
+    if func.is_null() || res.is_null() {
         return Err(anyhow!("Invalid argument"));
     }
-    let td = unsafe {
-        &*td
+    let func  = unsafe {
+        &*func
     };
     unsafe {
-        *res = td.align.size(&td.ty);
+        *res = func.sig.text.as_ptr();
     }
     Ok(())
 }
 
+// Like wit_func_core_sig_string_get, but for any signature, e.g. one
+// returned by wit_func_sig_get_by_variant.
 #[no_mangle]
-pub extern "C" fn wit_typedef_type_get(s: *mut WITSession, td: *const WITTypeDef, res: *mut WITType) -> bool {
-    ffi_return!(s, _wit_typedef_type_get(td, res))
+pub extern "C" fn wit_sig_string_get(s: *mut WITSession, sig: *const WITSignature, res: *mut *const c_char) -> bool {
+    ffi_return!(s, _wit_sig_string_get(sig, res))
 }
-fn _wit_typedef_type_get(td: *const WITTypeDef, res: *mut WITType) -> Result<()> {
-    if td.is_null() || res.is_null() {
+fn _wit_sig_string_get(sig: *const WITSignature, res: *mut *const c_char) -> Result<()> {
+    if sig.is_null() || res.is_null() {
         return Err(anyhow!("Invalid argument"));
     }
-    let td = unsafe {
-        &*td
-    };
-    let ty = match &td.ty {
-        Type::Unit => WITType::Unit,
-        Type::Bool => WITType::Bool,
-        Type::U8 => WITType::U8,
-        Type::U16 => WITType::U16,
-        Type::U32 => WITType::U32,
-        Type::U64 => WITType::U64,
-        Type::S8 => WITType::S8,
-        Type::S16 => WITType::S16,
-        Type::S32 => WITType::S32,
-        Type::S64 => WITType::S64,
-        Type::Float32 => WITType::Float32,
-        Type::Float64 => WITType::Float64,
-        Type::Char => WITType::Char,
-        Type::String => WITType::String,
-        Type::Handle(_) => WITType::Handle,
-        Type::Id(id) => match td.iface.types[*id].kind {
-            TypeDefKind::Flags(_) => WITType::Flags,
-            TypeDefKind::Expected(_) => WITType::Expected,
-            TypeDefKind::Option(_) => WITType::Option,
-            TypeDefKind::Union(_) => WITType::Union,
-            TypeDefKind::Enum(_) => WITType::Enum,
-            TypeDefKind::Tuple(_) => WITType::Tuple,
-            TypeDefKind::Record(_) => WITType::Record,
-            TypeDefKind::List(_) => WITType::List,
-            TypeDefKind::Variant(_) => WITType::Variant,
-            TypeDefKind::Type(_) => WITType::Type,
-            TypeDefKind::Stream(_) => WITType::Stream,
-            TypeDefKind::Future(_) => WITType::Future,
-        },
+    let sig  = unsafe {
+        &*sig
     };
     unsafe {
-        *res = ty;
+        *res = sig.text.as_ptr();
     }
     Ok(())
 }
 
+// Checks a core function type observed by the host, e.g. the type of a guest
+// export, against the function's signature for the given ABI variant.
+// Fails with an error describing the first mismatch if they differ.
 #[no_mangle]
-pub extern "C" fn wit_func_sig_get(s: *mut WITSession, func: *const WITFunction, res: *mut *const WITSignature) -> bool {
-    ffi_return!(s, _wit_func_sig_get(func, res))
+pub extern "C" fn wit_func_core_sig_check(s: *mut WITSession, func: *const WITFunction, variant: WITAbiVariant, params: *const WASMType, nparams: usize, results: *const WASMType, nresults: usize) -> bool {
+    ffi_return!(s, _wit_func_core_sig_check(func, variant, params, nparams, results, nresults))
 }
-fn _wit_func_sig_get(func: *const WITFunction, res: *mut *const WITSignature) -> Result<()> {
-    if func.is_null() || res.is_null() {
+fn _wit_func_core_sig_check(func: *const WITFunction, variant: WITAbiVariant, params: *const WASMType, nparams: usize, results: *const WASMType, nresults: usize) -> Result<()> {
+    if func.is_null() || (params.is_null() && nparams > 0) || (results.is_null() && nresults > 0) {
         return Err(anyhow!("Invalid argument"));
     }
     let func  = unsafe {
         &*func
     };
-    unsafe {
-        *res = &func.sig as *const WITSignature; 
+    let (params, results) = unsafe {
+        (
+            if nparams > 0 { slice::from_raw_parts(params, nparams) } else { &[] },
+            if nresults > 0 { slice::from_raw_parts(results, nresults) } else { &[] },
+        )
+    };
+    let sig = match variant {
+        WITAbiVariant::GuestImport => &func.import_sig.sig,
+        WITAbiVariant::GuestExport => &func.sig.sig,
+    };
+    let name = func.name.to_str()?;
+    sig_part_check(name, "param", &sig.params, params)?;
+    sig_part_check(name, "result", &sig.results, results)
+}
+
+fn sig_part_check(name: &str, part: &str, expected: &[abi::WasmType], actual: &[WASMType]) -> Result<()> {
+    if expected.len() != actual.len() {
+        return Err(anyhow!(
+            "Function `{}` expects {} core {}s, found {}",
+            name, expected.len(), part, actual.len()
+        ));
+    }
+    for (i, (e, a)) in expected.iter().zip(actual).enumerate() {
+        if WASMType::from(*e) != *a {
+            return Err(anyhow!(
+                "Function `{}` expects core {} {} to be {}, found {}",
+                name, part, i, wasm_type_name(WASMType::from(*e)), wasm_type_name(*a)
+            ));
+        }
     }
     Ok(())
 }
 
+fn wasm_type_name(t: WASMType) -> &'static str {
+    match t {
+        WASMType::I32 => "i32",
+        WASMType::I64 => "i64",
+        WASMType::F32 => "f32",
+        WASMType::F64 => "f64",
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn wit_sig_is_indirect(s: *mut WITSession, sig: *const WITSignature, part: WITSigPart, res: *mut bool) -> bool {
     ffi_return!(s, _wit_sig_is_indirect(sig, part, res))
@@ -1172,6 +5770,27 @@ fn _wit_sig_length_get(sig: *const WITSignature, part: WITSigPart, res: *mut usi
     Ok(())
 }
 
+// Sets `present` to true if the signature ends with a return pointer param,
+// which is the case for imports (see wit_func_sig_get_by_variant) returning
+// results indirectly, and `index` to its position among the params.
+#[no_mangle]
+pub extern "C" fn wit_sig_retptr_param_get(s: *mut WITSession, sig: *const WITSignature, present: *mut bool, index: *mut usize) -> bool {
+    ffi_return!(s, _wit_sig_retptr_param_get(sig, present, index))
+}
+fn _wit_sig_retptr_param_get(sig: *const WITSignature, present: *mut bool, index: *mut usize) -> Result<()> {
+    if sig.is_null() || present.is_null() || index.is_null() {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let sig  = unsafe {
+        &*sig
+    };
+    unsafe {
+        *present = sig.retptr_param;
+        *index = if sig.retptr_param { sig.sig.params.len() - 1 } else { 0 };
+    }
+    Ok(())
+}
+
 #[no_mangle]
 pub extern "C" fn wit_sig_type_get_by_index(s: *mut WITSession, sig: *const WITSignature, part: WITSigPart, idx: usize, res: *mut WASMType) -> bool {
     ffi_return!(s, _wit_sig_type_get_by_index(sig, part, idx, res))
@@ -1194,3 +5813,33 @@ fn _wit_sig_type_get_by_index(sig: *const WITSignature, part: WITSigPart, idx: u
     Ok(())
 }
 
+// Copies up to `cap` types of the given part of the signature into `out` and
+// sets `len` to the total number of types in that part.  If `len` > `cap`,
+// call again with a larger buffer.
+#[no_mangle]
+pub extern "C" fn wit_sig_types_get(s: *mut WITSession, sig: *const WITSignature, part: WITSigPart, out: *mut WASMType, cap: usize, len: *mut usize) -> bool {
+    ffi_return!(s, _wit_sig_types_get(sig, part, out, cap, len))
+}
+fn _wit_sig_types_get(sig: *const WITSignature, part: WITSigPart, out: *mut WASMType, cap: usize, len: *mut usize) -> Result<()> {
+    if sig.is_null() || len.is_null() || (out.is_null() && cap > 0) {
+        return Err(anyhow!("Invalid argument"));
+    }
+    let sig  = unsafe {
+        &*sig
+    };
+    let v = 
+        match part {
+            WITSigPart::Params => &sig.sig.params,
+            WITSigPart::Results => &sig.sig.results,
+        };
+    for (i, t) in v.iter().take(cap).enumerate() {
+        unsafe {
+            *out.add(i) = From::from(*t);
+        }
+    }
+    unsafe {
+        *len = v.len();
+    }
+    Ok(())
+}
+
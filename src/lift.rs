@@ -0,0 +1,325 @@
+use anyhow::{anyhow, Result};
+use parser::{Int, Interface, Type, TypeDefKind};
+use crate::layout::Layout;
+use crate::lower::UTF16_TAG;
+use crate::{WITGuestMemory, WITStringEncoding, WITValueSink};
+
+// Reads values from core values or guest memory using the canonical ABI and
+// delivers them to a host value sink.
+pub(crate) struct Lifter<'a> {
+    pub(crate) iface:  &'a Interface,
+    pub(crate) layout: &'a Layout,
+    pub(crate) mem:    &'a WITGuestMemory,
+    pub(crate) sink:   &'a WITValueSink,
+}
+
+impl<'a> Lifter<'a> {
+    // Lifts a value of type `ty` from the core values starting at `*index`,
+    // advancing `*index` past them.  Core values are passed as their bit
+    // patterns, with 32-bit values zero-extended.
+    pub(crate) fn lift_flat(&self, ty: &Type, values: &[u64], index: &mut usize) -> Result<()> {
+        let ty = crate::resolve_alias(self.iface, ty);
+        let start = *index;
+        match ty {
+            Type::Unit => Ok(()),
+            Type::Float32 => {
+                let v = f32::from_bits(next(values, index)? as u32);
+                self.float(ty, v as f64)
+            },
+            Type::Float64 => {
+                let v = f64::from_bits(next(values, index)?);
+                self.float(ty, v)
+            },
+            Type::String => {
+                let (ptr, len) = (next(values, index)?, next(values, index)?);
+                self.string(ptr, len as usize)
+            },
+            Type::Id(id) => match &self.iface.types[*id].kind {
+                TypeDefKind::Type(_) => unreachable!(),
+                TypeDefKind::Record(r) => {
+                    self.record(ty, r.fields.len(), |l| {
+                        r.fields.iter().try_for_each(|f| l.lift_flat(&f.ty, values, index))
+                    })
+                },
+                TypeDefKind::Tuple(t) => {
+                    self.record(ty, t.types.len(), |l| {
+                        t.types.iter().try_for_each(|t| l.lift_flat(t, values, index))
+                    })
+                },
+                TypeDefKind::List(elem) => {
+                    let (ptr, len) = (next(values, index)?, next(values, index)?);
+                    self.list(elem, ptr, len as usize)
+                },
+                TypeDefKind::Flags(f) => {
+                    let words = (0..f.repr().count())
+                        .map(|_| next(values, index).map(|w| w as u32))
+                        .collect::<Result<Vec<u32>>>()?;
+                    self.flags(&words)
+                },
+                TypeDefKind::Enum(e) => {
+                    let discr = next(values, index)?;
+                    self.case(ty, discr, e.cases.len())
+                },
+                TypeDefKind::Variant(_) |
+                TypeDefKind::Union(_) |
+                TypeDefKind::Option(_) |
+                TypeDefKind::Expected(_) => {
+                    let (_, cases) = crate::cases_of(self.iface, ty)
+                        .ok_or_else(|| anyhow!("Expected a variant type"))?;
+                    let discr = next(values, index)?;
+                    self.case(ty, discr, cases.len())?;
+                    self.lift_flat(cases[discr as usize], values, index)?;
+                    // Skip any values the other cases need but this one doesn't.
+                    let mut flat = Vec::new();
+                    crate::flatten(self.iface, ty, self.layout.ptr_type(), &mut flat);
+                    *index = start + flat.len();
+                    Ok(())
+                },
+                TypeDefKind::Future(_) |
+                TypeDefKind::Stream(_) => {
+                    let v = next(values, index)?;
+                    self.int(ty, v)
+                },
+            },
+            _ => {
+                let v = next(values, index)?;
+                self.int(ty, sign_extend(ty, v))
+            },
+        }
+    }
+
+    // Lifts a value of type `ty` stored in guest memory at `addr`.
+    pub(crate) fn load(&self, ty: &Type, addr: u64) -> Result<()> {
+        let ty = crate::resolve_alias(self.iface, ty);
+        match ty {
+            Type::Unit => Ok(()),
+            Type::Float32 => {
+                let v = f32::from_bits(self.read_uint(addr, 4)? as u32);
+                self.float(ty, v as f64)
+            },
+            Type::Float64 => {
+                let v = f64::from_bits(self.read_uint(addr, 8)?);
+                self.float(ty, v)
+            },
+            Type::String => {
+                let (ptr, len) = self.read_ptr_len(addr)?;
+                self.string(ptr, len)
+            },
+            Type::Id(id) => match &self.iface.types[*id].kind {
+                TypeDefKind::Type(_) => unreachable!(),
+                TypeDefKind::Record(r) => {
+                    let tys: Vec<&Type> = r.fields.iter().map(|f| &f.ty).collect();
+                    self.load_fields(ty, tys, addr)
+                },
+                TypeDefKind::Tuple(t) => {
+                    self.load_fields(ty, t.types.iter().collect(), addr)
+                },
+                TypeDefKind::List(elem) => {
+                    let (ptr, len) = self.read_ptr_len(addr)?;
+                    self.list(elem, ptr, len)
+                },
+                TypeDefKind::Flags(f) => {
+                    let size = self.layout.size(ty);
+                    let mut bytes = vec![0u8; f.repr().count() * 4];
+                    self.read(addr, &mut bytes[..size])?;
+                    let words: Vec<u32> = bytes.chunks(4)
+                        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                        .collect();
+                    self.flags(&words)
+                },
+                TypeDefKind::Enum(e) => {
+                    let discr = self.read_uint(addr, tag_size(e.tag()))?;
+                    self.case(ty, discr, e.cases.len())
+                },
+                TypeDefKind::Variant(_) |
+                TypeDefKind::Union(_) |
+                TypeDefKind::Option(_) |
+                TypeDefKind::Expected(_) => {
+                    let (tag, cases) = crate::cases_of(self.iface, ty)
+                        .ok_or_else(|| anyhow!("Expected a variant type"))?;
+                    let discr = self.read_uint(addr, tag_size(tag))?;
+                    self.case(ty, discr, cases.len())?;
+                    let payload = addr + self.layout.payload_offset(tag, cases.iter().copied()) as u64;
+                    self.load(cases[discr as usize], payload)
+                },
+                TypeDefKind::Future(_) |
+                TypeDefKind::Stream(_) => {
+                    let v = self.read_uint(addr, 4)?;
+                    self.int(ty, v)
+                },
+            },
+            _ => {
+                let v = self.read_uint(addr, self.layout.size(ty))?;
+                self.int(ty, sign_extend(ty, v))
+            },
+        }
+    }
+
+    fn load_fields(&self, ty: &Type, tys: Vec<&Type>, addr: u64) -> Result<()> {
+        let offsets = self.layout.field_offsets(tys.iter().copied());
+        self.record(ty, tys.len(), |l| {
+            tys.iter().zip(offsets).try_for_each(|(t, off)| l.load(t, addr + off as u64))
+        })
+    }
+
+    fn int(&self, ty: &Type, v: u64) -> Result<()> {
+        if !(self.sink.on_int)(self.sink.user_data, crate::wit_type_of(self.iface, ty), v) {
+            return Err(anyhow!("Value sink rejected an integer"));
+        }
+        Ok(())
+    }
+
+    fn float(&self, ty: &Type, v: f64) -> Result<()> {
+        if !(self.sink.on_float)(self.sink.user_data, crate::wit_type_of(self.iface, ty), v) {
+            return Err(anyhow!("Value sink rejected a float"));
+        }
+        Ok(())
+    }
+
+    // Delivers the string at `ptr` as UTF-8, decoding it from the guest's
+    // string encoding, in which `len` counts code units.
+    fn string(&self, ptr: u64, len: usize) -> Result<()> {
+        let bytes = match self.layout.string_encoding() {
+            WITStringEncoding::Utf8 => self.read_bytes(ptr, len)?,
+            WITStringEncoding::Utf16 => self.utf16(ptr, len)?,
+            WITStringEncoding::Latin1Utf16 if len & UTF16_TAG != 0 => self.utf16(ptr, len & !UTF16_TAG)?,
+            WITStringEncoding::Latin1Utf16 => {
+                let latin1 = self.read_bytes(ptr, len)?;
+                latin1.iter().map(|&b| b as char).collect::<String>().into_bytes()
+            },
+        };
+        if !(self.sink.on_string)(self.sink.user_data, bytes.as_ptr(), bytes.len()) {
+            return Err(anyhow!("Value sink rejected a string"));
+        }
+        Ok(())
+    }
+
+    // Reads `len` UTF-16 code units at `ptr` and returns them as UTF-8.
+    fn utf16(&self, ptr: u64, len: usize) -> Result<Vec<u8>> {
+        if ptr & 1 != 0 {
+            return Err(anyhow!("UTF-16 string at {:#x} is misaligned", ptr));
+        }
+        let size = len.checked_mul(2)
+            .ok_or_else(|| anyhow!("String of {} code units is too large", len))?;
+        let bytes = self.read_bytes(ptr, size)?;
+        let units: Vec<u16> = bytes.chunks(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16(&units)
+            .map(String::into_bytes)
+            .map_err(|_| anyhow!("Invalid UTF-16 string at {:#x}", ptr))
+    }
+
+    fn list(&self, elem: &Type, ptr: u64, len: usize) -> Result<()> {
+        if !(self.sink.on_list_begin)(self.sink.user_data, crate::wit_type_of(self.iface, elem), len) {
+            return Err(anyhow!("Value sink rejected a list"));
+        }
+        let stride = crate::align_to(self.layout.size(elem), self.layout.align(elem));
+        for i in 0..len {
+            self.load(elem, ptr + (i * stride) as u64)?;
+        }
+        if !(self.sink.on_list_end)(self.sink.user_data) {
+            return Err(anyhow!("Value sink rejected a list"));
+        }
+        Ok(())
+    }
+
+    fn record(&self, ty: &Type, count: usize, fields: impl FnOnce(&Self) -> Result<()>) -> Result<()> {
+        if !(self.sink.on_record_begin)(self.sink.user_data, crate::wit_type_of(self.iface, ty), count) {
+            return Err(anyhow!("Value sink rejected a record"));
+        }
+        fields(self)?;
+        if !(self.sink.on_record_end)(self.sink.user_data) {
+            return Err(anyhow!("Value sink rejected a record"));
+        }
+        Ok(())
+    }
+
+    fn case(&self, ty: &Type, discr: u64, count: usize) -> Result<()> {
+        if discr >= count as u64 {
+            return Err(anyhow!("Case {} out of bounds (type has {} cases)", discr, count));
+        }
+        if !(self.sink.on_case)(self.sink.user_data, crate::wit_type_of(self.iface, ty), discr as u32) {
+            return Err(anyhow!("Value sink rejected a case"));
+        }
+        Ok(())
+    }
+
+    fn flags(&self, words: &[u32]) -> Result<()> {
+        if !(self.sink.on_flags)(self.sink.user_data, words.as_ptr(), words.len()) {
+            return Err(anyhow!("Value sink rejected flags"));
+        }
+        Ok(())
+    }
+
+    fn read_ptr_len(&self, addr: u64) -> Result<(u64, usize)> {
+        let size = if self.layout.memory64() { 8 } else { 4 };
+        let ptr = self.read_uint(addr, size)?;
+        let len = self.read_uint(addr + size as u64, size)?;
+        Ok((ptr, len as usize))
+    }
+
+    // Reads a little-endian unsigned integer of `size` bytes.
+    fn read_uint(&self, addr: u64, size: usize) -> Result<u64> {
+        let mut bytes = [0u8; 8];
+        self.read(addr, &mut bytes[..size])?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    // Reads `len` bytes at `addr`, a chunk at a time, so that an invalid
+    // range fails before much is allocated for it.
+    fn read_bytes(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        let limit = if self.layout.memory64() { u64::MAX } else { 1 << 32 };
+        match addr.checked_add(len as u64) {
+            Some(end) if end <= limit => {},
+            _ => return Err(anyhow!("Guest range of {} bytes at {:#x} out of bounds", len, addr)),
+        }
+        let mut bytes = Vec::new();
+        while bytes.len() < len {
+            let start = bytes.len();
+            let chunk = (len - start).min(READ_CHUNK);
+            bytes.resize(start + chunk, 0);
+            self.read(addr + start as u64, &mut bytes[start..])?;
+        }
+        Ok(bytes)
+    }
+
+    fn read(&self, addr: u64, buf: &mut [u8]) -> Result<()> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+        if !(self.mem.read)(self.mem.user_data, addr, buf.as_mut_ptr(), buf.len()) {
+            return Err(anyhow!("Guest read of {} bytes at {:#x} failed", buf.len(), addr));
+        }
+        Ok(())
+    }
+}
+
+// Largest single read of string data, see `read_bytes`.
+const READ_CHUNK: usize = 64 * 1024;
+
+fn next(values: &[u64], index: &mut usize) -> Result<u64> {
+    let v = *values.get(*index)
+        .ok_or_else(|| anyhow!("Too few core values ({})", values.len()))?;
+    *index += 1;
+    Ok(v)
+}
+
+fn tag_size(tag: Int) -> usize {
+    match tag {
+        Int::U8 => 1,
+        Int::U16 => 2,
+        Int::U32 => 4,
+        Int::U64 => 8,
+    }
+}
+
+// Signed integers are delivered sign-extended to 64 bits.
+fn sign_extend(ty: &Type, v: u64) -> u64 {
+    match ty {
+        Type::S8 => v as i8 as i64 as u64,
+        Type::S16 => v as i16 as i64 as u64,
+        Type::S32 => v as i32 as i64 as u64,
+        _ => v,
+    }
+}
@@ -0,0 +1,104 @@
+use parser::{FunctionKind, Interface, Type, TypeDefKind, TypeId};
+use std::collections::{HashMap, HashSet};
+use crate::ident;
+
+// Finds issues in a parsed interface that aren't errors but likely mistakes:
+// named types no function uses, directly or through other types (only
+// checked if there are functions, as a types-only interface is meant to be
+// `use`d), names that aren't kebab-case, and names in the same scope that
+// are the same but for case and `_` vs. `-`.  Each warning is prefixed with
+// the interface name.
+pub(crate) fn lint(iface: &Interface) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if !iface.functions.is_empty() {
+        let mut used = HashSet::new();
+        for f in &iface.functions {
+            for (_, ty) in &f.params {
+                mark(iface, ty, &mut used);
+            }
+            mark(iface, &f.result, &mut used);
+        }
+        for (id, def) in iface.types.iter() {
+            if let Some(name) = &def.name {
+                if def.foreign_module.is_none() && !used.contains(&id) {
+                    warnings.push(format!("type `{}` is never used", name));
+                }
+            }
+        }
+    }
+
+    names(&mut warnings, "function", iface.functions.iter().map(|f| f.name.as_str()));
+    names(&mut warnings, "type", iface.types.iter().filter_map(|(_, def)| def.name.as_deref()));
+    names(&mut warnings, "resource", iface.resources.iter().map(|(_, r)| r.name.as_str()));
+    for f in &iface.functions {
+        let scope = match &f.kind {
+            FunctionKind::Freestanding => format!("parameter of `{}`", f.name),
+            FunctionKind::Method { name, .. } |
+            FunctionKind::Static { name, .. } => format!("parameter of `{}`", name),
+        };
+        names(&mut warnings, &scope, f.params.iter().map(|(n, _)| n.as_str()));
+    }
+    for (_, def) in iface.types.iter() {
+        let owner = match &def.name {
+            Some(name) => name,
+            None => continue,
+        };
+        let members: Vec<&str> = match &def.kind {
+            TypeDefKind::Record(r) => r.fields.iter().map(|f| f.name.as_str()).collect(),
+            TypeDefKind::Variant(v) => v.cases.iter().map(|c| c.name.as_str()).collect(),
+            TypeDefKind::Enum(e) => e.cases.iter().map(|c| c.name.as_str()).collect(),
+            TypeDefKind::Flags(f) => f.flags.iter().map(|f| f.name.as_str()).collect(),
+            _ => continue,
+        };
+        names(&mut warnings, &format!("member of `{}`", owner), members.into_iter());
+    }
+
+    warnings.into_iter().map(|w| format!("{}: {}", iface.name, w)).collect()
+}
+
+// Adds `ty` and the types it refers to to `used`.
+fn mark(iface: &Interface, ty: &Type, used: &mut HashSet<TypeId>) {
+    let id = match ty {
+        Type::Id(id) => *id,
+        _ => return,
+    };
+    if !used.insert(id) {
+        return;
+    }
+    let mut all = |tys: Vec<&Type>| tys.into_iter().for_each(|t| mark(iface, t, used));
+    match &iface.types[id].kind {
+        TypeDefKind::Type(t) |
+        TypeDefKind::List(t) |
+        TypeDefKind::Option(t) |
+        TypeDefKind::Future(t) => all(vec![t]),
+        TypeDefKind::Tuple(t) => all(t.types.iter().collect()),
+        TypeDefKind::Union(u) => all(u.cases.iter().map(|c| &c.ty).collect()),
+        TypeDefKind::Expected(e) => all(vec![&e.ok, &e.err]),
+        TypeDefKind::Stream(s) => all(vec![&s.element, &s.end]),
+        TypeDefKind::Record(r) => all(r.fields.iter().map(|f| &f.ty).collect()),
+        TypeDefKind::Variant(v) => all(v.cases.iter().map(|c| &c.ty).collect()),
+        TypeDefKind::Flags(_) |
+        TypeDefKind::Enum(_) => {},
+    }
+}
+
+// Checks the names of one scope, e.g. the fields of a record.
+fn names<'a>(warnings: &mut Vec<String>, what: &str, names: impl Iterator<Item = &'a str>) {
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for name in names {
+        let bare = name.trim_start_matches('%');
+        if bare.chars().any(|c| c == '_' || c.is_ascii_uppercase()) && bare != "_" {
+            warnings.push(format!("{} `{}` is not kebab-case", what, name));
+        }
+        match seen.get(&ident::normalize(name)) {
+            Some(other) if *other != name => {
+                warnings.push(format!("{} `{}` differs from `{}` only in case or `_` vs. `-`", what, name, other));
+            },
+            Some(_) => {},
+            None => {
+                seen.insert(ident::normalize(name), name);
+            },
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::Error;
+use std::fmt;
+
+// Where a parse error is in its source.  wit-parser only keeps the span in
+// the message it renders, e.g.
+//
+//     expected `,`, found `}`
+//          --> wit:3:14
+//           |
+//        3 |     f: func(a: u32 b: u32)
+//           |              ^
+//
+// so it's recovered from there, with the byte span found in the source.
+#[derive(Clone, Debug)]
+pub(crate) struct Location {
+    pub(crate) file:   String,
+    pub(crate) line:   usize,  // 1-based
+    pub(crate) column: usize,  // 1-based, in bytes
+    pub(crate) start:  usize,  // byte offsets into the file's source
+    pub(crate) end:    usize,
+}
+
+// A parse error along with its location.  Displays as the original message.
+#[derive(Debug)]
+pub(crate) struct ParseError {
+    msg:                 String,
+    pub(crate) location: Location,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Attaches the location to `err` if it's a parse error rendered with one,
+// looking up the text of the file it names with `source`.  Other errors are
+// returned as is.
+pub(crate) fn locate(err: Error, source: impl Fn(&str) -> Option<String>) -> Error {
+    let msg = err.to_string();
+    match find(&msg, source) {
+        Some(location) => Error::new(ParseError { msg, location }),
+        None => err,
+    }
+}
+
+fn find(msg: &str, source: impl Fn(&str) -> Option<String>) -> Option<Location> {
+    let lines: Vec<&str> = msg.lines().collect();
+    let at = lines.iter().position(|l| l.trim_start().starts_with("--> "))?;
+    let mut place = lines[at].trim_start()["--> ".len()..].rsplitn(3, ':');
+    let column: usize = place.next()?.parse().ok()?;
+    let line: usize = place.next()?.parse().ok()?;
+    let file = place.next()?.to_string();
+    if line == 0 || column == 0 {
+        return None;
+    }
+
+    let text = source(&file)?;
+    let line_start: usize = text.split_terminator('\n').take(line - 1).map(|l| l.len() + 1).sum();
+    let start = line_start + column - 1;
+    if start > text.len() || !text.is_char_boundary(start) {
+        return None;
+    }
+    // The marker is a `^` followed by a `-` per further character of the
+    // span, two lines below the location.
+    let width = lines.get(at + 3)
+        .and_then(|l| l.find('^').map(|i| 1 + l[i + 1..].chars().take_while(|c| *c == '-').count()))
+        .unwrap_or(1);
+    let end = text[start..].char_indices().nth(width).map(|(i, _)| start + i).unwrap_or_else(|| text.len());
+    Some(Location { file, line, column, start, end })
+}
@@ -0,0 +1,245 @@
+use anyhow::{anyhow, Result};
+use parser::{Int, Interface, Type, TypeDefKind};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::ptr;
+use crate::layout::Layout;
+use crate::{WITGuestMemory, WITStringEncoding, WITValueSource};
+
+// Writes values pulled from a host value source into guest memory using the
+// canonical ABI memory layout.
+pub(crate) struct Lowerer<'a> {
+    pub(crate) iface:  &'a Interface,
+    pub(crate) layout: &'a Layout,
+    pub(crate) src:    &'a WITValueSource,
+    pub(crate) mem:    &'a WITGuestMemory,
+}
+
+impl<'a> Lowerer<'a> {
+    pub(crate) fn store(&self, ty: &Type, addr: u64) -> Result<()> {
+        let ty = crate::resolve_alias(self.iface, ty);
+        match ty {
+            Type::Unit => Ok(()),
+            Type::Bool |
+            Type::U8 |
+            Type::S8 => self.store_int(ty, addr, 1),
+            Type::U16 |
+            Type::S16 => self.store_int(ty, addr, 2),
+            Type::U32 |
+            Type::S32 |
+            Type::Char |
+            Type::Handle(_) => self.store_int(ty, addr, 4),
+            Type::U64 |
+            Type::S64 => self.store_int(ty, addr, 8),
+            Type::Float32 => {
+                let v = self.get_float(ty)? as f32;
+                self.write(addr, &v.to_le_bytes())
+            },
+            Type::Float64 => {
+                let v = self.get_float(ty)?;
+                self.write(addr, &v.to_le_bytes())
+            },
+            Type::String => {
+                let mut data: *const u8 = ptr::null();
+                let mut len: usize = 0;
+                if !(self.src.get_string)(self.src.user_data, &mut data, &mut len) || (data.is_null() && len > 0) {
+                    return Err(anyhow!("Value source failed to supply a string"));
+                }
+                let bytes = if len > 0 {
+                    unsafe { std::slice::from_raw_parts(data, len) }
+                } else {
+                    &[]
+                };
+                self.store_string(bytes, addr)
+            },
+            Type::Id(id) => match &self.iface.types[*id].kind {
+                TypeDefKind::Type(_) => unreachable!(),
+                TypeDefKind::Record(r) => {
+                    let tys: Vec<&Type> = r.fields.iter().map(|f| &f.ty).collect();
+                    self.store_record(ty, tys, addr)
+                },
+                TypeDefKind::Tuple(t) => {
+                    self.store_record(ty, t.types.iter().collect(), addr)
+                },
+                TypeDefKind::List(elem) => {
+                    let mut len: usize = 0;
+                    if !(self.src.begin_list)(self.src.user_data, crate::wit_type_of(self.iface, elem), &mut len) {
+                        return Err(anyhow!("Value source failed to begin a list"));
+                    }
+                    let stride = crate::align_to(self.layout.size(elem), self.layout.align(elem));
+                    let size = stride.checked_mul(len)
+                        .ok_or_else(|| anyhow!("List of {} elements is too large", len))?;
+                    let buf = self.alloc(self.layout.align(elem), size)?;
+                    for i in 0..len {
+                        self.store(elem, buf + (i * stride) as u64)?;
+                    }
+                    if !(self.src.end_list)(self.src.user_data) {
+                        return Err(anyhow!("Value source failed to end a list"));
+                    }
+                    self.store_ptr_len(addr, buf, len)
+                },
+                TypeDefKind::Flags(f) => {
+                    let count = f.repr().count();
+                    let mut words = vec![0u32; count];
+                    if !(self.src.get_flags)(self.src.user_data, words.as_mut_ptr(), count) {
+                        return Err(anyhow!("Value source failed to supply flags"));
+                    }
+                    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+                    self.write(addr, &bytes[..self.layout.size(ty)])
+                },
+                TypeDefKind::Enum(e) => {
+                    self.get_case(ty, e.cases.len())
+                        .and_then(|discr| self.store_tag(e.tag(), addr, discr))
+                },
+                TypeDefKind::Variant(v) => {
+                    self.store_variant(ty, v.tag(), v.cases.iter().map(|c| &c.ty).collect(), addr)
+                },
+                TypeDefKind::Union(u) => {
+                    self.store_variant(ty, u.tag(), u.cases.iter().map(|c| &c.ty).collect(), addr)
+                },
+                TypeDefKind::Option(t) => {
+                    self.store_variant(ty, Int::U8, vec![&Type::Unit, t], addr)
+                },
+                TypeDefKind::Expected(e) => {
+                    self.store_variant(ty, Int::U8, vec![&e.ok, &e.err], addr)
+                },
+                TypeDefKind::Future(_) |
+                TypeDefKind::Stream(_) => self.store_int(ty, addr, 4),
+            },
+        }
+    }
+
+    // Stores `tys` as the fields of a record at `addr`, e.g. a record, a
+    // tuple or a function's params.
+    pub(crate) fn store_record(&self, ty: &Type, tys: Vec<&Type>, addr: u64) -> Result<()> {
+        if !(self.src.begin_record)(self.src.user_data, crate::wit_type_of(self.iface, ty), tys.len()) {
+            return Err(anyhow!("Value source failed to begin a record"));
+        }
+        let offsets = self.layout.field_offsets(tys.iter().copied());
+        for (t, off) in tys.into_iter().zip(offsets) {
+            self.store(t, addr + off as u64)?;
+        }
+        if !(self.src.end_record)(self.src.user_data) {
+            return Err(anyhow!("Value source failed to end a record"));
+        }
+        Ok(())
+    }
+
+    // Transcodes the UTF-8 `bytes` to the guest's string encoding.  For
+    // latin1+utf16, a string that isn't all Latin-1 is stored as UTF-16 with
+    // the top bit of the length set.
+    fn store_string(&self, bytes: &[u8], addr: u64) -> Result<()> {
+        let (align, data, len) = match self.layout.string_encoding() {
+            WITStringEncoding::Utf8 => (1, Cow::Borrowed(bytes), bytes.len()),
+            WITStringEncoding::Utf16 => {
+                let units = utf16(bytes)?;
+                let len = units.len() / 2;
+                (2, Cow::Owned(units), len)
+            },
+            WITStringEncoding::Latin1Utf16 => {
+                let s = utf8(bytes)?;
+                if s.chars().all(|c| (c as u32) < 0x100) {
+                    let latin1: Vec<u8> = s.chars().map(|c| c as u8).collect();
+                    let len = latin1.len();
+                    (2, Cow::Owned(latin1), len)
+                } else {
+                    let units = utf16(bytes)?;
+                    let len = units.len() / 2;
+                    if len >= UTF16_TAG {
+                        return Err(anyhow!("String of {} code units is too long", len));
+                    }
+                    (2, Cow::Owned(units), len | UTF16_TAG)
+                }
+            },
+        };
+        let buf = self.alloc(align, data.len())?;
+        self.write(buf, &data)?;
+        self.store_ptr_len(addr, buf, len)
+    }
+
+    fn store_variant(&self, ty: &Type, tag: Int, cases: Vec<&Type>, addr: u64) -> Result<()> {
+        let discr = self.get_case(ty, cases.len())?;
+        self.store_tag(tag, addr, discr)?;
+        let payload = addr + self.layout.payload_offset(tag, cases.iter().copied()) as u64;
+        self.store(cases[discr as usize], payload)
+    }
+
+    fn get_case(&self, ty: &Type, count: usize) -> Result<u32> {
+        let mut discr: u32 = 0;
+        if !(self.src.get_case)(self.src.user_data, crate::wit_type_of(self.iface, ty), count, &mut discr) {
+            return Err(anyhow!("Value source failed to supply a case"));
+        }
+        if discr as usize >= count {
+            return Err(anyhow!("Case {} out of bounds (type has {} cases)", discr, count));
+        }
+        Ok(discr)
+    }
+
+    fn store_tag(&self, tag: Int, addr: u64, discr: u32) -> Result<()> {
+        let bytes = (discr as u64).to_le_bytes();
+        match tag {
+            Int::U8 => self.write(addr, &bytes[..1]),
+            Int::U16 => self.write(addr, &bytes[..2]),
+            Int::U32 => self.write(addr, &bytes[..4]),
+            Int::U64 => self.write(addr, &bytes[..8]),
+        }
+    }
+
+    fn store_int(&self, ty: &Type, addr: u64, size: usize) -> Result<()> {
+        let mut v: u64 = 0;
+        if !(self.src.get_int)(self.src.user_data, crate::wit_type_of(self.iface, ty), &mut v) {
+            return Err(anyhow!("Value source failed to supply an integer"));
+        }
+        self.write(addr, &v.to_le_bytes()[..size])
+    }
+
+    fn get_float(&self, ty: &Type) -> Result<f64> {
+        let mut v: f64 = 0.0;
+        if !(self.src.get_float)(self.src.user_data, crate::wit_type_of(self.iface, ty), &mut v) {
+            return Err(anyhow!("Value source failed to supply a float"));
+        }
+        Ok(v)
+    }
+
+    fn store_ptr_len(&self, addr: u64, buf: u64, len: usize) -> Result<()> {
+        if self.layout.memory64() {
+            self.write(addr, &buf.to_le_bytes())?;
+            self.write(addr + 8, &(len as u64).to_le_bytes())
+        } else {
+            let buf = u32::try_from(buf).map_err(|_| anyhow!("Address {:#x} out of range", buf))?;
+            let len = u32::try_from(len).map_err(|_| anyhow!("Length {} out of range", len))?;
+            self.write(addr, &buf.to_le_bytes())?;
+            self.write(addr + 4, &len.to_le_bytes())
+        }
+    }
+
+    fn alloc(&self, align: usize, size: usize) -> Result<u64> {
+        let mut addr: u64 = 0;
+        if !(self.mem.realloc)(self.mem.user_data, 0, 0, align, size, &mut addr) {
+            return Err(anyhow!("Guest allocation of {} bytes failed", size));
+        }
+        Ok(addr)
+    }
+
+    fn write(&self, addr: u64, bytes: &[u8]) -> Result<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        if !(self.mem.write)(self.mem.user_data, addr, bytes.as_ptr(), bytes.len()) {
+            return Err(anyhow!("Guest write of {} bytes at {:#x} failed", bytes.len(), addr));
+        }
+        Ok(())
+    }
+}
+
+// Set in the length of a latin1+utf16 string stored as UTF-16.
+pub(crate) const UTF16_TAG: usize = 1 << 31;
+
+fn utf8(bytes: &[u8]) -> Result<&str> {
+    std::str::from_utf8(bytes).map_err(|e| anyhow!("Value source supplied invalid UTF-8: {}", e))
+}
+
+// The UTF-16 code units of `bytes`, little-endian.
+fn utf16(bytes: &[u8]) -> Result<Vec<u8>> {
+    Ok(utf8(bytes)?.encode_utf16().flat_map(|u| u.to_le_bytes()).collect())
+}
@@ -0,0 +1,148 @@
+use parser::{Docs, Interface, Type, TypeDefKind};
+
+// Renders a Markdown reference page for an interface: its functions, then
+// its named types and resources, each with their doc comments.
+pub(crate) fn emit(iface: &Interface, name: &str) -> String {
+    let mut out = format!("# {}\n", name);
+    if !iface.functions.is_empty() {
+        out.push_str("\n## Functions\n");
+    }
+    for f in &iface.functions {
+        out.push_str(&format!("\n### `{}`\n\n", f.name));
+        docs(&f.docs, &mut out);
+        let params: Vec<String> = f.params.iter()
+            .map(|(n, ty)| format!("{}: {}", n, type_expr(iface, ty)))
+            .collect();
+        let result = if crate::is_unit(iface, &f.result) {
+            String::new()
+        } else {
+            format!(" -> {}", type_expr(iface, &f.result))
+        };
+        out.push_str(&format!("```wit\n{}: func({}){}\n```\n", f.name, params.join(", "), result));
+        if !f.params.is_empty() {
+            out.push_str("\n| Parameter | Type |\n|---|---|\n");
+            for (n, ty) in &f.params {
+                out.push_str(&format!("| `{}` | `{}` |\n", n, type_expr(iface, ty)));
+            }
+        }
+        if !crate::is_unit(iface, &f.result) {
+            out.push_str(&format!("\nReturns `{}`.\n", type_expr(iface, &f.result)));
+        }
+    }
+
+    let named: Vec<_> = iface.types.iter().filter(|(_, def)| def.name.is_some()).collect();
+    if !named.is_empty() {
+        out.push_str("\n## Types\n");
+    }
+    for (_, def) in named {
+        let name = def.name.as_deref().unwrap_or("");
+        let (keyword, rows) = match &def.kind {
+            TypeDefKind::Record(r) => ("record", Some(("Field", r.fields.iter()
+                .map(|f| (f.name.clone(), Some(&f.ty), &f.docs))
+                .collect::<Vec<_>>()))),
+            TypeDefKind::Variant(v) => ("variant", Some(("Case", v.cases.iter()
+                .map(|c| (c.name.clone(), Some(&c.ty), &c.docs))
+                .collect()))),
+            TypeDefKind::Union(u) => ("union", Some(("Case", u.cases.iter().enumerate()
+                .map(|(i, c)| (i.to_string(), Some(&c.ty), &c.docs))
+                .collect()))),
+            TypeDefKind::Flags(f) => ("flags", Some(("Flag", f.flags.iter()
+                .map(|f| (f.name.clone(), None, &f.docs))
+                .collect()))),
+            TypeDefKind::Enum(e) => ("enum", Some(("Case", e.cases.iter()
+                .map(|c| (c.name.clone(), None, &c.docs))
+                .collect()))),
+            _ => ("type", None),
+        };
+        out.push_str(&format!("\n### {} `{}`\n\n", keyword, name));
+        docs(&def.docs, &mut out);
+        match rows {
+            Some((heading, rows)) => {
+                let typed = rows.iter().any(|(_, ty, _)| ty.is_some());
+                if typed {
+                    out.push_str(&format!("| {} | Type | Description |\n|---|---|---|\n", heading));
+                } else {
+                    out.push_str(&format!("| {} | Description |\n|---|---|\n", heading));
+                }
+                for (n, ty, d) in rows {
+                    let desc = d.contents.as_deref().unwrap_or("").trim().replace('\n', " ");
+                    match ty {
+                        Some(ty) if typed => {
+                            out.push_str(&format!("| `{}` | `{}` | {} |\n", n, type_expr(iface, ty), desc));
+                        },
+                        _ => out.push_str(&format!("| `{}` | {} |\n", n, desc)),
+                    }
+                }
+            },
+            None => {
+                if let TypeDefKind::Type(t) = &def.kind {
+                    out.push_str(&format!("Alias for `{}`.\n", type_expr(iface, t)));
+                } else {
+                    out.push_str(&format!("Defined as `{}`.\n", inline(iface, &def.kind)));
+                }
+            },
+        }
+    }
+
+    if iface.resources.len() > 0 {
+        out.push_str("\n## Resources\n");
+    }
+    for (_, r) in iface.resources.iter() {
+        out.push_str(&format!("\n### resource `{}`\n\n", r.name));
+        docs(&r.docs, &mut out);
+    }
+    out
+}
+
+fn docs(docs: &Docs, out: &mut String) {
+    if let Some(text) = &docs.contents {
+        let text = text.trim();
+        if !text.is_empty() {
+            out.push_str(text);
+            out.push_str("\n\n");
+        }
+    }
+}
+
+// Spells `ty` as in legacy WIT source, inlining anonymous types.
+pub(crate) fn type_expr(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Unit => "unit".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::U8 => "u8".to_string(),
+        Type::U16 => "u16".to_string(),
+        Type::U32 => "u32".to_string(),
+        Type::U64 => "u64".to_string(),
+        Type::S8 => "s8".to_string(),
+        Type::S16 => "s16".to_string(),
+        Type::S32 => "s32".to_string(),
+        Type::S64 => "s64".to_string(),
+        Type::Float32 => "float32".to_string(),
+        Type::Float64 => "float64".to_string(),
+        Type::Char => "char".to_string(),
+        Type::String => "string".to_string(),
+        Type::Handle(r) => format!("handle {}", iface.resources[*r].name),
+        Type::Id(id) => match &iface.types[*id].name {
+            Some(name) => name.clone(),
+            None => inline(iface, &iface.types[*id].kind),
+        },
+    }
+}
+
+pub(crate) fn inline(iface: &Interface, kind: &TypeDefKind) -> String {
+    let list = |tys: Vec<&Type>| tys.into_iter().map(|t| type_expr(iface, t)).collect::<Vec<_>>().join(", ");
+    match kind {
+        TypeDefKind::Type(t) => type_expr(iface, t),
+        TypeDefKind::List(t) => format!("list<{}>", type_expr(iface, t)),
+        TypeDefKind::Option(t) => format!("option<{}>", type_expr(iface, t)),
+        TypeDefKind::Tuple(t) => format!("tuple<{}>", list(t.types.iter().collect())),
+        TypeDefKind::Union(u) => format!("union {{ {} }}", list(u.cases.iter().map(|c| &c.ty).collect())),
+        TypeDefKind::Expected(e) => format!("expected<{}, {}>", type_expr(iface, &e.ok), type_expr(iface, &e.err)),
+        TypeDefKind::Future(t) => format!("future<{}>", type_expr(iface, t)),
+        TypeDefKind::Stream(s) => format!("stream<{}, {}>", type_expr(iface, &s.element), type_expr(iface, &s.end)),
+        TypeDefKind::Record(_) => "record".to_string(),
+        TypeDefKind::Flags(_) => "flags".to_string(),
+        TypeDefKind::Enum(_) => "enum".to_string(),
+        TypeDefKind::Variant(_) => "variant".to_string(),
+    }
+}
@@ -0,0 +1,209 @@
+use parser::{Docs, Function, FunctionKind, Interface, Type, TypeDefKind};
+use crate::ident;
+
+// Words reserved by component-model WIT on top of those reserved by the
+// legacy syntax.
+const KEYWORDS: &[&str] = &[
+    "borrow", "constructor", "export", "f32", "f64", "import", "include",
+    "own", "package", "result", "with", "world",
+];
+
+// Renders an interface parsed from the legacy syntax as component-model WIT:
+// a package holding the interface, plus a world exporting it.
+//
+// Legacy constructs without a direct equivalent are rewritten: `expected`
+// becomes `result`, unions become variants with one case per member
+// (`c0`, `c1`, ...), unit payloads are dropped, and handles become `own`.
+pub(crate) fn emit(iface: &Interface, package: &str, name: &str) -> String {
+    let name = escape(name);
+    let mut out = format!("package {};\n\ninterface {} {{\n", package, name);
+    for (_, def) in iface.types.iter() {
+        if let (Some(n), Some(module)) = (&def.name, &def.foreign_module) {
+            out.push_str(&format!("  use {}.{{{}}};\n", escape(module), escape(n)));
+        }
+    }
+    for (id, def) in iface.types.iter() {
+        let n = match &def.name {
+            Some(n) if def.foreign_module.is_none() => escape(n),
+            _ => continue,
+        };
+        out.push('\n');
+        docs(&def.docs, "  ", &mut out);
+        out.push_str(&match &def.kind {
+            TypeDefKind::Record(r) => {
+                let mut s = format!("  record {} {{\n", n);
+                for f in &r.fields {
+                    docs(&f.docs, "    ", &mut s);
+                    s.push_str(&format!("    {}: {},\n", escape(&f.name), type_expr(iface, &f.ty)));
+                }
+                s + "  }\n"
+            },
+            TypeDefKind::Flags(f) => {
+                let mut s = format!("  flags {} {{\n", n);
+                for flag in &f.flags {
+                    docs(&flag.docs, "    ", &mut s);
+                    s.push_str(&format!("    {},\n", escape(&flag.name)));
+                }
+                s + "  }\n"
+            },
+            TypeDefKind::Enum(e) => {
+                let mut s = format!("  enum {} {{\n", n);
+                for case in &e.cases {
+                    docs(&case.docs, "    ", &mut s);
+                    s.push_str(&format!("    {},\n", escape(&case.name)));
+                }
+                s + "  }\n"
+            },
+            TypeDefKind::Variant(v) => {
+                let mut s = format!("  variant {} {{\n", n);
+                for case in &v.cases {
+                    docs(&case.docs, "    ", &mut s);
+                    s.push_str(&format!("    {}{},\n", escape(&case.name), payload(iface, &case.ty)));
+                }
+                s + "  }\n"
+            },
+            TypeDefKind::Union(u) => {
+                let mut s = format!("  variant {} {{\n", n);
+                for (i, case) in u.cases.iter().enumerate() {
+                    docs(&case.docs, "    ", &mut s);
+                    s.push_str(&format!("    c{}{},\n", i, payload(iface, &case.ty)));
+                }
+                s + "  }\n"
+            },
+            _ => format!("  type {} = {};\n", n, type_expr(iface, &Type::Id(id))),
+        });
+    }
+    for (rid, r) in iface.resources.iter() {
+        let funcs: Vec<&Function> = iface.functions.iter()
+            .filter(|f| match &f.kind {
+                FunctionKind::Method { resource, .. } |
+                FunctionKind::Static { resource, .. } => *resource == rid,
+                FunctionKind::Freestanding => false,
+            })
+            .collect();
+        out.push('\n');
+        docs(&r.docs, "  ", &mut out);
+        if funcs.is_empty() {
+            out.push_str(&format!("  resource {};\n", escape(&r.name)));
+            continue;
+        }
+        out.push_str(&format!("  resource {} {{\n", escape(&r.name)));
+        for f in funcs {
+            func(iface, f, "    ", &mut out);
+        }
+        out.push_str("  }\n");
+    }
+    out.push('\n');
+    for f in &iface.functions {
+        if let FunctionKind::Freestanding = f.kind {
+            func(iface, f, "  ", &mut out);
+        }
+    }
+    out.push_str(&format!("}}\n\nworld {} {{\n  export {};\n}}\n", name, name));
+    out
+}
+
+fn func(iface: &Interface, f: &Function, indent: &str, out: &mut String) {
+    // Methods take their resource as an implicit first `self` param in
+    // component-model WIT.
+    let (name, kind, params) = match &f.kind {
+        FunctionKind::Freestanding => (f.name.as_str(), "func", &f.params[..]),
+        FunctionKind::Static { name, .. } => (name.as_str(), "static func", &f.params[..]),
+        FunctionKind::Method { name, .. } => {
+            let params = match f.params.first() {
+                Some((p, _)) if p == "self" => &f.params[1..],
+                _ => &f.params[..],
+            };
+            (name.as_str(), "func", params)
+        },
+    };
+    let params: Vec<String> = params.iter()
+        .map(|(n, ty)| format!("{}: {}", escape(n), type_expr(iface, ty)))
+        .collect();
+    let result = if crate::is_unit(iface, &f.result) {
+        String::new()
+    } else {
+        format!(" -> {}", type_expr(iface, &f.result))
+    };
+    docs(&f.docs, indent, out);
+    out.push_str(&format!("{}{}: {}({}){};\n", indent, escape(name), kind, params.join(", "), result));
+}
+
+fn docs(docs: &Docs, indent: &str, out: &mut String) {
+    if let Some(text) = &docs.contents {
+        for line in text.trim_end().lines() {
+            out.push_str(&format!("{}/// {}\n", indent, line.trim()));
+        }
+    }
+}
+
+fn payload(iface: &Interface, ty: &Type) -> String {
+    if crate::is_unit(iface, ty) {
+        String::new()
+    } else {
+        format!("({})", type_expr(iface, ty))
+    }
+}
+
+// Spells `ty` as a type expression, inlining anonymous types.  Unit has no
+// spelling of its own and is written as `tuple<>`.
+fn type_expr(iface: &Interface, ty: &Type) -> String {
+    let id = match ty {
+        Type::Unit => return "tuple<>".to_string(),
+        Type::Bool => return "bool".to_string(),
+        Type::U8 => return "u8".to_string(),
+        Type::U16 => return "u16".to_string(),
+        Type::U32 => return "u32".to_string(),
+        Type::U64 => return "u64".to_string(),
+        Type::S8 => return "s8".to_string(),
+        Type::S16 => return "s16".to_string(),
+        Type::S32 => return "s32".to_string(),
+        Type::S64 => return "s64".to_string(),
+        Type::Float32 => return "f32".to_string(),
+        Type::Float64 => return "f64".to_string(),
+        Type::Char => return "char".to_string(),
+        Type::String => return "string".to_string(),
+        Type::Handle(r) => return format!("own<{}>", escape(&iface.resources[*r].name)),
+        Type::Id(id) => *id,
+    };
+    let def = &iface.types[id];
+    if let Some(name) = &def.name {
+        return escape(name);
+    }
+    match &def.kind {
+        TypeDefKind::Type(t) => type_expr(iface, t),
+        TypeDefKind::List(t) => format!("list<{}>", type_expr(iface, t)),
+        TypeDefKind::Option(t) => format!("option<{}>", type_expr(iface, t)),
+        TypeDefKind::Tuple(t) => {
+            let types: Vec<String> = t.types.iter().map(|t| type_expr(iface, t)).collect();
+            format!("tuple<{}>", types.join(", "))
+        },
+        TypeDefKind::Expected(e) => {
+            match (crate::is_unit(iface, &e.ok), crate::is_unit(iface, &e.err)) {
+                (true, true) => "result".to_string(),
+                (false, true) => format!("result<{}>", type_expr(iface, &e.ok)),
+                (true, false) => format!("result<_, {}>", type_expr(iface, &e.err)),
+                (false, false) => format!("result<{}, {}>", type_expr(iface, &e.ok), type_expr(iface, &e.err)),
+            }
+        },
+        TypeDefKind::Future(t) => {
+            if crate::is_unit(iface, t) {
+                "future".to_string()
+            } else {
+                format!("future<{}>", type_expr(iface, t))
+            }
+        },
+        TypeDefKind::Stream(s) => format!("stream<{}>", type_expr(iface, &s.element)),
+        // Records, flags, enums, variants and unions are always named.
+        _ => "tuple<>".to_string(),
+    }
+}
+
+fn escape(name: &str) -> String {
+    let name = name.trim_start_matches('%');
+    if ident::is_keyword(name) || KEYWORDS.contains(&name) {
+        format!("%{}", name)
+    } else {
+        name.to_string()
+    }
+}
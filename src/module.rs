@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+use std::str;
+
+const MAGIC: &[u8] = b"\0asm";
+const VERSION: &[u8] = &[1, 0, 0, 0];
+
+// Returns the name and WIT source of every interface embedded in the custom
+// sections of the core wasm module `bytes` named in `names`, in the order
+// they appear.  A name ending in `:` matches every section starting with it,
+// the rest of the section name being the interface name; any other name
+// must match exactly and is also the interface name.
+pub(crate) fn wit_sections(bytes: &[u8], names: &[&str]) -> Result<Vec<(String, String)>> {
+    let mut res = Vec::new();
+    for (name, payload) in custom_sections(bytes)? {
+        let iface = names.iter().find_map(|n| if n.ends_with(':') {
+            name.strip_prefix(n).filter(|iface| !iface.is_empty())
+        } else {
+            Some(name).filter(|name| name == n)
+        });
+        let iface = match iface {
+            Some(iface) => iface,
+            None => continue,
+        };
+        let body = str::from_utf8(payload)
+            .map_err(|_| anyhow!("Custom section `{}` is not valid UTF-8", name))?;
+        res.push((iface.to_string(), body.to_string()));
+    }
+    Ok(res)
+}
+
+fn custom_sections(bytes: &[u8]) -> Result<Vec<(&str, &[u8])>> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(anyhow!("Not a wasm module"));
+    }
+    if bytes.len() < 8 || &bytes[4..8] != VERSION {
+        return Err(anyhow!("Unsupported wasm version (only core modules are supported)"));
+    }
+    let mut sections = Vec::new();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let id = bytes[pos];
+        pos += 1;
+        let size = read_u32(bytes, &mut pos)? as usize;
+        let end = pos.checked_add(size)
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| anyhow!("Section at offset {} runs past the end of the module", pos))?;
+        if id == 0 {
+            let mut p = pos;
+            let name_len = read_u32(bytes, &mut p)? as usize;
+            if p + name_len > end {
+                return Err(anyhow!("Malformed custom section name at offset {}", pos));
+            }
+            let name = str::from_utf8(&bytes[p..p + name_len])
+                .map_err(|_| anyhow!("Malformed custom section name at offset {}", pos))?;
+            sections.push((name, &bytes[p + name_len..end]));
+        }
+        pos = end;
+    }
+    Ok(sections)
+}
+
+// Reads an unsigned LEB128 u32 at `pos`, advancing `pos` past it.
+fn read_u32(bytes: &[u8], pos: &mut usize) -> Result<u32> {
+    let mut res: u32 = 0;
+    for shift in (0..35).step_by(7) {
+        let b = *bytes.get(*pos).ok_or_else(|| anyhow!("Unexpected end of module"))?;
+        *pos += 1;
+        if shift == 28 && b > 0x0f {
+            return Err(anyhow!("Invalid LEB128 integer at offset {}", *pos - 1));
+        }
+        res |= ((b & 0x7f) as u32) << shift;
+        if b & 0x80 == 0 {
+            return Ok(res);
+        }
+    }
+    unreachable!()
+}
@@ -0,0 +1,52 @@
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+thread_local! {
+    static DEPTH: Cell<usize> = Cell::new(0);  // entry points running on this thread
+    static LAST: RefCell<Option<String>> = RefCell::new(None);  // last panic caught
+}
+
+// Runs the entry point `name`, turning a panic into an error message with
+// the entry point, the panic's message and location, and a backtrace.
+pub(crate) fn catch<T>(name: &str, f: impl FnOnce() -> T) -> Result<T, String> {
+    install_hook();
+    DEPTH.with(|d| d.set(d.get() + 1));
+    let res = panic::catch_unwind(AssertUnwindSafe(f));
+    DEPTH.with(|d| d.set(d.get() - 1));
+    res.map_err(|_| {
+        let report = LAST.with(|l| l.borrow_mut().take()).unwrap_or_else(|| "unknown panic".to_string());
+        format!("Caught Rust panic in {}: {}", name, report)
+    })
+}
+
+// The entry point `ffi_return!` was given the body of, from the name of the
+// function it calls, e.g. `wit_parse` for `_wit_parse(...)`.
+pub(crate) fn entry_point(call: &str) -> &str {
+    call.split('(').next().unwrap_or(call).trim().trim_start_matches('_')
+}
+
+// The backtrace has to be taken while the stack is still there, so panics in
+// entry points are recorded by a panic hook, installed once, which also keeps
+// them off stderr.  Other panics go to the previous hook.
+fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let prev = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if DEPTH.with(|d| d.get()) == 0 {
+                return prev(info);
+            }
+            let payload = info.payload();
+            let msg = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+                (Some(s), _) => s.to_string(),
+                (_, Some(s)) => s.clone(),
+                _ => "Box<dyn Any>".to_string(),
+            };
+            let at = info.location().map(|l| format!(" at {}", l)).unwrap_or_default();
+            let report = format!("{}{}\n{}", msg, at, Backtrace::force_capture());
+            LAST.with(|l| *l.borrow_mut() = Some(report));
+        }));
+    });
+}
@@ -0,0 +1,152 @@
+use anyhow::{anyhow, Result};
+use parser::{Interface, Int, Type, TypeDefKind, TypeId};
+use crate::layout::Layout;
+use crate::{WITPlanInstr, WITPlanOp};
+
+// Where a value lives: at an index into the core values, or at a byte
+// offset from the current base address in guest memory.
+#[derive(Clone, Copy)]
+enum Loc {
+    Flat(usize),
+    Mem(usize),
+}
+
+// Builds the plan for reading a value of type `ty`, in pre-order: each
+// instruction is followed by the `skip` instructions making up its children.
+// If `indirect`, the value is read from guest memory starting at offset 0
+// from a base address supplied by the caller (the return pointer).
+pub(crate) fn lift_plan(iface: &Interface, sizes: &Layout, ty: &Type, indirect: bool) -> Result<Vec<WITPlanInstr>> {
+    let mut plan = Vec::new();
+    if !crate::is_unit(iface, ty) {
+        let loc = if indirect { Loc::Mem(0) } else { Loc::Flat(0) };
+        build(iface, sizes, ty, loc, &mut plan, &mut Vec::new())?;
+    }
+    Ok(plan)
+}
+
+fn build(iface: &Interface, sizes: &Layout, ty: &Type, loc: Loc, plan: &mut Vec<WITPlanInstr>, stack: &mut Vec<TypeId>) -> Result<()> {
+    let ty = crate::resolve_alias(iface, ty);
+    let (memory, at) = match loc {
+        Loc::Flat(i) => (false, i),
+        Loc::Mem(off) => (true, off),
+    };
+    let index = plan.len();
+    plan.push(WITPlanInstr {
+        op:     WITPlanOp::Value,
+        ty:     crate::wit_type_of(iface, ty),
+        memory,
+        loc:    at,
+        size:   sizes.size(ty),
+        count:  0,
+        skip:   0,
+    });
+
+    let id = match ty {
+        Type::String => {
+            plan[index].op = WITPlanOp::String;
+            return Ok(());
+        },
+        Type::Id(id) => *id,
+        _ => return Ok(()),
+    };
+    if stack.contains(&id) {
+        return Err(anyhow!("Recursive types are not supported!"));
+    }
+    stack.push(id);
+    match &iface.types[id].kind {
+        TypeDefKind::Type(_) => unreachable!(),
+        TypeDefKind::Enum(_) |
+        TypeDefKind::Future(_) |
+        TypeDefKind::Stream(_) => {},
+        TypeDefKind::Flags(f) => {
+            plan[index].op = WITPlanOp::Flags;
+            plan[index].count = f.repr().count();
+        },
+        TypeDefKind::List(elem) => {
+            plan[index].op = WITPlanOp::List;
+            plan[index].size = crate::align_to(sizes.size(elem), sizes.align(elem));
+            plan[index].count = 1;
+            build(iface, sizes, elem, Loc::Mem(0), plan, stack)?;
+        },
+        TypeDefKind::Record(r) => {
+            plan[index].op = WITPlanOp::Record;
+            plan[index].count = r.fields.len();
+            build_fields(iface, sizes, r.fields.iter().map(|f| &f.ty).collect(), loc, plan, stack)?;
+        },
+        TypeDefKind::Tuple(t) => {
+            plan[index].op = WITPlanOp::Tuple;
+            plan[index].count = t.types.len();
+            build_fields(iface, sizes, t.types.iter().collect(), loc, plan, stack)?;
+        },
+        TypeDefKind::Variant(v) => {
+            build_cases(iface, sizes, v.tag(), v.cases.iter().map(|c| &c.ty).collect(), loc, plan, stack)?;
+        },
+        TypeDefKind::Union(u) => {
+            build_cases(iface, sizes, u.tag(), u.cases.iter().map(|c| &c.ty).collect(), loc, plan, stack)?;
+        },
+        TypeDefKind::Option(t) => {
+            build_cases(iface, sizes, Int::U8, vec![&Type::Unit, t], loc, plan, stack)?;
+        },
+        TypeDefKind::Expected(e) => {
+            build_cases(iface, sizes, Int::U8, vec![&e.ok, &e.err], loc, plan, stack)?;
+        },
+    }
+    stack.pop();
+    plan[index].skip = plan.len() - index - 1;
+    Ok(())
+}
+
+// Fields of records and tuples follow each other in both flat and memory
+// layouts.
+fn build_fields(iface: &Interface, sizes: &Layout, tys: Vec<&Type>, loc: Loc, plan: &mut Vec<WITPlanInstr>, stack: &mut Vec<TypeId>) -> Result<()> {
+    match loc {
+        Loc::Flat(mut i) => {
+            for ty in tys {
+                build(iface, sizes, ty, Loc::Flat(i), plan, stack)?;
+                i += flat_len(iface, sizes, ty);
+            }
+        },
+        Loc::Mem(base) => {
+            let offsets = sizes.field_offsets(tys.iter().copied());
+            for (ty, off) in tys.into_iter().zip(offsets) {
+                build(iface, sizes, ty, Loc::Mem(base + off), plan, stack)?;
+            }
+        },
+    }
+    Ok(())
+}
+
+// A variant's discriminant is at `loc` and is followed by one `Case`
+// instruction per case, each holding the plan for that case's payload.
+// Flat payloads all start right after the discriminant; a case's core
+// values may have been widened to fit the other cases, see `join`.
+fn build_cases(iface: &Interface, sizes: &Layout, tag: Int, cases: Vec<&Type>, loc: Loc, plan: &mut Vec<WITPlanInstr>, stack: &mut Vec<TypeId>) -> Result<()> {
+    let index = plan.len() - 1;
+    plan[index].op = WITPlanOp::Variant;
+    plan[index].count = cases.len();
+    let payload = match loc {
+        Loc::Flat(i) => Loc::Flat(i + 1),
+        Loc::Mem(base) => Loc::Mem(base + sizes.payload_offset(tag, cases.iter().copied())),
+    };
+    for (discr, ty) in cases.into_iter().enumerate() {
+        let case = plan.len();
+        plan.push(WITPlanInstr {
+            op:     WITPlanOp::Case,
+            ty:     crate::wit_type_of(iface, ty),
+            memory: matches!(payload, Loc::Mem(_)),
+            loc:    discr,
+            size:   0,
+            count:  1,
+            skip:   0,
+        });
+        build(iface, sizes, ty, payload, plan, stack)?;
+        plan[case].skip = plan.len() - case - 1;
+    }
+    Ok(())
+}
+
+fn flat_len(iface: &Interface, sizes: &Layout, ty: &Type) -> usize {
+    let mut flat = Vec::new();
+    crate::flatten(iface, ty, sizes.ptr_type(), &mut flat);
+    flat.len()
+}
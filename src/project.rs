@@ -0,0 +1,212 @@
+use anyhow::{anyhow, Result};
+use parser::{Case, Expected, Field, Function, FunctionKind, Interface, Record, ResourceId, Stream, Tuple, Type, TypeDefKind, TypeId, Union, UnionCase, Variant};
+use std::collections::HashMap;
+use crate::diff;
+
+// Builds a copy of `iface` with only the functions named in `names`, in
+// their original order, plus the types and resources they refer to,
+// directly or through other types.  Selecting a method or static also keeps
+// its resource.  Globals and `use`d interfaces are dropped; imported types
+// keep their foreign module.
+pub(crate) fn project(iface: &Interface, names: &[&str]) -> Result<Interface> {
+    for name in names {
+        if !iface.functions.iter().any(|f| f.name == *name) {
+            return Err(anyhow!("Function {} not found!", name));
+        }
+    }
+    let mut p = Projection::new(iface);
+    for f in iface.functions.iter().filter(|f| names.contains(&f.name.as_str())) {
+        p.function(f)?;
+    }
+    Ok(p.to)
+}
+
+// Combines two interfaces into one named after `a`: all of `a`'s types and
+// functions, then those of `b`.  Types, resources and functions defined in
+// both are kept once if they're the same, compared like wit_diff does, and
+// are an error otherwise.
+pub(crate) fn merge(a: &Interface, b: &Interface) -> Result<Interface> {
+    let mut p = Projection::new(a);
+    for &from in &[a, b] {
+        p.from = from;
+        p.types.clear();
+        p.resources.clear();
+        for (id, _) in from.types.iter() {
+            p.type_id(id)?;
+        }
+        for (id, _) in from.resources.iter() {
+            p.resource(id);
+        }
+        for f in &from.functions {
+            p.function(f)?;
+        }
+    }
+    Ok(p.to)
+}
+
+struct Projection<'a> {
+    from:      &'a Interface,
+    to:        Interface,
+    types:     HashMap<TypeId, TypeId>,  // from `from` to `to`
+    resources: HashMap<ResourceId, ResourceId>,
+}
+
+impl<'a> Projection<'a> {
+    fn new(from: &'a Interface) -> Projection<'a> {
+        let mut to = Interface::default();
+        to.name = from.name.clone();
+        to.module = from.module.clone();
+        Projection {
+            from,
+            to,
+            types:     HashMap::new(),
+            resources: HashMap::new(),
+        }
+    }
+
+    fn function(&mut self, f: &Function) -> Result<()> {
+        let mut f = f.clone();
+        f.kind = match f.kind {
+            FunctionKind::Freestanding => FunctionKind::Freestanding,
+            FunctionKind::Method { resource, name } => FunctionKind::Method { resource: self.resource(resource), name },
+            FunctionKind::Static { resource, name } => FunctionKind::Static { resource: self.resource(resource), name },
+        };
+        let mut params = Vec::new();
+        for (n, ty) in &f.params {
+            params.push((n.clone(), self.ty(ty)?));
+        }
+        f.params = params;
+        f.result = self.ty(&f.result)?;
+        if let Some(other) = self.to.functions.iter().find(|o| o.name == f.name) {
+            let to = &self.to;
+            let same = other.kind == f.kind
+                && other.params.len() == f.params.len()
+                && other.params.iter().zip(&f.params).all(|((on, ot), (n, t))| on == n && diff::same_type(to, ot, to, t))
+                && diff::same_type(to, &other.result, to, &f.result);
+            if !same {
+                return Err(anyhow!("Conflicting definitions of function {}", f.name));
+            }
+            return Ok(());
+        }
+        self.to.functions.push(f);
+        Ok(())
+    }
+
+    fn ty(&mut self, ty: &Type) -> Result<Type> {
+        Ok(match ty {
+            Type::Id(id) => Type::Id(self.type_id(*id)?),
+            Type::Handle(r) => Type::Handle(self.resource(*r)),
+            ty => ty.clone(),
+        })
+    }
+
+    // Copies the type after the types it refers to, so that dependencies
+    // come first as in a parsed interface.
+    fn type_id(&mut self, id: TypeId) -> Result<TypeId> {
+        if let Some(new) = self.types.get(&id) {
+            return Ok(*new);
+        }
+        let def = &self.from.types[id];
+        let kind = match &def.kind {
+            TypeDefKind::Type(t) => TypeDefKind::Type(self.ty(t)?),
+            TypeDefKind::List(t) => TypeDefKind::List(self.ty(t)?),
+            TypeDefKind::Option(t) => TypeDefKind::Option(self.ty(t)?),
+            TypeDefKind::Future(t) => TypeDefKind::Future(self.ty(t)?),
+            TypeDefKind::Tuple(t) => TypeDefKind::Tuple(Tuple {
+                types: t.types.iter().map(|t| self.ty(t)).collect::<Result<_>>()?,
+            }),
+            TypeDefKind::Record(r) => TypeDefKind::Record(Record {
+                fields: r.fields.iter()
+                    .map(|f| Ok(Field { docs: f.docs.clone(), name: f.name.clone(), ty: self.ty(&f.ty)? }))
+                    .collect::<Result<_>>()?,
+            }),
+            TypeDefKind::Variant(v) => TypeDefKind::Variant(Variant {
+                cases: v.cases.iter()
+                    .map(|c| Ok(Case { docs: c.docs.clone(), name: c.name.clone(), ty: self.ty(&c.ty)? }))
+                    .collect::<Result<_>>()?,
+            }),
+            TypeDefKind::Union(u) => TypeDefKind::Union(Union {
+                cases: u.cases.iter()
+                    .map(|c| Ok(UnionCase { docs: c.docs.clone(), ty: self.ty(&c.ty)? }))
+                    .collect::<Result<_>>()?,
+            }),
+            TypeDefKind::Expected(e) => TypeDefKind::Expected(Expected { ok: self.ty(&e.ok)?, err: self.ty(&e.err)? }),
+            TypeDefKind::Stream(s) => TypeDefKind::Stream(Stream { element: self.ty(&s.element)?, end: self.ty(&s.end)? }),
+            kind @ TypeDefKind::Flags(_) |
+            kind @ TypeDefKind::Enum(_) => kind.clone(),
+        };
+        let mut def = def.clone();
+        def.kind = kind;
+        let new = match &def.name {
+            Some(name) => match self.to.type_lookup.get(name) {
+                Some(other) => {
+                    if !diff::same_kind(&self.to, &self.to.types[*other].kind, &self.to, &def.kind) {
+                        return Err(anyhow!("Conflicting definitions of type {}", name));
+                    }
+                    *other
+                },
+                None => {
+                    let name = name.clone();
+                    let new = self.to.types.alloc(def);
+                    self.to.type_lookup.insert(name, new);
+                    new
+                },
+            },
+            None => self.to.types.alloc(def),
+        };
+        self.types.insert(id, new);
+        Ok(new)
+    }
+
+    fn resource(&mut self, id: ResourceId) -> ResourceId {
+        if let Some(new) = self.resources.get(&id) {
+            return *new;
+        }
+        let r = self.from.resources[id].clone();
+        let new = match self.to.resource_lookup.get(&r.name) {
+            Some(other) => *other,
+            None => {
+                let name = r.name.clone();
+                let new = self.to.resources.alloc(r);
+                self.to.resource_lookup.insert(name, new);
+                new
+            },
+        };
+        self.resources.insert(id, new);
+        new
+    }
+}
+
+// Renames the functions and named types of `iface` found in `renames`,
+// returning the functions' original names, indexed like `iface.functions`.
+pub(crate) fn rename(iface: &mut Interface, renames: &HashMap<String, String>) -> Result<Vec<String>> {
+    let originals: Vec<String> = iface.functions.iter().map(|f| f.name.clone()).collect();
+    if renames.is_empty() {
+        return Ok(originals);
+    }
+    for f in iface.functions.iter_mut() {
+        if let Some(name) = renames.get(&f.name) {
+            f.name = name.clone();
+        }
+    }
+    for (i, f) in iface.functions.iter().enumerate() {
+        if iface.functions[..i].iter().any(|o| o.name == f.name) {
+            return Err(anyhow!("Renaming to {} conflicts with another function", f.name));
+        }
+    }
+
+    let mut lookup = HashMap::new();
+    let ids: Vec<TypeId> = iface.types.iter().map(|(id, _)| id).collect();
+    for id in ids {
+        let def = &mut iface.types[id];
+        if let Some(name) = &def.name {
+            let name = renames.get(name).unwrap_or(name).clone();
+            if lookup.insert(name.clone(), id).is_some() {
+                return Err(anyhow!("Renaming to {} conflicts with another type", name));
+            }
+            def.name = Some(name);
+        }
+    }
+    iface.type_lookup = lookup;
+    Ok(originals)
+}
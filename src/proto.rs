@@ -0,0 +1,163 @@
+use parser::{Interface, Type, TypeDefKind, TypeId};
+use std::collections::HashSet;
+
+// Emits a proto3 schema for an interface's named types.  Records, tuples and
+// flags become messages (flags as one bool per flag), enums become enums,
+// and variants, unions and expecteds become messages with a `oneof`.  Lists
+// are `repeated` fields (`bytes` for list<u8>) and options are `optional`
+// fields.  Anonymous types that proto3 can't spell inline, e.g. tuples or
+// lists of lists, get a message named `Type<index>`; unit payloads use an
+// empty `Unit` message.
+struct Proto<'a> {
+    iface:   &'a Interface,
+    pending: Vec<TypeId>,
+    emitted: HashSet<TypeId>,
+    unit:    bool,
+}
+
+pub(crate) fn emit(iface: &Interface, package: &str) -> String {
+    let mut p = Proto {
+        iface,
+        pending: Vec::new(),
+        emitted: HashSet::new(),
+        unit:    false,
+    };
+    for (id, def) in iface.types.iter() {
+        if def.name.is_some() && !matches!(def.kind, TypeDefKind::Type(_) | TypeDefKind::List(_) | TypeDefKind::Option(_)) {
+            p.message(id);
+        }
+    }
+    let mut body = String::new();
+    while let Some(id) = p.pending.pop() {
+        body.push('\n');
+        body.push_str(&p.define(id));
+    }
+    let mut out = format!("syntax = \"proto3\";\n\npackage {};\n", package.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "_"));
+    if p.unit {
+        out.push_str("\nmessage Unit {}\n");
+    }
+    out + &body
+}
+
+impl<'a> Proto<'a> {
+    // Name of the message or enum for `id`, queuing its definition.
+    fn message(&mut self, id: TypeId) -> String {
+        if self.emitted.insert(id) {
+            self.pending.insert(0, id);
+        }
+        match &self.iface.types[id].name {
+            Some(name) => pascal(name),
+            None => format!("Type{}", id.index()),
+        }
+    }
+
+    // Returns the label (`repeated `, `optional ` or empty) and type of a
+    // field holding `ty`.
+    fn field(&mut self, ty: &Type) -> (&'static str, String) {
+        let ty = crate::resolve_alias(self.iface, ty);
+        let scalar = match ty {
+            Type::Unit => {
+                self.unit = true;
+                "Unit"
+            },
+            Type::Bool => "bool",
+            Type::U8 | Type::U16 | Type::U32 => "uint32",
+            Type::S8 | Type::S16 | Type::S32 => "int32",
+            Type::U64 => "uint64",
+            Type::S64 => "int64",
+            Type::Float32 => "float",
+            Type::Float64 => "double",
+            Type::Char | Type::String => "string",
+            Type::Handle(_) => "uint32",
+            Type::Id(id) => {
+                let id = *id;
+                return match &self.iface.types[id].kind {
+                    TypeDefKind::List(e) if matches!(crate::resolve_alias(self.iface, e), Type::U8) => ("", "bytes".to_string()),
+                    TypeDefKind::List(e) => match self.field(e) {
+                        ("", t) => ("repeated ", t),
+                        _ => ("", self.message(id)),
+                    },
+                    TypeDefKind::Option(t) => match self.field(t) {
+                        ("", t) => ("optional ", t),
+                        _ => ("", self.message(id)),
+                    },
+                    TypeDefKind::Future(_) |
+                    TypeDefKind::Stream(_) => ("", "uint32".to_string()),
+                    _ => ("", self.message(id)),
+                };
+            },
+        };
+        ("", scalar.to_string())
+    }
+
+    // Type of a field that can't carry a label, e.g. a `oneof` member.
+    fn single(&mut self, ty: &Type) -> String {
+        match (self.field(ty), crate::resolve_alias(self.iface, ty)) {
+            (("", t), _) => t,
+            (_, Type::Id(id)) => self.message(*id),
+            ((_, t), _) => t,
+        }
+    }
+
+    fn define(&mut self, id: TypeId) -> String {
+        let name = self.message(id);
+        let iface = self.iface;
+        let (fields, oneof): (Vec<(String, &Type)>, bool) = match &iface.types[id].kind {
+            TypeDefKind::Record(r) => (r.fields.iter().map(|f| (snake(&f.name), &f.ty)).collect(), false),
+            TypeDefKind::Tuple(t) => (t.types.iter().enumerate().map(|(i, t)| (format!("f{}", i), t)).collect(), false),
+            TypeDefKind::Flags(f) => (f.flags.iter().map(|f| (snake(&f.name), &Type::Bool)).collect(), false),
+            TypeDefKind::Variant(v) => (v.cases.iter().map(|c| (snake(&c.name), &c.ty)).collect(), true),
+            TypeDefKind::Union(u) => (u.cases.iter().enumerate().map(|(i, c)| (format!("c{}", i), &c.ty)).collect(), true),
+            TypeDefKind::Expected(e) => (vec![("ok".to_string(), &e.ok), ("err".to_string(), &e.err)], true),
+            TypeDefKind::Option(t) => (vec![("none".to_string(), &Type::Unit), ("some".to_string(), t)], true),
+            TypeDefKind::List(e) => {
+                return format!("message {} {{\n  repeated {} value = 1;\n}}\n", name, self.single(e));
+            },
+            TypeDefKind::Enum(e) => {
+                let prefix = snake(iface.types[id].name.as_deref().unwrap_or("")).to_ascii_uppercase();
+                let mut def = format!("enum {} {{\n", name);
+                for (i, case) in e.cases.iter().enumerate() {
+                    def.push_str(&format!("  {}_{} = {};\n", prefix, snake(&case.name).to_ascii_uppercase(), i));
+                }
+                return def + "}\n";
+            },
+            TypeDefKind::Type(_) |
+            TypeDefKind::Future(_) |
+            TypeDefKind::Stream(_) => unreachable!(),
+        };
+        let mut def = format!("message {} {{\n", name);
+        if oneof {
+            def.push_str("  oneof value {\n");
+            for (i, (n, ty)) in fields.into_iter().enumerate() {
+                def.push_str(&format!("    {} {} = {};\n", self.single(ty), n, i + 1));
+            }
+            def.push_str("  }\n");
+        } else {
+            // Unit fields are left out but keep their field number.
+            for (i, (n, ty)) in fields.into_iter().enumerate() {
+                if !crate::is_unit(iface, ty) {
+                    let (label, t) = self.field(ty);
+                    def.push_str(&format!("  {}{} {} = {};\n", label, t, n, i + 1));
+                }
+            }
+        }
+        def + "}\n"
+    }
+}
+
+fn pascal(name: &str) -> String {
+    name.trim_start_matches('%')
+        .split('-')
+        .map(|w| {
+            let mut c = w.chars();
+            match c.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + c.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn snake(name: &str) -> String {
+    name.trim_start_matches('%').replace('-', "_")
+}
@@ -0,0 +1,104 @@
+use parser::{Int, Interface, Type, TypeDefKind};
+use crate::layout::Layout;
+use crate::markdown::type_expr;
+
+// Renders a table of the size and alignment of every type in the interface,
+// in definition order, with the offset, size and alignment of each field of
+// records and tuples, and of the discriminant and each case's payload of
+// variants, unions, options and expecteds.  Lists show where their pointer
+// and length are.
+pub(crate) fn emit(iface: &Interface, layout: &Layout) -> String {
+    let mut rows = vec![(
+        "type".to_string(),
+        "size".to_string(),
+        "align".to_string(),
+        "offset".to_string(),
+    )];
+    let mut row = |label: String, ty: Option<&Type>, size: usize, align: usize, offset: Option<usize>| {
+        let label = match ty {
+            Some(ty) => format!("{}: {}", label, type_expr(iface, ty)),
+            None => label,
+        };
+        rows.push((label, size.to_string(), align.to_string(), offset.map(|o| o.to_string()).unwrap_or_default()));
+    };
+    let ptr_size = if layout.memory64() { 8 } else { 4 };
+
+    for (id, def) in iface.types.iter() {
+        let ty = Type::Id(id);
+        let label = match &def.name {
+            Some(name) => format!("{} ({})", name, kind_name(&def.kind)),
+            None => type_expr(iface, &ty),
+        };
+        row(label, None, layout.size(&ty), layout.align(&ty), None);
+        let fields: Vec<(String, &Type)> = match &def.kind {
+            TypeDefKind::Record(r) => r.fields.iter().map(|f| (format!("  .{}", f.name), &f.ty)).collect(),
+            TypeDefKind::Tuple(t) => t.types.iter().enumerate().map(|(i, t)| (format!("  .{}", i), t)).collect(),
+            _ => Vec::new(),
+        };
+        let offsets = layout.field_offsets(fields.iter().map(|(_, t)| *t));
+        for ((label, fty), off) in fields.into_iter().zip(offsets) {
+            row(label, Some(fty), layout.size(fty), layout.align(fty), Some(off));
+        }
+
+        let cases: Option<(Int, Vec<(String, &Type)>)> = match &def.kind {
+            TypeDefKind::Variant(v) => Some((v.tag(), v.cases.iter().map(|c| (c.name.clone(), &c.ty)).collect())),
+            TypeDefKind::Union(u) => Some((u.tag(), u.cases.iter().enumerate().map(|(i, c)| (i.to_string(), &c.ty)).collect())),
+            TypeDefKind::Option(t) => Some((Int::U8, vec![("none".to_string(), &Type::Unit), ("some".to_string(), t)])),
+            TypeDefKind::Expected(e) => Some((Int::U8, vec![("ok".to_string(), &e.ok), ("err".to_string(), &e.err)])),
+            TypeDefKind::Enum(e) => Some((e.tag(), Vec::new())),
+            _ => None,
+        };
+        if let Some((tag, cases)) = cases {
+            let tag_size = match tag {
+                Int::U8 => 1,
+                Int::U16 => 2,
+                Int::U32 => 4,
+                Int::U64 => 8,
+            };
+            row("  tag".to_string(), None, tag_size, tag_size, Some(0));
+            if !cases.is_empty() {
+                let payload = layout.payload_offset(tag, cases.iter().map(|(_, t)| *t));
+                for (name, cty) in cases {
+                    if !crate::is_unit(iface, cty) {
+                        row(format!("  {}", name), Some(cty), layout.size(cty), layout.align(cty), Some(payload));
+                    }
+                }
+            }
+        }
+
+        if let TypeDefKind::List(_) = &def.kind {
+            row("  ptr".to_string(), None, ptr_size, ptr_size, Some(0));
+            row("  len".to_string(), None, ptr_size, ptr_size, Some(ptr_size));
+        }
+    }
+
+    let width = |i: usize| rows.iter().map(|r| [&r.0, &r.1, &r.2, &r.3][i].len()).max().unwrap_or(0);
+    let (w0, w1, w2, w3) = (width(0), width(1), width(2), width(3));
+    let mut out = String::new();
+    for (i, (label, size, align, offset)) in rows.iter().enumerate() {
+        let line = format!("{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}", label, size, align, offset, w0 = w0, w1 = w1, w2 = w2, w3 = w3);
+        out.push_str(line.trim_end());
+        out.push('\n');
+        if i == 0 {
+            out.push_str(&format!("{}\n", "-".repeat(w0 + w1 + w2 + w3 + 6)));
+        }
+    }
+    out
+}
+
+fn kind_name(kind: &TypeDefKind) -> &'static str {
+    match kind {
+        TypeDefKind::Record(_) => "record",
+        TypeDefKind::Flags(_) => "flags",
+        TypeDefKind::Tuple(_) => "tuple",
+        TypeDefKind::Variant(_) => "variant",
+        TypeDefKind::Enum(_) => "enum",
+        TypeDefKind::Option(_) => "option",
+        TypeDefKind::Expected(_) => "expected",
+        TypeDefKind::Union(_) => "union",
+        TypeDefKind::List(_) => "list",
+        TypeDefKind::Future(_) => "future",
+        TypeDefKind::Stream(_) => "stream",
+        TypeDefKind::Type(_) => "alias",
+    }
+}
@@ -0,0 +1,122 @@
+use parser::{Interface, Type, TypeDefKind};
+use crate::json::Json;
+
+// Builds a JSON Schema (draft 2020-12) for values of type `ty` in their JSON
+// form: records are objects, tuples and lists arrays, flags arrays of the
+// names of the flags set, enums case names, and options `null` or the value.
+// Variants and expecteds are a case name alone for unit cases, or else an
+// object with the case name as its only key, e.g. `{"ok": 1}`.  Unions are
+// any of their members.  Handles, futures and streams are u32 indices.
+pub(crate) fn document(iface: &Interface, ty: &Type) -> Json {
+    let mut schema = vec![("$schema".to_string(), Json::str("https://json-schema.org/draft/2020-12/schema"))];
+    match schema_of(iface, ty) {
+        Json::Map(items) => schema.extend(items),
+        other => return other,
+    }
+    Json::Map(schema)
+}
+
+fn schema_of(iface: &Interface, ty: &Type) -> Json {
+    let id = match ty {
+        Type::Unit => return simple("null"),
+        Type::Bool => return simple("boolean"),
+        Type::U8 => return int(0, u8::MAX as i64),
+        Type::U16 => return int(0, u16::MAX as i64),
+        Type::U32 |
+        Type::Handle(_) => return int(0, u32::MAX as i64),
+        Type::U64 => return map(vec![("type", Json::str("integer")), ("minimum", Json::Int(0)), ("maximum", Json::Num(u64::MAX))]),
+        Type::S8 => return int(i8::MIN as i64, i8::MAX as i64),
+        Type::S16 => return int(i16::MIN as i64, i16::MAX as i64),
+        Type::S32 => return int(i32::MIN as i64, i32::MAX as i64),
+        Type::S64 => return int(i64::MIN, i64::MAX),
+        Type::Float32 |
+        Type::Float64 => return simple("number"),
+        Type::Char => return map(vec![("type", Json::str("string")), ("minLength", Json::Num(1)), ("maxLength", Json::Num(1))]),
+        Type::String => return simple("string"),
+        Type::Id(id) => *id,
+    };
+    let def = &iface.types[id];
+    let mut schema = match &def.kind {
+        TypeDefKind::Type(t) => match schema_of(iface, t) {
+            Json::Map(items) => items,
+            other => return other,
+        },
+        TypeDefKind::Record(r) => {
+            let props = r.fields.iter().map(|f| (f.name.clone(), schema_of(iface, &f.ty))).collect();
+            let required = r.fields.iter().map(|f| Json::str(&f.name)).collect();
+            pairs(vec![
+                ("type", Json::str("object")),
+                ("properties", Json::Map(props)),
+                ("required", Json::Arr(required)),
+                ("additionalProperties", Json::Bool(false)),
+            ])
+        },
+        TypeDefKind::Tuple(t) => pairs(vec![
+            ("type", Json::str("array")),
+            ("prefixItems", Json::Arr(t.types.iter().map(|t| schema_of(iface, t)).collect())),
+            ("minItems", Json::Num(t.types.len() as u64)),
+            ("maxItems", Json::Num(t.types.len() as u64)),
+        ]),
+        TypeDefKind::List(t) => pairs(vec![
+            ("type", Json::str("array")),
+            ("items", schema_of(iface, t)),
+        ]),
+        TypeDefKind::Flags(f) => pairs(vec![
+            ("type", Json::str("array")),
+            ("items", map(vec![("enum", Json::Arr(f.flags.iter().map(|f| Json::str(&f.name)).collect()))])),
+            ("uniqueItems", Json::Bool(true)),
+        ]),
+        TypeDefKind::Enum(e) => pairs(vec![
+            ("enum", Json::Arr(e.cases.iter().map(|c| Json::str(&c.name)).collect())),
+        ]),
+        TypeDefKind::Variant(v) => pairs(vec![
+            ("oneOf", Json::Arr(v.cases.iter().map(|c| case(iface, &c.name, &c.ty)).collect())),
+        ]),
+        TypeDefKind::Expected(e) => pairs(vec![
+            ("oneOf", Json::Arr(vec![case(iface, "ok", &e.ok), case(iface, "err", &e.err)])),
+        ]),
+        TypeDefKind::Union(u) => pairs(vec![
+            ("anyOf", Json::Arr(u.cases.iter().map(|c| schema_of(iface, &c.ty)).collect())),
+        ]),
+        TypeDefKind::Option(t) => pairs(vec![
+            ("anyOf", Json::Arr(vec![simple("null"), schema_of(iface, t)])),
+        ]),
+        TypeDefKind::Future(_) |
+        TypeDefKind::Stream(_) => return int(0, u32::MAX as i64),
+    };
+    if let Some(name) = &def.name {
+        schema.insert(0, ("title".to_string(), Json::str(name)));
+    }
+    if let Some(docs) = &def.docs.contents {
+        schema.push(("description".to_string(), Json::str(docs.trim())));
+    }
+    Json::Map(schema)
+}
+
+fn case(iface: &Interface, name: &str, ty: &Type) -> Json {
+    if crate::is_unit(iface, ty) {
+        return map(vec![("const", Json::str(name))]);
+    }
+    map(vec![
+        ("type", Json::str("object")),
+        ("properties", Json::Map(vec![(name.to_string(), schema_of(iface, ty))])),
+        ("required", Json::Arr(vec![Json::str(name)])),
+        ("additionalProperties", Json::Bool(false)),
+    ])
+}
+
+fn simple(ty: &str) -> Json {
+    map(vec![("type", Json::str(ty))])
+}
+
+fn int(min: i64, max: i64) -> Json {
+    map(vec![("type", Json::str("integer")), ("minimum", Json::Int(min)), ("maximum", Json::Int(max))])
+}
+
+fn map(items: Vec<(&str, Json)>) -> Json {
+    Json::Map(pairs(items))
+}
+
+fn pairs(items: Vec<(&str, Json)>) -> Vec<(String, Json)> {
+    items.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+}
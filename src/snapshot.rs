@@ -0,0 +1,623 @@
+use anyhow::{anyhow, Result};
+use parser::abi::{AbiVariant, WasmSignature, WasmType};
+use parser::{Case, Docs, EnumCase, Enum, Expected, Field, Flag, Flags, Function, FunctionKind, Global, Interface, Record, Resource, ResourceId, Stream, Tuple, Type, TypeDef, TypeDefKind, TypeId, Union, UnionCase, Variant};
+use std::sync::Arc;
+use std::str;
+use crate::layout::Layout;
+use crate::{FuncAbi, ParseOptions, WIT, WITStringEncoding};
+
+// A snapshot is a WIT as parsed, with the layout and signatures computed
+// from it, so that loading needn't parse it again; loading only checks the
+// layout and signatures against the definitions.  All integers are
+// little-endian u32s, strings are a length and UTF-8 bytes, and optional
+// strings are a presence byte and the string.  In order:
+//
+//   "WITS", version
+//   options: string encoding u8, memory64 u8, max flat params, results
+//   name, module (optional)
+//   resources: count, then name, docs, supertype, foreign module
+//   types: count, then name (optional), docs, foreign module, kind
+//   functions: count, then name, docs, async u8, kind, params, result
+//   globals: count, then name, docs, type
+//   layout: size and align of each type, indexed like the types
+//   functions again: export signature, import signature, export name,
+//     post-return (optional), indexed like the functions
+//
+// Types are a tag byte, followed by an index for handles and references to
+// other types.  Type ids and resource ids are the indices into their arenas,
+// which loading allocates in the same order.
+const MAGIC: &[u8; 4] = b"WITS";
+const VERSION: u32 = 1;
+
+pub(crate) fn save(wit: &WIT) -> Vec<u8> {
+    let mut w = Writer { out: Vec::new() };
+    let iface: &Interface = &wit.iface;
+    w.out.extend_from_slice(MAGIC);
+    w.u32(VERSION);
+    w.u8(match wit.string_encoding {
+        WITStringEncoding::Utf8 => 0,
+        WITStringEncoding::Utf16 => 1,
+        WITStringEncoding::Latin1Utf16 => 2,
+    });
+    w.u8(wit.align.memory64() as u8);
+    w.u32(wit.max_flat_params as u32);
+    w.u32(wit.max_flat_results as u32);
+    w.str(&iface.name);
+    w.opt_str(iface.module.as_deref());
+
+    w.u32(iface.resources.len() as u32);
+    for (_, r) in iface.resources.iter() {
+        w.str(&r.name);
+        w.docs(&r.docs);
+        w.opt_str(r.supertype.as_deref());
+        w.opt_str(r.foreign_module.as_deref());
+    }
+
+    w.u32(iface.types.len() as u32);
+    for (_, def) in iface.types.iter() {
+        w.opt_str(def.name.as_deref());
+        w.docs(&def.docs);
+        w.opt_str(def.foreign_module.as_deref());
+        w.kind(&def.kind);
+    }
+
+    w.u32(iface.functions.len() as u32);
+    for f in &iface.functions {
+        w.str(&f.name);
+        w.docs(&f.docs);
+        w.u8(f.is_async as u8);
+        match &f.kind {
+            FunctionKind::Freestanding => w.u8(0),
+            FunctionKind::Static { resource, name } => {
+                w.u8(1);
+                w.u32(resource.index() as u32);
+                w.str(name);
+            },
+            FunctionKind::Method { resource, name } => {
+                w.u8(2);
+                w.u32(resource.index() as u32);
+                w.str(name);
+            },
+        }
+        w.u32(f.params.len() as u32);
+        for (name, ty) in &f.params {
+            w.str(name);
+            w.ty(ty);
+        }
+        w.ty(&f.result);
+    }
+
+    w.u32(iface.globals.len() as u32);
+    for g in &iface.globals {
+        w.str(&g.name);
+        w.docs(&g.docs);
+        w.ty(&g.ty);
+    }
+
+    for (id, _) in iface.types.iter() {
+        w.u32(wit.align.size(&Type::Id(id)) as u32);
+        w.u32(wit.align.align(&Type::Id(id)) as u32);
+    }
+
+    for f in &iface.functions {
+        let func = &wit.funcs[&f.name];
+        w.sig(&func.sig.sig);
+        w.sig(&func.import_sig.sig);
+        w.str(func.export_name.to_str().unwrap_or(""));
+        w.opt_str(func.post_return.as_ref().and_then(|p| p.to_str().ok()));
+    }
+    w.out
+}
+
+struct Writer {
+    out: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, v: u8) {
+        self.out.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.out.extend_from_slice(s.as_bytes());
+    }
+
+    fn opt_str(&mut self, s: Option<&str>) {
+        match s {
+            Some(s) => {
+                self.u8(1);
+                self.str(s);
+            },
+            None => self.u8(0),
+        }
+    }
+
+    fn docs(&mut self, docs: &Docs) {
+        self.opt_str(docs.contents.as_deref());
+    }
+
+    fn ty(&mut self, ty: &Type) {
+        match ty {
+            Type::Unit => self.u8(0),
+            Type::Bool => self.u8(1),
+            Type::U8 => self.u8(2),
+            Type::U16 => self.u8(3),
+            Type::U32 => self.u8(4),
+            Type::U64 => self.u8(5),
+            Type::S8 => self.u8(6),
+            Type::S16 => self.u8(7),
+            Type::S32 => self.u8(8),
+            Type::S64 => self.u8(9),
+            Type::Float32 => self.u8(10),
+            Type::Float64 => self.u8(11),
+            Type::Char => self.u8(12),
+            Type::String => self.u8(13),
+            Type::Handle(r) => {
+                self.u8(14);
+                self.u32(r.index() as u32);
+            },
+            Type::Id(id) => {
+                self.u8(15);
+                self.u32(id.index() as u32);
+            },
+        }
+    }
+
+    // Member lists are a count followed by each member's name (absent for
+    // union cases), docs and type (absent for flags and enum cases).
+    fn kind(&mut self, kind: &TypeDefKind) {
+        match kind {
+            TypeDefKind::Record(r) => {
+                self.u8(0);
+                self.u32(r.fields.len() as u32);
+                for f in &r.fields {
+                    self.str(&f.name);
+                    self.docs(&f.docs);
+                    self.ty(&f.ty);
+                }
+            },
+            TypeDefKind::Flags(f) => {
+                self.u8(1);
+                self.u32(f.flags.len() as u32);
+                for f in &f.flags {
+                    self.str(&f.name);
+                    self.docs(&f.docs);
+                }
+            },
+            TypeDefKind::Tuple(t) => {
+                self.u8(2);
+                self.u32(t.types.len() as u32);
+                for t in &t.types {
+                    self.ty(t);
+                }
+            },
+            TypeDefKind::Variant(v) => {
+                self.u8(3);
+                self.u32(v.cases.len() as u32);
+                for c in &v.cases {
+                    self.str(&c.name);
+                    self.docs(&c.docs);
+                    self.ty(&c.ty);
+                }
+            },
+            TypeDefKind::Enum(e) => {
+                self.u8(4);
+                self.u32(e.cases.len() as u32);
+                for c in &e.cases {
+                    self.str(&c.name);
+                    self.docs(&c.docs);
+                }
+            },
+            TypeDefKind::Option(t) => {
+                self.u8(5);
+                self.ty(t);
+            },
+            TypeDefKind::Expected(e) => {
+                self.u8(6);
+                self.ty(&e.ok);
+                self.ty(&e.err);
+            },
+            TypeDefKind::Union(u) => {
+                self.u8(7);
+                self.u32(u.cases.len() as u32);
+                for c in &u.cases {
+                    self.docs(&c.docs);
+                    self.ty(&c.ty);
+                }
+            },
+            TypeDefKind::List(t) => {
+                self.u8(8);
+                self.ty(t);
+            },
+            TypeDefKind::Future(t) => {
+                self.u8(9);
+                self.ty(t);
+            },
+            TypeDefKind::Stream(s) => {
+                self.u8(10);
+                self.ty(&s.element);
+                self.ty(&s.end);
+            },
+            TypeDefKind::Type(t) => {
+                self.u8(11);
+                self.ty(t);
+            },
+        }
+    }
+
+    fn sig(&mut self, sig: &WasmSignature) {
+        let tys = |w: &mut Writer, tys: &[WasmType]| {
+            w.u32(tys.len() as u32);
+            for t in tys {
+                w.u8(match t {
+                    WasmType::I32 => 0,
+                    WasmType::I64 => 1,
+                    WasmType::F32 => 2,
+                    WasmType::F64 => 3,
+                });
+            }
+        };
+        tys(self, &sig.params);
+        tys(self, &sig.results);
+        self.u8(sig.indirect_params as u8);
+        self.u8(sig.retptr as u8);
+    }
+}
+
+// Rebuilds the WIT saved in `data` without parsing it or computing its
+// layout or signatures.  wit-parser's Interface owns its strings, so names
+// and docs are copied out of `data`, which needn't outlive the call.
+//
+// `data` is untrusted, so besides being read with bounds checks it's
+// rejected if a type contains or aliases itself, which everything walking
+// types would recurse on forever, or if the layout doesn't fit the types.
+pub(crate) fn load(data: &[u8]) -> Result<WIT> {
+    let mut r = Reader {
+        data,
+        pos:       0,
+        types:     Vec::new(),
+        resources: Vec::new(),
+    };
+    if r.bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        return Err(anyhow!("Not a WIT snapshot"));
+    }
+    let version = r.u32()?;
+    if version != VERSION {
+        return Err(anyhow!("Unsupported WIT snapshot version {}", version));
+    }
+    let string_encoding = match r.u8()? {
+        0 => WITStringEncoding::Utf8,
+        1 => WITStringEncoding::Utf16,
+        2 => WITStringEncoding::Latin1Utf16,
+        e => return Err(anyhow!("Invalid string encoding {} in snapshot", e)),
+    };
+    let opts = ParseOptions {
+        resolver: None,
+        string_encoding,
+        memory64: r.u8()? != 0,
+        max_flat_params: r.u32()? as usize,
+        max_flat_results: r.u32()? as usize,
+        renames: Arc::default(),
+        warnings: Arc::default(),
+        logger: None,
+    };
+    let mut iface = Interface::default();
+    iface.name = r.str()?;
+    iface.module = r.opt_str()?;
+
+    let count = r.u32()?;
+    for _ in 0..count {
+        let resource = Resource {
+            name: r.str()?,
+            docs: r.docs()?,
+            supertype: r.opt_str()?,
+            foreign_module: r.opt_str()?,
+        };
+        let name = resource.name.clone();
+        r.resources.push(iface.resources.alloc(resource));
+        iface.resource_lookup.insert(name, *r.resources.last().unwrap());
+    }
+
+    // Types may refer to types defined after them, so allocate them all
+    // before reading any.  Each takes at least 4 bytes.
+    let count = r.u32()?;
+    if count as usize > (data.len() - r.pos) / 4 {
+        return Err(anyhow!("Snapshot is truncated"));
+    }
+    for _ in 0..count {
+        r.types.push(iface.types.alloc(TypeDef {
+            docs: Docs::default(),
+            kind: TypeDefKind::Type(Type::Unit),
+            name: None,
+            foreign_module: None,
+        }));
+    }
+    for i in 0..r.types.len() {
+        let id = r.types[i];
+        let name = r.opt_str()?;
+        let docs = r.docs()?;
+        let foreign_module = r.opt_str()?;
+        let kind = r.kind()?;
+        if let Some(name) = &name {
+            iface.type_lookup.insert(name.clone(), id);
+        }
+        iface.types[id] = TypeDef { docs, kind, name, foreign_module };
+    }
+
+    let count = r.u32()?;
+    for _ in 0..count {
+        let name = r.str()?;
+        let docs = r.docs()?;
+        let is_async = r.u8()? != 0;
+        let kind = match r.u8()? {
+            0 => FunctionKind::Freestanding,
+            1 => FunctionKind::Static { resource: r.resource()?, name: r.str()? },
+            2 => FunctionKind::Method { resource: r.resource()?, name: r.str()? },
+            k => return Err(anyhow!("Invalid function kind {} in snapshot", k)),
+        };
+        let mut params = Vec::new();
+        for _ in 0..r.u32()? {
+            params.push((r.str()?, r.ty()?));
+        }
+        let result = r.ty()?;
+        iface.functions.push(Function { is_async, docs, name, kind, params, result });
+    }
+
+    let count = r.u32()?;
+    for _ in 0..count {
+        iface.globals.push(Global { name: r.str()?, docs: r.docs()?, ty: r.ty()? });
+    }
+
+    let mut table = Vec::new();
+    for _ in 0..r.types.len() {
+        table.push((r.u32()? as usize, r.u32()? as usize));
+    }
+    let mut abis = Vec::new();
+    for _ in 0..iface.functions.len() {
+        abis.push(FuncAbi {
+            sig: r.sig()?,
+            import_sig: r.sig()?,
+            export_name: r.str()?,
+            post_return: r.opt_str()?,
+        });
+    }
+    if r.pos != data.len() {
+        return Err(anyhow!("Unexpected data at the end of the snapshot"));
+    }
+
+    check_acyclic(&iface)?;
+    let iface = Arc::new(iface);
+    let align = Layout::with_table(&iface, opts.memory64, opts.string_encoding, table)?;
+    check_signatures(&iface, &align, &opts, &abis)?;
+    WIT::build(iface, align, &opts, abis)
+}
+
+// Fails if a function's signatures aren't the ones its params and result
+// give, as the rest of the library relies on them, e.g. on a return pointer
+// being the last param.
+fn check_signatures(iface: &Interface, align: &Layout, opts: &ParseOptions, abis: &[FuncAbi]) -> Result<()> {
+    let same = |a: &WasmSignature, b: &WasmSignature| {
+        a.params == b.params && a.results == b.results
+            && a.indirect_params == b.indirect_params && a.retptr == b.retptr
+    };
+    for (f, fabi) in iface.functions.iter().zip(abis) {
+        let sig = crate::wasm_signature(iface, align, opts, AbiVariant::GuestExport, f);
+        let import_sig = crate::wasm_signature(iface, align, opts, AbiVariant::GuestImport, f);
+        if !same(&fabi.sig, &sig) || !same(&fabi.import_sig, &import_sig) {
+            return Err(anyhow!("Signature of function {} doesn't match its definition", f.name));
+        }
+    }
+    Ok(())
+}
+
+// Fails if a type refers to itself, directly or through other types.  The
+// walk keeps its own stack, as the chain of references may be long.
+fn check_acyclic(iface: &Interface) -> Result<()> {
+    let mut done = vec![false; iface.types.len()];
+    let mut active = vec![false; iface.types.len()];
+    for (root, _) in iface.types.iter() {
+        if done[root.index()] {
+            continue;
+        }
+        active[root.index()] = true;
+        let mut stack = vec![(root, refs(&iface.types[root].kind))];
+        while let Some((id, pending)) = stack.last_mut() {
+            let id = *id;
+            match pending.pop() {
+                Some(child) if active[child.index()] => {
+                    return Err(anyhow!("Type {} refers to itself in snapshot", child.index()));
+                },
+                Some(child) if done[child.index()] => {},
+                Some(child) => {
+                    active[child.index()] = true;
+                    stack.push((child, refs(&iface.types[child].kind)));
+                },
+                None => {
+                    active[id.index()] = false;
+                    done[id.index()] = true;
+                    stack.pop();
+                },
+            }
+        }
+    }
+    Ok(())
+}
+
+// The types a type of `kind` refers to.
+fn refs(kind: &TypeDefKind) -> Vec<TypeId> {
+    let tys: Vec<&Type> = match kind {
+        TypeDefKind::Type(t) |
+        TypeDefKind::List(t) |
+        TypeDefKind::Option(t) |
+        TypeDefKind::Future(t) => vec![t],
+        TypeDefKind::Tuple(t) => t.types.iter().collect(),
+        TypeDefKind::Union(u) => u.cases.iter().map(|c| &c.ty).collect(),
+        TypeDefKind::Expected(e) => vec![&e.ok, &e.err],
+        TypeDefKind::Stream(s) => vec![&s.element, &s.end],
+        TypeDefKind::Record(r) => r.fields.iter().map(|f| &f.ty).collect(),
+        TypeDefKind::Variant(v) => v.cases.iter().map(|c| &c.ty).collect(),
+        TypeDefKind::Flags(_) |
+        TypeDefKind::Enum(_) => Vec::new(),
+    };
+    tys.into_iter().filter_map(|t| match t {
+        Type::Id(id) => Some(*id),
+        _ => None,
+    }).collect()
+}
+
+struct Reader<'a> {
+    data:      &'a [u8],
+    pos:       usize,
+    types:     Vec<TypeId>,  // by index in the snapshot
+    resources: Vec<ResourceId>,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| anyhow!("Snapshot is truncated"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn str(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        Ok(str::from_utf8(self.bytes(len)?)?.to_string())
+    }
+
+    fn opt_str(&mut self) -> Result<Option<String>> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.str()?)),
+        }
+    }
+
+    fn docs(&mut self) -> Result<Docs> {
+        Ok(Docs { contents: self.opt_str()? })
+    }
+
+    fn resource(&mut self) -> Result<ResourceId> {
+        let index = self.u32()? as usize;
+        self.resources.get(index).copied()
+            .ok_or_else(|| anyhow!("Resource index {} out of bounds in snapshot", index))
+    }
+
+    fn ty(&mut self) -> Result<Type> {
+        Ok(match self.u8()? {
+            0 => Type::Unit,
+            1 => Type::Bool,
+            2 => Type::U8,
+            3 => Type::U16,
+            4 => Type::U32,
+            5 => Type::U64,
+            6 => Type::S8,
+            7 => Type::S16,
+            8 => Type::S32,
+            9 => Type::S64,
+            10 => Type::Float32,
+            11 => Type::Float64,
+            12 => Type::Char,
+            13 => Type::String,
+            14 => Type::Handle(self.resource()?),
+            15 => {
+                let index = self.u32()? as usize;
+                Type::Id(*self.types.get(index)
+                    .ok_or_else(|| anyhow!("Type index {} out of bounds in snapshot", index))?)
+            },
+            t => return Err(anyhow!("Invalid type tag {} in snapshot", t)),
+        })
+    }
+
+    fn kind(&mut self) -> Result<TypeDefKind> {
+        Ok(match self.u8()? {
+            0 => {
+                let mut fields = Vec::new();
+                for _ in 0..self.u32()? {
+                    fields.push(Field { name: self.str()?, docs: self.docs()?, ty: self.ty()? });
+                }
+                TypeDefKind::Record(Record { fields })
+            },
+            1 => {
+                let mut flags = Vec::new();
+                for _ in 0..self.u32()? {
+                    flags.push(Flag { name: self.str()?, docs: self.docs()? });
+                }
+                TypeDefKind::Flags(Flags { flags })
+            },
+            2 => {
+                let mut types = Vec::new();
+                for _ in 0..self.u32()? {
+                    types.push(self.ty()?);
+                }
+                TypeDefKind::Tuple(Tuple { types })
+            },
+            3 => {
+                let mut cases = Vec::new();
+                for _ in 0..self.u32()? {
+                    cases.push(Case { name: self.str()?, docs: self.docs()?, ty: self.ty()? });
+                }
+                TypeDefKind::Variant(Variant { cases })
+            },
+            4 => {
+                let mut cases = Vec::new();
+                for _ in 0..self.u32()? {
+                    cases.push(EnumCase { name: self.str()?, docs: self.docs()? });
+                }
+                TypeDefKind::Enum(Enum { cases })
+            },
+            5 => TypeDefKind::Option(self.ty()?),
+            6 => TypeDefKind::Expected(Expected { ok: self.ty()?, err: self.ty()? }),
+            7 => {
+                let mut cases = Vec::new();
+                for _ in 0..self.u32()? {
+                    cases.push(UnionCase { docs: self.docs()?, ty: self.ty()? });
+                }
+                TypeDefKind::Union(Union { cases })
+            },
+            8 => TypeDefKind::List(self.ty()?),
+            9 => TypeDefKind::Future(self.ty()?),
+            10 => TypeDefKind::Stream(Stream { element: self.ty()?, end: self.ty()? }),
+            11 => TypeDefKind::Type(self.ty()?),
+            k => return Err(anyhow!("Invalid type kind {} in snapshot", k)),
+        })
+    }
+
+    fn sig(&mut self) -> Result<WasmSignature> {
+        let tys = |r: &mut Reader| -> Result<Vec<WasmType>> {
+            let mut tys = Vec::new();
+            for _ in 0..r.u32()? {
+                tys.push(match r.u8()? {
+                    0 => WasmType::I32,
+                    1 => WasmType::I64,
+                    2 => WasmType::F32,
+                    3 => WasmType::F64,
+                    t => return Err(anyhow!("Invalid core type {} in snapshot", t)),
+                });
+            }
+            Ok(tys)
+        };
+        Ok(WasmSignature {
+            params: tys(self)?,
+            results: tys(self)?,
+            indirect_params: self.u8()? != 0,
+            retptr: self.u8()? != 0,
+        })
+    }
+}
@@ -0,0 +1,978 @@
+// Session-less entry points, for simple single-threaded tools where passing
+// a session through every call is noise.  `wit_x_tl(args)` is
+// `wit_x(session, args)` with a session private to the calling thread, so
+// its options, warnings and error slot (see wit_error_get_tl) are per thread.
+//
+// Each call borrows the thread's session while it runs.  A `_tl` call made
+// while it's borrowed, i.e. from a resolver or logger callback of a `_tl`
+// parse, fails without touching it, returning false (or NULL from
+// wit_error_get_tl) with no error recorded; such callbacks can use a session
+// of their own instead.
+//
+// Generated from the entry points in lib.rs by tools/gen-tl.py; don't edit.
+
+use libc::{c_char, c_void};
+use std::cell::RefCell;
+use std::ptr;
+use crate::*;
+
+thread_local! {
+    static SESSION: RefCell<WITSession> = RefCell::new(WITSession {
+        error: None,
+        options: ParseOptions::default(),
+    });
+}
+
+// Calls `f` with the thread's session, or returns `busy` if it's in use
+// further up the stack (or already destroyed, as the thread exits).
+fn with_session<T: Copy>(busy: T, f: impl FnOnce(*mut WITSession) -> T) -> T {
+    SESSION.try_with(|s| match s.try_borrow_mut() {
+        Ok(mut s) => f(&mut *s),
+        Err(_) => busy,
+    }).unwrap_or(busy)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_error_get_tl() -> *const c_char {
+    with_session(ptr::null(), |s| wit_error_get(s))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_error_location_get_tl(res: *mut WITErrorLocation) -> bool {
+    with_session(false, |s| wit_error_location_get(s, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_error_clear_tl() {
+    with_session((), |s| wit_error_clear(s))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_session_set_resolver_tl(cb: Option<WITResolver>, user_data: *mut c_void) {
+    with_session((), |s| wit_session_set_resolver(s, cb, user_data))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_session_set_logger_tl(cb: Option<WITLogger>, user_data: *mut c_void, level: WITLogLevel) {
+    with_session((), |s| wit_session_set_logger(s, cb, user_data, level))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_session_set_string_encoding_tl(encoding: WITStringEncoding) {
+    with_session((), |s| wit_session_set_string_encoding(s, encoding))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_session_set_memory64_tl(memory64: bool) {
+    with_session((), |s| wit_session_set_memory64(s, memory64))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_session_set_max_flat_tl(max_params: usize, max_results: usize) {
+    with_session((), |s| wit_session_set_max_flat(s, max_params, max_results))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_session_set_rename_tl(from: *const c_char, to: *const c_char) -> bool {
+    with_session(false, |s| wit_session_set_rename(s, from, to))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_session_clear_renames_tl() {
+    with_session((), |s| wit_session_clear_renames(s))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_warning_count_get_tl(res: *mut usize) -> bool {
+    with_session(false, |s| wit_warning_count_get(s, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_warning_get_tl(index: usize, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_warning_get(s, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_parse_tl(content: *const u8, len: usize, res: *mut *mut WIT) -> bool {
+    with_session(false, |s| wit_parse(s, content, len, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_parse_named_tl(name: *const c_char, content: *const u8, len: usize, res: *mut *mut WIT) -> bool {
+    with_session(false, |s| wit_parse_named(s, name, content, len, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_parse_file_tl(path: *const c_char, res: *mut *mut WIT) -> bool {
+    with_session(false, |s| wit_parse_file(s, path, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_parse_all_tl(names: *const *const c_char, contents: *const *const u8, lens: *const usize, count: usize, res: *mut *mut WITCollection) -> bool {
+    with_session(false, |s| wit_parse_all(s, names, contents, lens, count, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_parse_module_tl(module: *const u8, len: usize, sections: *const *const c_char, count: usize, res: *mut *mut WITCollection) -> bool {
+    with_session(false, |s| wit_parse_module(s, module, len, sections, count, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_collection_count_get_tl(coll: *const WITCollection, res: *mut usize) -> bool {
+    with_session(false, |s| wit_collection_count_get(s, coll, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_collection_get_by_index_tl(coll: *const WITCollection, index: usize, res: *mut *const WIT) -> bool {
+    with_session(false, |s| wit_collection_get_by_index(s, coll, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_collection_delete_tl(coll: *mut WITCollection) {
+    wit_collection_delete(ptr::null_mut(), coll)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_delete_tl(wit: *mut WIT) {
+    wit_delete(ptr::null_mut(), wit)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_name_get_tl(wit: *const WIT, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_name_get(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_name_get_tl(func: *const WITFunction, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_func_name_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_index_get_tl(func: *const WITFunction, res: *mut usize) -> bool {
+    with_session(false, |s| wit_func_index_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_name_raw_get_tl(func: *const WITFunction, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_func_name_raw_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_core_export_name_get_tl(func: *const WITFunction, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_func_core_export_name_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_needs_post_return_tl(func: *const WITFunction, res: *mut bool) -> bool {
+    with_session(false, |s| wit_func_needs_post_return(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_needs_realloc_tl(func: *const WITFunction, res: *mut bool) -> bool {
+    with_session(false, |s| wit_func_needs_realloc(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_needs_realloc_tl(wit: *const WIT, res: *mut bool) -> bool {
+    with_session(false, |s| wit_needs_realloc(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_post_return_name_get_tl(func: *const WITFunction, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_func_post_return_name_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_docs_get_tl(func: *const WITFunction, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_func_docs_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_kind_get_tl(func: *const WITFunction, res: *mut WITFuncKind) -> bool {
+    with_session(false, |s| wit_func_kind_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_string_encoding_get_tl(wit: *const WIT, res: *mut WITStringEncoding) -> bool {
+    with_session(false, |s| wit_string_encoding_get(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_is_memory64_tl(wit: *const WIT, res: *mut bool) -> bool {
+    with_session(false, |s| wit_is_memory64(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_max_flat_params_get_tl(wit: *const WIT, res: *mut usize) -> bool {
+    with_session(false, |s| wit_max_flat_params_get(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_max_flat_results_get_tl(wit: *const WIT, res: *mut usize) -> bool {
+    with_session(false, |s| wit_max_flat_results_get(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_string_data_align_get_tl(wit: *const WIT, res: *mut usize) -> bool {
+    with_session(false, |s| wit_string_data_align_get(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_count_get_tl(wit: *const WIT, res: *mut usize) -> bool {
+    with_session(false, |s| wit_func_count_get(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_max_retptr_size_get_tl(wit: *const WIT, res: *mut usize) -> bool {
+    with_session(false, |s| wit_max_retptr_size_get(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_get_by_index_tl(wit: *const WIT, index: usize, res: *mut *const WITFunction) -> bool {
+    with_session(false, |s| wit_func_get_by_index(s, wit, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_walk_tl<'a>(wit: *const WIT, res: *mut *mut WITFuncIter<'a>) -> bool {
+    with_session(false, |s| wit_func_walk(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_iter_off_tl(iter: *const WITFuncIter) -> bool {
+    wit_func_iter_off(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_iter_next_tl(iter: *mut WITFuncIter) -> bool {
+    with_session(false, |s| wit_func_iter_next(s, iter))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_iter_at_tl(iter: *const WITFuncIter, res: *mut *const WITFunction) -> bool {
+    with_session(false, |s| wit_func_iter_at(s, iter, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_iter_delete_tl(iter: *mut WITFuncIter) {
+    wit_func_iter_delete(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_get_by_name_tl(wit: *const WIT, fname: *const c_char, res: *mut *const WITFunction) -> bool {
+    with_session(false, |s| wit_func_get_by_name(s, wit, fname, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_get_by_name_normalized_tl(wit: *const WIT, fname: *const c_char, res: *mut *const WITFunction) -> bool {
+    with_session(false, |s| wit_func_get_by_name_normalized(s, wit, fname, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_type_count_get_tl(wit: *const WIT, res: *mut usize) -> bool {
+    with_session(false, |s| wit_type_count_get(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_type_get_by_index_tl(wit: *const WIT, index: usize, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_type_get_by_index(s, wit, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_resource_count_get_tl(wit: *const WIT, res: *mut usize) -> bool {
+    with_session(false, |s| wit_resource_count_get(s, wit, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_resource_get_by_index_tl(wit: *const WIT, index: usize, res: *mut *const WITResource) -> bool {
+    with_session(false, |s| wit_resource_get_by_index(s, wit, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_resource_name_get_tl(r: *const WITResource, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_resource_name_get(s, r, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_resource_func_count_get_tl(r: *const WITResource, res: *mut usize) -> bool {
+    with_session(false, |s| wit_resource_func_count_get(s, r, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_resource_func_get_by_index_tl(r: *const WITResource, index: usize, res: *mut *const WITFunction) -> bool {
+    with_session(false, |s| wit_resource_func_get_by_index(s, r, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_walk_tl<'a>(func: *const WITFunction, res: *mut *mut WITTypeDefIter<'a>) -> bool {
+    with_session(false, |s| wit_func_param_walk(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_result_walk_tl<'a>(func: *const WITFunction, res: *mut *mut WITTypeDefIter<'a>) -> bool {
+    with_session(false, |s| wit_func_result_walk(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_result_count_get_tl(func: *const WITFunction, res: *mut usize) -> bool {
+    with_session(false, |s| wit_func_result_count_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_lift_plan_get_tl(func: *const WITFunction, res: *mut *const WITPlanInstr, len: *mut usize) -> bool {
+    with_session(false, |s| wit_func_lift_plan_get(s, func, res, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_cost_get_tl(func: *const WITFunction, res: *mut WITFuncCost) -> bool {
+    with_session(false, |s| wit_func_cost_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_count_get_tl(func: *const WITFunction, res: *mut usize) -> bool {
+    with_session(false, |s| wit_func_param_count_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_get_by_index_tl(func: *const WITFunction, index: usize, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_func_param_get_by_index(s, func, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_block_size_get_tl(func: *const WITFunction, res: *mut usize) -> bool {
+    with_session(false, |s| wit_func_param_block_size_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_block_align_get_tl(func: *const WITFunction, res: *mut usize) -> bool {
+    with_session(false, |s| wit_func_param_block_align_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_offset_get_tl(func: *const WITFunction, index: usize, res: *mut usize) -> bool {
+    with_session(false, |s| wit_func_param_offset_get(s, func, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_param_alloc_mask_get_tl(func: *const WITFunction, res: *mut u64) -> bool {
+    with_session(false, |s| wit_func_param_alloc_mask_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_core_param_map_get_tl(func: *const WITFunction, core_index: usize, param: *mut usize, leaf: *mut usize) -> bool {
+    with_session(false, |s| wit_func_core_param_map_get(s, func, core_index, param, leaf))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_result_get_tl(func: *const WITFunction, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_func_result_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_iter_off_tl(iter: *const WITTypeDefIter) -> bool {
+    wit_typedef_iter_off(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_iter_next_tl(iter: *mut WITTypeDefIter) -> bool {
+    with_session(false, |s| wit_typedef_iter_next(s, iter))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_iter_at_tl(iter: *const WITTypeDefIter, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_typedef_iter_at(s, iter, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_iter_delete_tl(iter: *mut WITTypeDefIter) {
+    wit_typedef_iter_delete(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_record_field_walk_tl<'a>(td: *const WITTypeDef, res: *mut *mut WITFieldIter<'a>) -> bool {
+    with_session(false, |s| wit_record_field_walk(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_record_field_count_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_record_field_count_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_record_field_get_by_index_tl(td: *const WITTypeDef, index: usize, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_record_field_get_by_index(s, td, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_field_iter_off_tl(iter: *const WITFieldIter) -> bool {
+    wit_field_iter_off(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_field_iter_next_tl(iter: *mut WITFieldIter) -> bool {
+    with_session(false, |s| wit_field_iter_next(s, iter))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_field_iter_at_tl<'a>(iter: *const WITFieldIter<'a>, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_field_iter_at(s, iter, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_field_iter_delete_tl(iter: *mut WITFieldIter) {
+    wit_field_iter_delete(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_variant_tag_get_tl(td: *const WITTypeDef, res: *mut u8) -> bool {
+    with_session(false, |s| wit_variant_tag_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_variant_payload_offset_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_variant_payload_offset_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_variant_max_payload_size_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_variant_max_payload_size_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_variant_case_walk_tl<'a>(td: *const WITTypeDef, res: *mut *mut WITCaseIter<'a>) -> bool {
+    with_session(false, |s| wit_variant_case_walk(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_variant_case_count_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_variant_case_count_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_variant_case_get_by_index_tl(td: *const WITTypeDef, index: usize, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_variant_case_get_by_index(s, td, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_case_iter_off_tl(iter: *const WITCaseIter) -> bool {
+    wit_case_iter_off(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_case_iter_next_tl(iter: *mut WITCaseIter) -> bool {
+    with_session(false, |s| wit_case_iter_next(s, iter))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_case_iter_at_tl<'a>(iter: *const WITCaseIter<'a>, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_case_iter_at(s, iter, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_case_iter_delete_tl(iter: *mut WITCaseIter) {
+    wit_case_iter_delete(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_case_discriminant_get_tl(td: *const WITTypeDef, res: *mut u32) -> bool {
+    with_session(false, |s| wit_case_discriminant_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_count_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_union_case_count_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_walk_tl<'a>(td: *const WITTypeDef, res: *mut *mut WITUnionCaseIter<'a>) -> bool {
+    with_session(false, |s| wit_union_case_walk(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_iter_off_tl(iter: *const WITUnionCaseIter) -> bool {
+    wit_union_case_iter_off(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_iter_next_tl(iter: *mut WITUnionCaseIter) -> bool {
+    with_session(false, |s| wit_union_case_iter_next(s, iter))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_iter_at_tl<'a>(iter: *const WITUnionCaseIter<'a>, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_union_case_iter_at(s, iter, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_union_case_iter_delete_tl(iter: *mut WITUnionCaseIter) {
+    wit_union_case_iter_delete(ptr::null_mut(), iter)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_expected_ok_typedef_get_tl(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_expected_ok_typedef_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_expected_err_typedef_get_tl(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_expected_err_typedef_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_expected_ok_offset_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_expected_ok_offset_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_expected_err_offset_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_expected_err_offset_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_flags_count_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_flags_count_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_flags_name_get_by_index_tl(td: *const WITTypeDef, index: usize, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_flags_name_get_by_index(s, td, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_flags_word_count_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_flags_word_count_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_enum_case_count_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_enum_case_count_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_enum_case_name_get_by_index_tl(td: *const WITTypeDef, index: usize, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_enum_case_name_get_by_index(s, td, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_tuple_elem_count_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_tuple_elem_count_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_tuple_elem_typedef_get_by_index_tl(td: *const WITTypeDef, index: usize, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_tuple_elem_typedef_get_by_index(s, td, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_tuple_elem_offset_get_by_index_tl(td: *const WITTypeDef, index: usize, res: *mut usize) -> bool {
+    with_session(false, |s| wit_tuple_elem_offset_get_by_index(s, td, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_option_typedef_get_tl(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_option_typedef_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_option_some_typedef_get_tl(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_option_some_typedef_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_option_payload_offset_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_option_payload_offset_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_type_aliased_typedef_get_tl(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_type_aliased_typedef_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_list_elem_typedef_get_tl(td: *const WITTypeDef, res: *mut *const WITTypeDef) -> bool {
+    with_session(false, |s| wit_list_elem_typedef_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_array_elem_stride_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_array_elem_stride_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_list_elem_layout_get_tl(td: *const WITTypeDef, offsets: *mut usize, cap: usize, len: *mut usize, stride: *mut usize) -> bool {
+    with_session(false, |s| wit_list_elem_layout_get(s, td, offsets, cap, len, stride))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_depth_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_typedef_depth_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_int_info_get_tl(td: *const WITTypeDef, res: *mut WITIntInfo) -> bool {
+    with_session(false, |s| wit_typedef_int_info_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_is_scalar_tl(td: *const WITTypeDef, res: *mut bool) -> bool {
+    with_session(false, |s| wit_typedef_is_scalar(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_name_get_tl(td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_typedef_name_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_name_raw_get_tl(td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_typedef_name_raw_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_docs_get_tl(td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_typedef_docs_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_typename_get_tl(td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_typedef_typename_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_handle_resource_name_get_tl(td: *const WITTypeDef, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_handle_resource_name_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_is_unit_tl(td: *const WITTypeDef, res: *mut bool) -> bool {
+    with_session(false, |s| wit_typedef_is_unit(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_contains_allocations_tl(td: *const WITTypeDef, res: *mut bool) -> bool {
+    with_session(false, |s| wit_typedef_contains_allocations(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_needs_bitcast_tl(td: *const WITTypeDef, res: *mut bool) -> bool {
+    with_session(false, |s| wit_typedef_needs_bitcast(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_needs_bitcast_tl(func: *const WITFunction, res: *mut bool) -> bool {
+    with_session(false, |s| wit_func_needs_bitcast(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_align_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_typedef_align_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_size_get_tl(td: *const WITTypeDef, res: *mut usize) -> bool {
+    with_session(false, |s| wit_typedef_size_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_type_get_tl(td: *const WITTypeDef, res: *mut WITType) -> bool {
+    with_session(false, |s| wit_typedef_type_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_despecialize_get_tl(td: *const WITTypeDef, res: *mut WITType) -> bool {
+    with_session(false, |s| wit_typedef_despecialize_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_fingerprint_get_tl(td: *const WITTypeDef, res: *mut u64) -> bool {
+    with_session(false, |s| wit_typedef_fingerprint_get(s, td, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_flatten_tl(td: *const WITTypeDef, out: *mut WASMType, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_typedef_flatten(s, td, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_layout_get_tl(td: *const WITTypeDef, out: *mut WITLayoutLeaf, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_typedef_layout_get(s, td, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_lower_tl(td: *const WITTypeDef, src: *const WITValueSource, mem: *const WITGuestMemory, addr: u64) -> bool {
+    with_session(false, |s| wit_typedef_lower(s, td, src, mem, addr))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_params_lower_tl(func: *const WITFunction, src: *const WITValueSource, mem: *const WITGuestMemory, addr: u64) -> bool {
+    with_session(false, |s| wit_func_params_lower(s, func, src, mem, addr))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_lift_flat_tl(td: *const WITTypeDef, values: *const u64, count: usize, mem: *const WITGuestMemory, sink: *const WITValueSink) -> bool {
+    with_session(false, |s| wit_typedef_lift_flat(s, td, values, count, mem, sink))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_lift_from_memory_tl(td: *const WITTypeDef, addr: u64, mem: *const WITGuestMemory, sink: *const WITValueSink) -> bool {
+    with_session(false, |s| wit_typedef_lift_from_memory(s, td, addr, mem, sink))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_results_lift_tl(func: *const WITFunction, values: *const u64, count: usize, mem: *const WITGuestMemory, sink: *const WITValueSink) -> bool {
+    with_session(false, |s| wit_func_results_lift(s, func, values, count, mem, sink))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_char_is_valid_tl(c: u32) -> bool {
+    wit_char_is_valid(ptr::null_mut(), c)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_char_to_utf8_tl(c: u32, out: *mut u8, len: *mut usize) -> bool {
+    with_session(false, |s| wit_char_to_utf8(s, c, out, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_char_from_utf8_tl(data: *const u8, len: usize, res: *mut u32, consumed: *mut usize) -> bool {
+    with_session(false, |s| wit_char_from_utf8(s, data, len, res, consumed))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_buffer_new_tl(res: *mut *mut WITBuffer) -> bool {
+    with_session(false, |s| wit_buffer_new(s, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_buffer_append_typed_tl(buf: *mut WITBuffer, td: *const WITTypeDef, data: *const u8, len: usize) -> bool {
+    with_session(false, |s| wit_buffer_append_typed(s, buf, td, data, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_buffer_offset_of_last_tl(buf: *const WITBuffer, res: *mut usize) -> bool {
+    with_session(false, |s| wit_buffer_offset_of_last(s, buf, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_buffer_finish_tl(buf: *mut WITBuffer, data: *mut *const u8, len: *mut usize, align: *mut usize) -> bool {
+    with_session(false, |s| wit_buffer_finish(s, buf, data, len, align))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_buffer_delete_tl(buf: *mut WITBuffer) {
+    wit_buffer_delete(ptr::null_mut(), buf)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_scalar_encode_tl(ty: WITType, bits: u64, out: *mut u8, len: *mut usize) -> bool {
+    with_session(false, |s| wit_scalar_encode(s, ty, bits, out, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_scalar_decode_tl(ty: WITType, data: *const u8, len: usize, bits: *mut u64) -> bool {
+    with_session(false, |s| wit_scalar_decode(s, ty, data, len, bits))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_ident_mangle_tl(name: *const c_char, style: WITIdentStyle, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_ident_mangle(s, name, style, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_ident_demangle_tl(name: *const c_char, style: WITIdentStyle, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_ident_demangle(s, name, style, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_emit_c_guest_tl(wit: *const WIT, file: WITGuestFile, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_emit_c_guest(s, wit, file, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_emit_c_header_tl(wit: *const WIT, options: *const WITHeaderOptions, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_emit_c_header(s, wit, options, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_to_json_tl(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_to_json(s, wit, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_abi_to_json_tl(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_abi_to_json(s, wit, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_to_modern_wit_tl(wit: *const WIT, package: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_to_modern_wit(s, wit, package, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_emit_create_function_tl(wit: *const WIT, module: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_emit_create_function(s, wit, module, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_emit_markdown_tl(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_emit_markdown(s, wit, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_emit_dot_tl(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_emit_dot(s, wit, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_emit_proto_tl(wit: *const WIT, package: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_emit_proto(s, wit, package, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_emit_go_tl(wit: *const WIT, package: *const c_char, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_emit_go(s, wit, package, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_to_json_schema_tl(td: *const WITTypeDef, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_typedef_to_json_schema(s, td, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_typedef_to_avro_tl(td: *const WITTypeDef, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_typedef_to_avro(s, td, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_emit_typescript_tl(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_emit_typescript(s, wit, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_diff_tl(old: *const WIT, newer: *const WIT, res: *mut *mut WITDiff) -> bool {
+    with_session(false, |s| wit_diff(s, old, newer, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_diff_count_get_tl(diff: *const WITDiff, res: *mut usize) -> bool {
+    with_session(false, |s| wit_diff_count_get(s, diff, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_diff_get_by_index_tl(diff: *const WITDiff, index: usize, res: *mut *const WITChange) -> bool {
+    with_session(false, |s| wit_diff_get_by_index(s, diff, index, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_diff_delete_tl(diff: *mut WITDiff) {
+    wit_diff_delete(ptr::null_mut(), diff)
+}
+
+#[no_mangle]
+pub extern "C" fn wit_change_kind_get_tl(change: *const WITChange, res: *mut WITChangeKind) -> bool {
+    with_session(false, |s| wit_change_kind_get(s, change, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_change_item_get_tl(change: *const WITChange, res: *mut WITItemKind) -> bool {
+    with_session(false, |s| wit_change_item_get(s, change, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_change_name_get_tl(change: *const WITChange, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_change_name_get(s, change, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_change_detail_get_tl(change: *const WITChange, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_change_detail_get(s, change, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_compat_check_tl(old: *const WIT, newer: *const WIT, res: *mut WITCompat) -> bool {
+    with_session(false, |s| wit_compat_check(s, old, newer, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_project_tl(wit: *const WIT, names: *const *const c_char, count: usize, res: *mut *mut WIT) -> bool {
+    with_session(false, |s| wit_project(s, wit, names, count, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_merge_tl(a: *const WIT, b: *const WIT, res: *mut *mut WIT) -> bool {
+    with_session(false, |s| wit_merge(s, a, b, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_layout_report_tl(wit: *const WIT, out: *mut c_char, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_layout_report(s, wit, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_walk_tl(wit: *const WIT, walker: *const WITWalker) -> bool {
+    with_session(false, |s| wit_walk(s, wit, walker))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_snapshot_save_tl(wit: *const WIT, out: *mut u8, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_snapshot_save(s, wit, out, cap, len))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_snapshot_load_tl(data: *const u8, len: usize, res: *mut *mut WIT) -> bool {
+    with_session(false, |s| wit_snapshot_load(s, data, len, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_snapshot_load_file_tl(path: *const c_char, res: *mut *mut WIT) -> bool {
+    with_session(false, |s| wit_snapshot_load_file(s, path, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_sig_get_tl(func: *const WITFunction, res: *mut *const WITSignature) -> bool {
+    with_session(false, |s| wit_func_sig_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_sig_get_by_variant_tl(func: *const WITFunction, variant: WITAbiVariant, res: *mut *const WITSignature) -> bool {
+    with_session(false, |s| wit_func_sig_get_by_variant(s, func, variant, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_core_sig_string_get_tl(func: *const WITFunction, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_func_core_sig_string_get(s, func, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_sig_string_get_tl(sig: *const WITSignature, res: *mut *const c_char) -> bool {
+    with_session(false, |s| wit_sig_string_get(s, sig, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_func_core_sig_check_tl(func: *const WITFunction, variant: WITAbiVariant, params: *const WASMType, nparams: usize, results: *const WASMType, nresults: usize) -> bool {
+    with_session(false, |s| wit_func_core_sig_check(s, func, variant, params, nparams, results, nresults))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_sig_is_indirect_tl(sig: *const WITSignature, part: WITSigPart, res: *mut bool) -> bool {
+    with_session(false, |s| wit_sig_is_indirect(s, sig, part, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_sig_length_get_tl(sig: *const WITSignature, part: WITSigPart, res: *mut usize) -> bool {
+    with_session(false, |s| wit_sig_length_get(s, sig, part, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_sig_retptr_param_get_tl(sig: *const WITSignature, present: *mut bool, index: *mut usize) -> bool {
+    with_session(false, |s| wit_sig_retptr_param_get(s, sig, present, index))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_sig_type_get_by_index_tl(sig: *const WITSignature, part: WITSigPart, idx: usize, res: *mut WASMType) -> bool {
+    with_session(false, |s| wit_sig_type_get_by_index(s, sig, part, idx, res))
+}
+
+#[no_mangle]
+pub extern "C" fn wit_sig_types_get_tl(sig: *const WITSignature, part: WITSigPart, out: *mut WASMType, cap: usize, len: *mut usize) -> bool {
+    with_session(false, |s| wit_sig_types_get(s, sig, part, out, cap, len))
+}
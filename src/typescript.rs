@@ -0,0 +1,174 @@
+use parser::{Docs, Function, FunctionKind, Interface, Type, TypeDefKind};
+
+// Emits TypeScript declarations for an interface, following the conventions
+// of JS component bindings: 64-bit integers are `bigint`, `list<u8>` is a
+// `Uint8Array`, options are `T | undefined`, and variants, unions and
+// expecteds are tagged objects `{ tag, val }`.  Type names are PascalCase
+// and function, param and field names camelCase.  Resources are classes
+// holding their methods and statics.
+pub(crate) fn emit(iface: &Interface) -> String {
+    let mut out = String::new();
+    for (_, def) in iface.types.iter() {
+        let name = match &def.name {
+            Some(n) => pascal(n),
+            None => continue,
+        };
+        docs(&def.docs, "", &mut out);
+        match &def.kind {
+            TypeDefKind::Record(r) => {
+                out.push_str(&format!("export interface {} {{\n", name));
+                for f in &r.fields {
+                    docs(&f.docs, "  ", &mut out);
+                    out.push_str(&format!("  {}: {},\n", camel(&f.name), type_expr(iface, &f.ty)));
+                }
+                out.push_str("}\n");
+            },
+            TypeDefKind::Flags(f) => {
+                out.push_str(&format!("export interface {} {{\n", name));
+                for flag in &f.flags {
+                    docs(&flag.docs, "  ", &mut out);
+                    out.push_str(&format!("  {}?: boolean,\n", camel(&flag.name)));
+                }
+                out.push_str("}\n");
+            },
+            TypeDefKind::Enum(e) => {
+                let cases: Vec<String> = e.cases.iter().map(|c| format!("'{}'", c.name)).collect();
+                out.push_str(&format!("export type {} = {};\n", name, cases.join(" | ")));
+            },
+            TypeDefKind::Variant(v) => {
+                let cases: Vec<String> = v.cases.iter().map(|c| format!("{}{}", name, pascal(&c.name))).collect();
+                out.push_str(&format!("export type {} = {};\n", name, cases.join(" | ")));
+                for (c, case_name) in v.cases.iter().zip(cases) {
+                    docs(&c.docs, "", &mut out);
+                    out.push_str(&format!("export interface {} {{\n  tag: '{}',\n", case_name, c.name));
+                    if !crate::is_unit(iface, &c.ty) {
+                        out.push_str(&format!("  val: {},\n", type_expr(iface, &c.ty)));
+                    }
+                    out.push_str("}\n");
+                }
+            },
+            _ => out.push_str(&format!("export type {} = {};\n", name, inline(iface, &def.kind))),
+        }
+    }
+    for (rid, r) in iface.resources.iter() {
+        docs(&r.docs, "", &mut out);
+        out.push_str(&format!("export class {} {{\n", pascal(&r.name)));
+        for f in &iface.functions {
+            match &f.kind {
+                FunctionKind::Method { resource, name } if *resource == rid => {
+                    let params = match f.params.first() {
+                        Some((p, _)) if p == "self" => &f.params[1..],
+                        _ => &f.params[..],
+                    };
+                    func(iface, f, name, params, "  ", "", &mut out);
+                },
+                FunctionKind::Static { resource, name } if *resource == rid => {
+                    func(iface, f, name, &f.params, "  ", "static ", &mut out);
+                },
+                _ => {},
+            }
+        }
+        out.push_str("}\n");
+    }
+    for f in &iface.functions {
+        if let FunctionKind::Freestanding = f.kind {
+            func(iface, f, &f.name, &f.params, "", "export function ", &mut out);
+        }
+    }
+    out
+}
+
+fn func(iface: &Interface, f: &Function, name: &str, params: &[(String, Type)], indent: &str, keyword: &str, out: &mut String) {
+    let params: Vec<String> = params.iter()
+        .map(|(n, ty)| format!("{}: {}", camel(n), type_expr(iface, ty)))
+        .collect();
+    let result = if crate::is_unit(iface, &f.result) {
+        "void".to_string()
+    } else {
+        type_expr(iface, &f.result)
+    };
+    docs(&f.docs, indent, out);
+    out.push_str(&format!("{}{}{}({}): {};\n", indent, keyword, camel(name), params.join(", "), result));
+}
+
+fn docs(docs: &Docs, indent: &str, out: &mut String) {
+    if let Some(text) = &docs.contents {
+        out.push_str(&format!("{}/**\n", indent));
+        for line in text.trim_end().lines() {
+            out.push_str(&format!("{} * {}\n", indent, line.trim()));
+        }
+        out.push_str(&format!("{} */\n", indent));
+    }
+}
+
+fn type_expr(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Unit => "undefined".to_string(),
+        Type::Bool => "boolean".to_string(),
+        Type::U64 | Type::S64 => "bigint".to_string(),
+        Type::U8 | Type::U16 | Type::U32 |
+        Type::S8 | Type::S16 | Type::S32 |
+        Type::Float32 | Type::Float64 => "number".to_string(),
+        Type::Char | Type::String => "string".to_string(),
+        Type::Handle(r) => pascal(&iface.resources[*r].name),
+        Type::Id(id) => match &iface.types[*id].name {
+            Some(n) => pascal(n),
+            None => inline(iface, &iface.types[*id].kind),
+        },
+    }
+}
+
+// Spells types that aren't declared as interfaces.
+fn inline(iface: &Interface, kind: &TypeDefKind) -> String {
+    let tagged = |cases: Vec<(String, &Type)>| {
+        cases.into_iter()
+            .map(|(tag, ty)| if crate::is_unit(iface, ty) {
+                format!("{{ tag: {} }}", tag)
+            } else {
+                format!("{{ tag: {}, val: {} }}", tag, type_expr(iface, ty))
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    };
+    match kind {
+        TypeDefKind::Type(t) => type_expr(iface, t),
+        TypeDefKind::List(t) if matches!(crate::resolve_alias(iface, t), Type::U8) => "Uint8Array".to_string(),
+        TypeDefKind::List(t) => format!("Array<{}>", type_expr(iface, t)),
+        TypeDefKind::Option(t) => format!("{} | undefined", type_expr(iface, t)),
+        TypeDefKind::Tuple(t) => {
+            let types: Vec<String> = t.types.iter().map(|t| type_expr(iface, t)).collect();
+            format!("[{}]", types.join(", "))
+        },
+        TypeDefKind::Union(u) => tagged(u.cases.iter().enumerate().map(|(i, c)| (i.to_string(), &c.ty)).collect()),
+        TypeDefKind::Expected(e) => tagged(vec![("'ok'".to_string(), &e.ok), ("'err'".to_string(), &e.err)]),
+        TypeDefKind::Future(_) |
+        TypeDefKind::Stream(_) => "number".to_string(),
+        // Always named, and declared above.
+        TypeDefKind::Record(_) |
+        TypeDefKind::Flags(_) |
+        TypeDefKind::Enum(_) |
+        TypeDefKind::Variant(_) => "unknown".to_string(),
+    }
+}
+
+fn pascal(name: &str) -> String {
+    name.trim_start_matches('%')
+        .split('-')
+        .map(|w| {
+            let mut c = w.chars();
+            match c.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + c.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn camel(name: &str) -> String {
+    let p = pascal(name);
+    let mut c = p.chars();
+    match c.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + c.as_str(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,95 @@
+#!/usr/bin/env python3
+#
+# Writes src/tl.rs, the `_tl` variant of every entry point in src/lib.rs that
+# takes a session, to stdout.  Run by `make src/tl.rs`, so the two can't
+# drift apart.  cbindgen doesn't expand macros, which is why the wrappers
+# are generated as source rather than by a macro.
+
+import re
+import sys
+
+PREAMBLE = '''\
+// Session-less entry points, for simple single-threaded tools where passing
+// a session through every call is noise.  `wit_x_tl(args)` is
+// `wit_x(session, args)` with a session private to the calling thread, so
+// its options, warnings and error slot (see wit_error_get_tl) are per thread.
+//
+// Each call borrows the thread's session while it runs.  A `_tl` call made
+// while it's borrowed, i.e. from a resolver or logger callback of a `_tl`
+// parse, fails without touching it, returning false (or NULL from
+// wit_error_get_tl) with no error recorded; such callbacks can use a session
+// of their own instead.
+//
+// Generated from the entry points in lib.rs by tools/gen-tl.py; don't edit.
+
+use libc::{c_char, c_void};
+use std::cell::RefCell;
+use std::ptr;
+use crate::*;
+
+thread_local! {
+    static SESSION: RefCell<WITSession> = RefCell::new(WITSession {
+        error: None,
+        options: ParseOptions::default(),
+    });
+}
+
+// Calls `f` with the thread's session, or returns `busy` if it's in use
+// further up the stack (or already destroyed, as the thread exits).
+fn with_session<T: Copy>(busy: T, f: impl FnOnce(*mut WITSession) -> T) -> T {
+    SESSION.try_with(|s| match s.try_borrow_mut() {
+        Ok(mut s) => f(&mut *s),
+        Err(_) => busy,
+    }).unwrap_or(busy)
+}
+'''
+
+# Entry points that manage the session itself.
+SKIP = {'wit_session_new', 'wit_session_delete'}
+
+# What a call returns when the thread's session is in use.
+BUSY = {'': '()', 'bool': 'false', '*const c_char': 'ptr::null()'}
+
+ENTRY = re.compile(r'#\[no_mangle\]\npub extern "C" fn (\w+)(<[^>]*>)?\((_?s): \*(?:const|mut) WITSession(?:, )?(.*)\)(?: -> (.+))? \{')
+
+
+def params(args):
+    # Splits on the commas between params, not those inside generics.
+    res, depth, cur = [], 0, ''
+    for c in args:
+        depth += (c == '<') - (c == '>')
+        if c == ',' and depth == 0:
+            res.append(cur.strip())
+            cur = ''
+        else:
+            cur += c
+    if cur.strip():
+        res.append(cur.strip())
+    return res
+
+
+def wrapper(m):
+    name, generics, session, args, ret = m.groups()
+    # A lifetime the params don't use would only draw a lint.
+    generics = generics if generics and generics[1:-1] in args else ''
+    ret = ret or ''
+    names = [p.split(':')[0] for p in params(args)]
+    sig = 'pub extern "C" fn {}_tl{}({}){}'.format(name, generics, args, ' -> ' + ret if ret else '')
+    if session == '_s':
+        body = '{}({})'.format(name, ', '.join(['ptr::null_mut()'] + names))
+    else:
+        body = 'with_session({}, |s| {}({}))'.format(BUSY[ret], name, ', '.join(['s'] + names))
+    return '#[no_mangle]\n{} {{\n    {}\n}}\n'.format(sig, body)
+
+
+def main():
+    with open(sys.argv[1] if len(sys.argv) > 1 else 'src/lib.rs') as f:
+        lib = f.read()
+    out = [PREAMBLE]
+    for m in ENTRY.finditer(lib):
+        if m.group(1) not in SKIP:
+            out.append(wrapper(m))
+    sys.stdout.write('\n'.join(out))
+
+
+main()